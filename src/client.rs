@@ -0,0 +1,600 @@
+use crate::config::{self, Settings};
+use crate::connection::RetryPolicy;
+use crate::env::{self, ENVX_PRIVATE_KEY};
+use crate::error::{Error, Result};
+use crate::key_provider::{KeyProvider, SecretKey};
+use crate::known_keys::{trusted_public_keys, KnownKeys, MemberKey};
+use crate::models::{Project, Variable};
+use crate::signing::{verify_record, Record, Signer};
+use chrono::Utc;
+use pgp::composed::message::Message;
+use pgp::{crypto, Deserializable, SignedPublicKey};
+use rand::prelude::*;
+use reqwest::{header, Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::Arc;
+use url::Url;
+
+/// The server `envx` talks to unless told otherwise
+pub const DEFAULT_URL: &str = "https://api.env-cli.com";
+
+/// Proves the client holds the key of a user, sent as a bearer token
+/// serialized as JSON
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuthToken {
+    /// Server user id
+    pub token: String,
+    /// Armored signed message of the current time
+    pub signature: String,
+}
+
+impl AuthToken {
+    pub fn new(token: String, signature: String) -> Self {
+        Self { token, signature }
+    }
+}
+
+impl From<AuthToken> for String {
+    fn from(auth_token: AuthToken) -> String {
+        auth_token.to_string()
+    }
+}
+
+impl Display for AuthToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string_token =
+            serde_json::to_string(&self).map_err(|_| std::fmt::Error)?;
+
+        write!(f, "{}", string_token)
+    }
+}
+
+/// A variable as the server stores it, encrypted to every project member
+#[derive(Serialize, Deserialize, Debug)]
+struct EncryptedVariable {
+    id: String,
+    value: String,
+    project_id: String,
+    created_at: String,
+}
+
+/// Builds a [`Client`]
+///
+/// Only the key is required. The user id is looked up in the envx config
+/// when [`from_config`](Self::from_config) is used.
+#[derive(Default)]
+pub struct ClientBuilder {
+    url: Option<String>,
+    user_id: Option<String>,
+    key: Option<Arc<dyn KeyProvider>>,
+    http: Option<reqwest::Client>,
+    retry: Option<RetryPolicy>,
+    known_keys: Option<KnownKeys>,
+    own_keys: Vec<String>,
+    config_dir: Option<PathBuf>,
+    context: Option<String>,
+    config: Option<Settings>,
+}
+
+impl ClientBuilder {
+    /// URL of the server, defaults to [`DEFAULT_URL`]
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Server user id of the key, as shown by `envx get keys`
+    pub fn user_id(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    /// Key to decrypt variables and sign requests with
+    pub fn key(mut self, key: impl KeyProvider + 'static) -> Self {
+        self.key = Some(Arc::new(key));
+        self
+    }
+
+    /// HTTP client to send requests with, instead of one built from the
+    /// connection settings of the context
+    pub fn http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// How to retry failed requests, defaults to the `retry_attempts` and
+    /// `retry_backoff` settings of the config
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Pinned member keys to verify signatures against, defaults to the
+    /// known_keys.json of the config. Without pins only signatures by
+    /// [`own_keys`](Self::own_keys) are [`Signer::Verified`].
+    pub fn known_keys(mut self, known_keys: KnownKeys) -> Self {
+        self.known_keys = Some(known_keys);
+        self
+    }
+
+    /// Fingerprints of keys that are trusted without being pinned, in
+    /// addition to the key of the client and the keys in the config
+    pub fn own_keys<I, S>(mut self, fingerprints: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.own_keys
+            .extend(fingerprints.into_iter().map(Into::into));
+        self
+    }
+
+    /// Directory to read config.json and known_keys.json from, instead of
+    /// the one `envx` uses. Set it before calling
+    /// [`from_config`](Self::from_config).
+    pub fn config_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config_dir = Some(dir.into());
+        self
+    }
+
+    /// Server context to use instead of the one `ENVX_CONTEXT` or
+    /// `envx context use` selects. Set it before calling
+    /// [`from_config`](Self::from_config).
+    pub fn context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Fill in whatever isn't set explicitly the way `envx` would: the
+    /// server and connection settings of the current context,
+    /// `ENVX_API_URL`, `ENVX_USER_ID`, the user id of the key from the
+    /// config, the retry settings, the pinned keys, and the key in
+    /// `ENVX_PRIVATE_KEY` if no key is given
+    pub fn from_config(mut self) -> Result<Self> {
+        self.config =
+            Some(config::load(self.config_dir.clone(), self.context.clone())?);
+        Ok(self)
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let from_config = self.config.is_some();
+        let config = self.config.unwrap_or_default();
+        let key = match self.key {
+            Some(key) => key,
+            None if from_config && env::var(ENVX_PRIVATE_KEY).is_some() => {
+                Arc::new(SecretKey::from_env()?)
+            }
+            None => return Err(Error::Config("No key given".into())),
+        };
+
+        let url = self
+            .url
+            .or_else(|| config.url.clone())
+            .unwrap_or_else(|| DEFAULT_URL.into());
+        let url = Url::parse(&url).map_err(|e| {
+            Error::Config(format!("Invalid server URL '{}': {}", url, e))
+        })?;
+
+        let fingerprint = key.fingerprint();
+        let user_id = self
+            .user_id
+            .or_else(|| config.user_id_of(&fingerprint))
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "No user id for key {}, set one with \
+                     ClientBuilder::user_id or ENVX_USER_ID",
+                    fingerprint
+                ))
+            })?;
+
+        let http = match self.http {
+            Some(http) => http,
+            None => config
+                .connection
+                .apply(reqwest::Client::builder())?
+                .build()?,
+        };
+
+        let mut own_keys = self.own_keys;
+        own_keys.extend(config.own_keys);
+        own_keys.push(fingerprint);
+
+        Ok(Client {
+            http,
+            url,
+            user_id,
+            key,
+            retry: self.retry.unwrap_or(config.retry),
+            known_keys: Arc::new(self.known_keys.unwrap_or(config.known_keys)),
+            own_keys: Arc::new(own_keys),
+        })
+    }
+}
+
+/// Reads and writes projects and variables on an envx server
+///
+/// Cheap to clone, clones share the key and the connection pool.
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    url: Url,
+    user_id: String,
+    key: Arc<dyn KeyProvider>,
+    retry: RetryPolicy,
+    known_keys: Arc<KnownKeys>,
+    own_keys: Arc<Vec<String>>,
+}
+
+impl Client {
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// Server user id of the key
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// Ids of the projects the user is a member of
+    pub async fn projects(&self) -> Result<Vec<String>> {
+        self.get("projects").await
+    }
+
+    pub async fn project(&self, project_id: &str) -> Result<Project> {
+        self.get(&format!("project/{}", project_id)).await
+    }
+
+    /// The members of a project with the status of their keys against the
+    /// pinned ones
+    pub async fn members(&self, project_id: &str) -> Result<Vec<MemberKey>> {
+        let project = self.project(project_id).await?;
+        self.known_keys
+            .check(project_id, &project.users, &self.own_keys)
+    }
+
+    /// Create a project, returning its id
+    pub async fn new_project(&self) -> Result<String> {
+        let req = self.request(Method::POST, "projects/new")?;
+        Ok(self.send(req).await?.text().await?)
+    }
+
+    pub async fn delete_project(&self, project_id: &str) -> Result<()> {
+        let path = format!("project/{}", project_id);
+        self.send(self.request(Method::DELETE, &path)?).await?;
+        Ok(())
+    }
+
+    /// Make a user a member of a project
+    ///
+    /// The variables aren't encrypted to the new member until they are
+    /// re-encrypted, see [`rekey_variables`](Self::rekey_variables).
+    pub async fn add_user(
+        &self,
+        project_id: &str,
+        user_id: &str,
+    ) -> Result<()> {
+        let path = format!("project/{}/add-user", project_id);
+        let req = self
+            .request(Method::POST, &path)?
+            .json(&json!({ "user_id": user_id }));
+        self.send(req).await?;
+        Ok(())
+    }
+
+    pub async fn remove_users(
+        &self,
+        project_id: &str,
+        user_ids: &[String],
+    ) -> Result<()> {
+        let path = format!("project/{}/remove-user", project_id);
+        let req = self
+            .request(Method::POST, &path)?
+            .json(&json!({ "users": user_ids }));
+        self.send(req).await?;
+        Ok(())
+    }
+
+    /// The armored public key of a user
+    pub async fn public_key(&self, user_id: &str) -> Result<String> {
+        #[derive(Deserialize)]
+        struct UserKey {
+            public_key: String,
+        }
+
+        let user: UserKey = self.get(&format!("user/{}", user_id)).await?;
+        Ok(user.public_key)
+    }
+
+    /// Publish a revoked public key of the user so the server stops
+    /// accepting it
+    pub async fn revoke_key(&self, revoked_public_key: &str) -> Result<()> {
+        let path = format!("user/{}/revoke", self.user_id);
+        let req = self
+            .request(Method::POST, &path)?
+            .json(&json!({ "public_key": revoked_public_key }));
+        self.send(req).await?;
+        Ok(())
+    }
+
+    /// Delete the user of the key from the server
+    pub async fn delete_user(&self) -> Result<()> {
+        let path = format!("user/{}", self.user_id);
+        self.send(self.request(Method::DELETE, &path)?).await?;
+        Ok(())
+    }
+
+    /// The latest value of every variable of a project, sorted by name
+    ///
+    /// Each variable carries the project member who signed it, see
+    /// [`Variable::signer`].
+    pub async fn variables(&self, project_id: &str) -> Result<Vec<Variable>> {
+        let mut latest: BTreeMap<String, Variable> = BTreeMap::new();
+        for variable in self.history(project_id).await? {
+            match latest.get(&variable.name) {
+                Some(newer) if newer.created_at >= variable.created_at => {}
+                _ => {
+                    latest.insert(variable.name.clone(), variable);
+                }
+            }
+        }
+
+        Ok(latest.into_values().collect())
+    }
+
+    /// Every version of every variable of a project, in the order the
+    /// server returns them
+    pub async fn history(&self, project_id: &str) -> Result<Vec<Variable>> {
+        let encrypted: Vec<EncryptedVariable> = self
+            .get(&format!("project/{}/variables", project_id))
+            .await?;
+        if encrypted.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let members = self.members(project_id).await?;
+        let messages = self.decrypt(&encrypted)?;
+
+        encrypted
+            .into_iter()
+            .zip(messages)
+            .map(|(variable, message)| {
+                let signer = verify_record(&message, project_id, &members);
+                parse(variable, &message, signer)
+            })
+            .collect()
+    }
+
+    /// The latest value of one variable, `None` if the project doesn't
+    /// have it
+    pub async fn variable(
+        &self,
+        project_id: &str,
+        name: &str,
+    ) -> Result<Option<Variable>> {
+        Ok(self
+            .variables(project_id)
+            .await?
+            .into_iter()
+            .find(|v| v.name == name))
+    }
+
+    /// The variables of a project by name, ready to pass to
+    /// [`std::process::Command::envs`]
+    pub async fn env(
+        &self,
+        project_id: &str,
+    ) -> Result<BTreeMap<String, String>> {
+        Ok(self
+            .variables(project_id)
+            .await?
+            .into_iter()
+            .map(|v| (v.name, v.value))
+            .collect())
+    }
+
+    /// Every version of every variable in all projects of the user
+    ///
+    /// Signatures aren't checked, the signer of each is
+    /// [`Signer::Unchecked`].
+    pub async fn user_variables(&self) -> Result<Vec<Variable>> {
+        let encrypted: Vec<EncryptedVariable> = self
+            .get(&format!("user/{}/variables", self.user_id))
+            .await?;
+        if encrypted.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let messages = self.decrypt(&encrypted)?;
+        encrypted
+            .into_iter()
+            .zip(messages)
+            .map(|(variable, message)| {
+                parse(variable, &message, Signer::Unchecked)
+            })
+            .collect()
+    }
+
+    /// Sign the variables and encrypt them to every project member,
+    /// returning the ids of the new versions
+    ///
+    /// Fails with [`Error::Untrusted`] if the key of any member isn't
+    /// pinned or our own.
+    pub async fn set_variables(
+        &self,
+        project_id: &str,
+        variables: &[(String, String)],
+    ) -> Result<Vec<String>> {
+        let recipients =
+            trusted_public_keys(project_id, self.members(project_id).await?)?;
+
+        let records = variables
+            .iter()
+            .map(|(key, value)| {
+                serde_json::to_string(&Record {
+                    key: key.clone(),
+                    value: value.clone(),
+                    project_id: Some(project_id.to_string()),
+                })
+                .map_err(|e| Error::Key(e.to_string()))
+            })
+            .collect::<Result<Vec<String>>>()?;
+
+        let messages = self
+            .key
+            .sign_many(&records)?
+            .iter()
+            .map(|message| encrypt(message, &recipients))
+            .collect::<Result<Vec<String>>>()?;
+
+        #[derive(Deserialize)]
+        struct Created {
+            id: String,
+        }
+
+        let req =
+            self.request(Method::POST, "variables/set-many")?
+                .json(&json!({
+                    "project_id": project_id,
+                    "variables": messages,
+                }));
+        let created: Vec<Created> = self.send(req).await?.json().await?;
+
+        Ok(created.into_iter().map(|c| c.id).collect())
+    }
+
+    /// Re-encrypt all variables of a project to the given recipients,
+    /// returning the ids of the updated variables
+    ///
+    /// The signed records are re-encrypted as they are, so the authors of
+    /// the variables are kept.
+    pub async fn rekey_variables(
+        &self,
+        project_id: &str,
+        recipients: &[SignedPublicKey],
+    ) -> Result<Vec<String>> {
+        let mut encrypted: Vec<EncryptedVariable> = self
+            .get(&format!("project/{}/variables", project_id))
+            .await?;
+        if !encrypted.is_empty() {
+            let messages = self.decrypt(&encrypted)?;
+            for (variable, message) in encrypted.iter_mut().zip(messages) {
+                variable.value = encrypt(&message, recipients)?;
+            }
+        }
+
+        let req = self
+            .request(Method::POST, "variables/update-many")?
+            .json(&json!({ "variables": encrypted }));
+        Ok(self.send(req).await?.json().await?)
+    }
+
+    pub async fn delete_variable(&self, variable_id: &str) -> Result<()> {
+        let path = format!("variables/{}", variable_id);
+        self.send(self.request(Method::DELETE, &path)?).await?;
+        Ok(())
+    }
+
+    /// Decrypt the variables in one go, returning the signed records
+    fn decrypt(&self, variables: &[EncryptedVariable]) -> Result<Vec<Message>> {
+        let messages = variables
+            .iter()
+            .map(|variable| {
+                Message::from_string(&variable.value)
+                    .map(|(message, _)| message)
+                    .map_err(|e| Error::Decrypt {
+                        id: variable.id.clone(),
+                        message: e.to_string(),
+                    })
+            })
+            .collect::<Result<Vec<Message>>>()?;
+
+        self.key.decrypt_many(&messages)
+    }
+
+    fn auth_header(&self) -> Result<String> {
+        let signature = self.key.sign(&Utc::now().to_string())?;
+        let auth_token = AuthToken::new(self.user_id.clone(), signature);
+        Ok(format!("Bearer {}", auth_token))
+    }
+
+    /// An authenticated request to `path`, relative to the server URL
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let url = self.url.join(path).map_err(|e| {
+            Error::Config(format!("Invalid path '{}': {}", path, e))
+        })?;
+
+        Ok(self
+            .http
+            .request(method, url)
+            .header(header::AUTHORIZATION, self.auth_header()?))
+    }
+
+    /// Send a request with retries, failing if the server refuses it
+    async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        let res = self.retry.send(req).await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            return Err(Error::Api {
+                status: status.as_u16(),
+                message: res.text().await.unwrap_or_default(),
+            });
+        }
+
+        Ok(res)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let res = self.send(self.request(Method::GET, path)?).await?;
+        Ok(res.json::<T>().await?)
+    }
+}
+
+/// The variable in a decrypted record
+fn parse(
+    variable: EncryptedVariable,
+    message: &Message,
+    signer: Signer,
+) -> Result<Variable> {
+    let error = |message: String| Error::Decrypt {
+        id: variable.id.clone(),
+        message,
+    };
+
+    let content = message
+        .get_content()
+        .map_err(|e| error(e.to_string()))?
+        .ok_or_else(|| error("The message is empty".into()))?;
+    let record: Record =
+        serde_json::from_slice(&content).map_err(|e| error(e.to_string()))?;
+
+    Ok(Variable {
+        id: variable.id,
+        project_id: variable.project_id,
+        name: record.key,
+        value: record.value,
+        created_at: variable.created_at,
+        signer,
+    })
+}
+
+/// Encrypt a (signed) record to every recipient, armored
+fn encrypt(
+    message: &Message,
+    recipients: &[SignedPublicKey],
+) -> Result<String> {
+    let recipients = recipients.iter().collect::<Vec<&SignedPublicKey>>();
+    message
+        .encrypt_to_keys(
+            &mut StdRng::from_entropy(),
+            crypto::sym::SymmetricKeyAlgorithm::AES128,
+            &recipients,
+        )
+        .and_then(|encrypted| encrypted.to_armored_string(None))
+        .map_err(|e| Error::Key(format!("Failed to encrypt: {}", e)))
+}
@@ -0,0 +1,31 @@
+use super::*;
+use crate::info;
+use crate::utils::{
+    agent::{expect_ok, request, Request},
+    config::get_config,
+};
+
+/// Lock a key in the agent, or all keys
+#[derive(Parser)]
+pub struct Args {
+    /// Partial fingerprint of the key to lock, locks all keys if not given
+    #[clap(short, long)]
+    key: Option<String>,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let fingerprint = match args.key {
+        Some(key) => Some(get_config()?.get_key(&key)?.fingerprint),
+        None => None,
+    };
+
+    expect_ok(request(&Request::Lock {
+        fingerprint: fingerprint.clone(),
+    })?)?;
+
+    match fingerprint {
+        Some(fingerprint) => info!("Locked {}", fingerprint),
+        None => info!("Locked all keys"),
+    }
+    Ok(())
+}
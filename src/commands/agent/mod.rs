@@ -0,0 +1,28 @@
+#[allow(unused_imports)]
+pub(super) use anyhow::{anyhow, Context, Result};
+pub(super) use clap::Parser;
+#[allow(unused_imports)]
+pub(super) use colored::Colorize;
+
+use crate::commands_enum;
+use clap::Subcommand;
+
+pub mod lock;
+pub mod start;
+pub mod status;
+pub mod stop;
+pub mod unlock;
+
+/// Keep unlocked keys in memory. (start, stop, status, unlock, lock)
+#[derive(Parser)]
+pub struct Args {
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+commands_enum!(start, stop, status, unlock, lock);
+
+pub async fn command(args: Args) -> Result<()> {
+    Commands::exec(args).await?;
+    Ok(())
+}
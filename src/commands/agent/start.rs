@@ -0,0 +1,98 @@
+use super::*;
+use crate::info;
+use crate::utils::{
+    agent::{self, socket_path},
+    config::get_config,
+    dirs,
+    duration::{format_duration, parse_duration},
+    env::ENVX_HOME,
+};
+use anyhow::bail;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Start the agent in the background
+#[derive(Parser)]
+pub struct Args {
+    /// Lock keys after this long without use, e.g. `30m` (default: the
+    /// `agent_idle_timeout` setting)
+    #[clap(long)]
+    idle_timeout: Option<String>,
+
+    /// Run in the foreground instead of detaching
+    #[clap(long)]
+    foreground: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let idle_timeout = match &args.idle_timeout {
+        Some(timeout) => parse_duration(timeout)?,
+        None => get_config()?.get_settings()?.agent_idle_timeout()?,
+    };
+
+    if agent::is_running() {
+        bail!(
+            "The agent is already running on {}",
+            socket_path()?.display()
+        );
+    }
+
+    if args.foreground {
+        return serve(idle_timeout).await;
+    }
+
+    let mut child = Command::new(std::env::current_exe()?);
+    child
+        .args(["agent", "start", "--foreground", "--idle-timeout"])
+        .arg(format!("{}s", idle_timeout.as_secs()))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(home) = dirs::home_override() {
+        child.env(ENVX_HOME, home);
+    }
+    detach(&mut child);
+    child.spawn().context("Failed to start the agent")?;
+
+    for _ in 0..50 {
+        if agent::is_running() {
+            info!(
+                "Agent started on {} (keys lock after {} unused)",
+                socket_path()?.display(),
+                format_duration(idle_timeout)
+            );
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    bail!("The agent did not start, try `envx agent start --foreground`")
+}
+
+#[cfg(unix)]
+async fn serve(idle_timeout: Duration) -> Result<()> {
+    agent::serve(idle_timeout).await
+}
+
+#[cfg(not(unix))]
+async fn serve(_idle_timeout: Duration) -> Result<()> {
+    bail!("envx agent is only supported on Unix")
+}
+
+/// Start a background process in its own session so it outlives the
+/// terminal, used for the agent and by `envx copy`
+#[cfg(unix)]
+pub(crate) fn detach(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    // SAFETY: setsid is async-signal-safe and only affects the child
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn detach(_command: &mut Command) {}
@@ -0,0 +1,60 @@
+use super::*;
+use crate::utils::{
+    agent::{request, socket_path, Request, Response},
+    duration::format_duration,
+    output,
+    settings::OutputFormat,
+};
+use anyhow::bail;
+use std::time::Duration;
+
+/// Show whether the agent is running and which keys it holds
+#[derive(Parser)]
+pub struct Args {
+    /// Same as `--output json`
+    #[clap(long)]
+    json: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    output::json_if(args.json);
+    let status = match request(&Request::Status)? {
+        Response::Status(status) => status,
+        other => bail!("Unexpected response from the agent: {:?}", other),
+    };
+
+    output::print(&status, |format| {
+        if format == OutputFormat::Plain {
+            for key in &status.keys {
+                println!("{}\t{}", key.fingerprint, key.locks_in);
+            }
+            return Ok(());
+        }
+
+        println!(
+            "Agent running on {} (pid {})",
+            socket_path()?.display(),
+            status.pid
+        );
+        println!(
+            "Keys lock after {} unused",
+            format_duration(Duration::from_secs(status.idle_timeout))
+        );
+
+        if status.keys.is_empty() {
+            println!("No keys unlocked");
+        }
+        for key in &status.keys {
+            println!(
+                "  {} {}",
+                key.fingerprint,
+                format!(
+                    "(locks in {})",
+                    format_duration(Duration::from_secs(key.locks_in))
+                )
+                .dimmed()
+            );
+        }
+        Ok(())
+    })
+}
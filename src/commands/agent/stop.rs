@@ -0,0 +1,13 @@
+use super::*;
+use crate::info;
+use crate::utils::agent::{expect_ok, request, Request};
+
+/// Stop the agent, forgetting all keys
+#[derive(Parser)]
+pub struct Args {}
+
+pub async fn command(_args: Args) -> Result<()> {
+    expect_ok(request(&Request::Stop)?)?;
+    info!("Agent stopped");
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use super::*;
+use crate::info;
+use crate::interact_or;
+use crate::utils::{
+    agent::{expect_ok, request, Request},
+    config::get_config,
+    env::{self, ENVX_PASSPHRASE},
+    prompt::prompt_password,
+};
+
+/// Unlock a key in the agent
+#[derive(Parser)]
+pub struct Args {
+    /// Partial fingerprint of the key to unlock, defaults to the primary key
+    #[clap(short, long)]
+    key: Option<String>,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let config = get_config()?;
+    let key = config.get_key_or_default(args.key)?;
+
+    let passphrase = match env::var(ENVX_PASSPHRASE) {
+        Some(passphrase) => passphrase,
+        None => {
+            interact_or!("No passphrase given, set {}", ENVX_PASSPHRASE);
+            prompt_password(&format!("Passphrase for {}", key))?
+        }
+    };
+
+    expect_ok(request(&Request::Unlock {
+        fingerprint: key.fingerprint.clone(),
+        passphrase,
+    })?)?;
+
+    info!("{}", format!("Unlocked {}", key.fingerprint).green());
+    Ok(())
+}
@@ -1,7 +1,13 @@
 use super::*;
+use crate::info;
 use crate::{
     sdk::get_api_url,
-    utils::{auth::get_token, config::get_config},
+    utils::{
+        auth::get_token,
+        config::get_config,
+        http::{http_client, SendWithRetry},
+        output,
+    },
 };
 use anyhow::bail;
 use reqwest::header;
@@ -18,7 +24,7 @@ pub async fn command(args: Args) -> anyhow::Result<()> {
     let config = get_config()?;
     let key = config.get_key_or_default(args.key)?;
 
-    let client = reqwest::Client::new();
+    let client = http_client()?;
 
     let uuid = key
         .uuid
@@ -28,27 +34,24 @@ pub async fn command(args: Args) -> anyhow::Result<()> {
         .await
         .context("Failed to get token")?;
 
-    println!("auth token:\n{}", auth_token.signature);
+    info!("auth token:\n{}", auth_token.signature);
 
     let res = client
-        .post(format!("{}/test-auth", get_api_url()))
+        .post(get_api_url()?.join("test-auth")?)
         .header(header::AUTHORIZATION, format!("Bearer {}", auth_token))
-        .send()
+        .send_with_retry()
         .await?;
 
     let status = res.status();
 
-    if status.is_success() {
-        println!("success");
-        // print the text response
-
-        let text = res.text().await?;
-
-        println!("{}", text);
-    } else {
-        println!("status: {}", status);
-        bail!("failed to auth")
+    if !status.is_success() {
+        bail!("failed to auth (status: {})", status)
     }
 
-    Ok(())
+    info!("success");
+    let text = res.text().await?;
+    output::print(&serde_json::json!({ "response": text }), |_| {
+        println!("{}", text);
+        Ok(())
+    })
 }
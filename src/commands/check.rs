@@ -0,0 +1,128 @@
+use super::*;
+use crate::info;
+use crate::{
+    sdk::SDK,
+    utils::{
+        choice::Choice,
+        config::get_config,
+        output,
+        project_file::{ProjectFile, PROJECT_FILE_NAME},
+        schema::VariableType,
+    },
+};
+use anyhow::bail;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Validate the variables of a project against the schema in .envx.toml
+#[derive(Parser)]
+pub struct Args {
+    /// Partial fingerprint of the key to use
+    #[clap(short, long)]
+    key: Option<String>,
+
+    /// Project ID
+    #[clap(short, long)]
+    project_id: Option<String>,
+
+    /// Same as `--output json`
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct CheckResult {
+    key: String,
+    #[serde(rename = "type")]
+    kind: VariableType,
+    required: bool,
+    secret: bool,
+    set: bool,
+    problem: Option<String>,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    output::json_if(args.json);
+    let Some(project_file) = ProjectFile::find()? else {
+        bail!(
+            "No {} found in this directory or its parents",
+            PROJECT_FILE_NAME
+        );
+    };
+
+    let config = get_config()?;
+    let key = config.get_key_or_default(args.key)?;
+    let project_id =
+        Choice::try_project(args.project_id, &key.fingerprint).await?;
+
+    let variables =
+        SDK::get_variables_pruned(&project_id, &key.fingerprint).await?;
+    let values = variables
+        .iter()
+        .map(|v| (v.key.as_str(), v.value.as_str()))
+        .collect::<BTreeMap<&str, &str>>();
+
+    let mut results = vec![];
+    for (name, schema) in project_file.schema() {
+        let value = values.get(name.as_str()).copied();
+        results.push(CheckResult {
+            problem: schema.validate(value)?,
+            set: value.is_some(),
+            key: name,
+            kind: schema.kind,
+            required: schema.required,
+            secret: schema.secret,
+        });
+    }
+
+    let failed = results.iter().filter(|r| r.problem.is_some()).count();
+
+    output::print(&results, |_| {
+        let schema = project_file.schema();
+        for result in &results {
+            let mut details = vec![result.kind.to_string()];
+            if !result.required {
+                details.push("optional".into());
+            }
+            if result.secret {
+                details.push("secret".into());
+            }
+            let details = format!("({})", details.join(", ")).dimmed();
+
+            match &result.problem {
+                None if !result.set => {
+                    println!("{} {} {}", "-".dimmed(), result.key, details)
+                }
+                None => println!("{} {} {}", "✓".green(), result.key, details),
+                Some(problem) => println!(
+                    "{} {} {} {}",
+                    "✗".red(),
+                    result.key,
+                    details,
+                    problem.red()
+                ),
+            }
+
+            let description = schema
+                .get(&result.key)
+                .and_then(|s| s.description.as_deref());
+            if let Some(description) = description {
+                println!("    {}", description.dimmed());
+            }
+        }
+        Ok(())
+    })?;
+
+    if failed > 0 {
+        bail!(
+            "{} of {} variables don't match the schema in {}",
+            failed,
+            results.len(),
+            project_file.path.display()
+        );
+    }
+
+    info!("{}", format!("Project {} is valid", project_id).green());
+
+    Ok(())
+}
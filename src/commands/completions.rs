@@ -0,0 +1,426 @@
+use super::*;
+use crate::utils::{
+    choice::Choice, completion::Cache, config::get_config, plugin,
+    project_file::ProjectFile, prompt,
+};
+use anyhow::bail;
+use clap::{Arg, Command, CommandFactory};
+use clap_complete::Shell;
+use indoc::indoc;
+use std::collections::BTreeMap;
+
+/// Print the completion script of a shell
+///
+/// Load it from the shell's startup file, e.g. `source <(envx completions
+/// bash)` or `envx completions fish | source`. Key fingerprints, project
+/// ids, contexts and variable names are completed too, the last two from
+/// what envx has fetched before.
+#[derive(Parser)]
+pub struct Args {
+    #[clap(value_enum)]
+    shell: Shell,
+}
+
+/// The hidden command the scripts run as `envx __complete <index> <words>`,
+/// where `index` is the position of the word being completed. It prints
+/// one `candidate<TAB>description` per line.
+pub const COMPLETE: &str = "__complete";
+
+pub async fn command(args: Args) -> Result<()> {
+    let script = match args.shell {
+        Shell::Bash => BASH,
+        Shell::Zsh => ZSH,
+        Shell::Fish => FISH,
+        Shell::PowerShell => POWERSHELL,
+        Shell::Elvish => ELVISH,
+        shell => bail!("Completions for {} are not supported", shell),
+    };
+    print!("{}", script);
+    Ok(())
+}
+
+/// Entry point of `envx __complete`, which must never prompt or fail loudly
+pub fn complete(args: &[String]) -> Result<()> {
+    prompt::set_non_interactive();
+
+    let (index, words) = args.split_first().context("Missing word index")?;
+    let index = index
+        .parse::<usize>()
+        .context("Invalid word index")?
+        .min(words.len());
+    let current = words.get(index).map(String::as_str).unwrap_or("");
+    let before = words.get(1..index).unwrap_or_default();
+
+    for (value, description) in candidates(before, current) {
+        if value.starts_with(current) {
+            let description = description.lines().next().unwrap_or("");
+            println!("{}\t{}", value, description.replace('\t', " "));
+        }
+    }
+    Ok(())
+}
+
+/// Candidates for `current`, given the words before it without the
+/// program name
+fn candidates(before: &[String], current: &str) -> Vec<(String, String)> {
+    let mut root = crate::Args::command();
+    root.build();
+
+    let mut command = &root;
+    let mut path = vec![];
+    let mut positionals = 0;
+    let mut pending: Option<&Arg> = None;
+    let mut only_positionals = false;
+
+    for word in before {
+        if pending.take().is_some() {
+            continue;
+        }
+        if only_positionals {
+            positionals += 1;
+            continue;
+        }
+        if word == "--" {
+            only_positionals = true;
+            continue;
+        }
+        if let Some(long) = word.strip_prefix("--") {
+            if !long.contains('=') {
+                pending = command
+                    .get_arguments()
+                    .find(|a| a.get_long() == Some(long))
+                    .filter(|a| takes_value(a));
+            }
+            continue;
+        }
+        if let Some(shorts) = word.strip_prefix('-').filter(|s| !s.is_empty()) {
+            // In `-vk` the value of `-k` is the next word, in `-kABC` not
+            for (i, short) in shorts.char_indices() {
+                let Some(arg) = command
+                    .get_arguments()
+                    .find(|a| a.get_short() == Some(short))
+                else {
+                    continue;
+                };
+                if takes_value(arg) {
+                    if i + short.len_utf8() == shorts.len() {
+                        pending = Some(arg);
+                    }
+                    break;
+                }
+            }
+            continue;
+        }
+        if positionals == 0 {
+            if let Some(subcommand) = command.find_subcommand(word) {
+                path.push(subcommand.get_name().to_string());
+                command = subcommand;
+                continue;
+            }
+        }
+        if positional(command, positionals)
+            .is_some_and(|a| a.is_trailing_var_arg_set())
+        {
+            only_positionals = true;
+        }
+        positionals += 1;
+    }
+
+    if let Some(arg) = pending {
+        return values(&path.join(" "), arg);
+    }
+    if only_positionals {
+        return vec![];
+    }
+    if current.starts_with('-') {
+        return flags(command);
+    }
+    if command.has_subcommands() {
+        let mut subcommands = command
+            .get_subcommands()
+            .filter(|c| !c.is_hide_set())
+            .map(|c| (c.get_name().to_string(), about(c)))
+            .collect::<Vec<_>>();
+        if path.is_empty() {
+            subcommands.extend(
+                plugin::list()
+                    .into_iter()
+                    .map(|p| (p.name, p.path.display().to_string())),
+            );
+        }
+        return subcommands;
+    }
+    match positional(command, positionals) {
+        Some(arg) => values(&path.join(" "), arg),
+        None => vec![],
+    }
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    arg.get_action().takes_values()
+}
+
+/// The positional argument at `index`, the last one if it takes many values
+fn positional(command: &Command, index: usize) -> Option<&Arg> {
+    let positionals = command.get_positionals().collect::<Vec<_>>();
+    positionals.get(index).copied().or_else(|| {
+        positionals
+            .last()
+            .copied()
+            .filter(|a| a.get_num_args().is_some_and(|n| n.max_values() > 1))
+    })
+}
+
+fn flags(command: &Command) -> Vec<(String, String)> {
+    command
+        .get_arguments()
+        .filter(|a| !a.is_positional() && !a.is_hide_set())
+        .filter_map(|a| {
+            let flag = match (a.get_long(), a.get_short()) {
+                (Some(long), _) => format!("--{}", long),
+                (None, Some(short)) => format!("-{}", short),
+                (None, None) => return None,
+            };
+            Some((
+                flag,
+                a.get_help().map(|h| h.to_string()).unwrap_or_default(),
+            ))
+        })
+        .collect()
+}
+
+fn about(command: &Command) -> String {
+    command
+        .get_about()
+        .map(|a| a.to_string())
+        .unwrap_or_default()
+}
+
+/// Values of `arg` of the command at `path`, e.g. `get var`
+fn values(path: &str, arg: &Arg) -> Vec<(String, String)> {
+    let possible = arg.get_possible_values();
+    if !possible.is_empty() {
+        return possible
+            .iter()
+            .filter(|v| !v.is_hide_set())
+            .map(|v| {
+                let help = v.get_help().map(|h| h.to_string());
+                (v.get_name().to_string(), help.unwrap_or_default())
+            })
+            .collect();
+    }
+
+    match (path, arg.get_id().as_str()) {
+        (_, "key") => keys(),
+        (_, "project_id") => projects(),
+        ("unset", "variable") => variable_ids(),
+        ("history", "variable") | ("get var", "name") | ("copy", "name") => {
+            variable_names()
+        }
+        (_, "context") | ("context use", "name") | ("context set", "name") => {
+            contexts()
+        }
+        _ => vec![],
+    }
+}
+
+fn keys() -> Vec<(String, String)> {
+    let Ok(config) = get_config() else {
+        return vec![];
+    };
+    config
+        .keys
+        .iter()
+        .map(|k| (k.fingerprint.clone(), k.primary_user_id.clone()))
+        .collect()
+}
+
+fn projects() -> Vec<(String, String)> {
+    let mut projects = BTreeMap::new();
+    for id in Cache::load().projects {
+        projects.insert(id, String::new());
+    }
+    if let Ok(config) = get_config() {
+        for project in &config.projects {
+            projects.insert(
+                project.project_id.clone(),
+                project.path.display().to_string(),
+            );
+        }
+    }
+    projects.into_iter().collect()
+}
+
+fn contexts() -> Vec<(String, String)> {
+    let Ok(config) = get_config() else {
+        return vec![];
+    };
+    config
+        .contexts
+        .iter()
+        .map(|(name, context)| {
+            (name.clone(), context.sdk_url.clone().unwrap_or_default())
+        })
+        .collect()
+}
+
+/// The cached variables of the project in use, or of every project
+fn current_variables() -> BTreeMap<String, String> {
+    let mut cache = Cache::load();
+    if let Ok(Some(project_id)) = Choice::configured_project() {
+        if let Some(variables) = cache.variables.remove(&project_id) {
+            return variables;
+        }
+    }
+    cache.variables.into_values().flatten().collect()
+}
+
+/// Variable names, including those declared in `.envx.toml`
+fn variable_names() -> Vec<(String, String)> {
+    let mut names = current_variables()
+        .into_values()
+        .map(|name| (name, String::new()))
+        .collect::<BTreeMap<_, _>>();
+    if let Ok(Some(project_file)) = ProjectFile::find() {
+        for (name, schema) in project_file.schema() {
+            names
+                .entry(name)
+                .or_insert(schema.description.unwrap_or_default());
+        }
+    }
+    names.into_iter().collect()
+}
+
+/// Variable ids, described by their names
+fn variable_ids() -> Vec<(String, String)> {
+    current_variables().into_iter().collect()
+}
+
+const BASH: &str = indoc! {r#"
+    _envx() {
+        local IFS=$'\n'
+        COMPREPLY=($(envx __complete "$COMP_CWORD" "${COMP_WORDS[@]}" 2>/dev/null | cut -f1))
+    }
+    complete -o default -o nosort -F _envx envx
+"#};
+
+const ZSH: &str = indoc! {r#"
+    #compdef envx
+    _envx() {
+        local -a lines candidates
+        local line
+        lines=("${(@f)$(envx __complete $((CURRENT - 1)) "${words[@]}" 2>/dev/null)}")
+        for line in $lines; do
+            [[ -n $line ]] && candidates+=("${${line%%$'\t'*}//:/\\:}:${line#*$'\t'}")
+        done
+        if (( ${#candidates} )); then
+            _describe -V envx candidates
+        else
+            _files
+        fi
+    }
+    compdef _envx envx
+"#};
+
+const FISH: &str = indoc! {r#"
+    complete -c envx -f -a '(envx __complete (count (commandline -opc)) (commandline -opc) (commandline -ct) 2>/dev/null)'
+"#};
+
+const POWERSHELL: &str = indoc! {r#"
+    Register-ArgumentCompleter -Native -CommandName envx -ScriptBlock {
+        param($wordToComplete, $commandAst, $cursorPosition)
+        $words = @($commandAst.CommandElements |
+            Where-Object { $_.Extent.StartOffset -lt $cursorPosition } |
+            ForEach-Object { $_.ToString() })
+        $index = $words.Count
+        if ($wordToComplete) { $index -= 1 }
+        envx __complete $index @words 2>$null | ForEach-Object {
+            $value, $description = $_ -split "`t", 2
+            if (-not $description) { $description = $value }
+            [System.Management.Automation.CompletionResult]::new(
+                $value, $value, 'ParameterValue', $description)
+        }
+    }
+"#};
+
+const ELVISH: &str = indoc! {r#"
+    use str
+    set edit:completion:arg-completer[envx] = {|@words|
+        envx __complete (- (count $words) 1) $@words 2>/dev/null | each {|line|
+            var value description = (str:split "\t" $line)
+            edit:complex-candidate $value &display=$value' '$description
+        }
+    }
+"#};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(before: &[&str], current: &str) -> Vec<String> {
+        let before = before.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        candidates(&before, current)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn completes_subcommands() {
+        let root = names(&[], "");
+        assert!(root.contains(&"variables".to_string()));
+        assert!(root.contains(&"completions".to_string()));
+        assert!(!root.contains(&COMPLETE.to_string()));
+
+        let get = names(&["get"], "");
+        assert!(get.contains(&"var".to_string()));
+        assert!(get.contains(&"keys".to_string()));
+    }
+
+    #[test]
+    fn completes_flags_of_the_subcommand() {
+        let flags = names(&["variables"], "--");
+        assert!(flags.contains(&"--reveal".to_string()));
+        assert!(flags.contains(&"--output".to_string()));
+        assert!(!flags.contains(&"--passphrase-stdin".to_string()));
+    }
+
+    #[test]
+    fn completes_possible_values() {
+        assert_eq!(
+            names(&["variables", "--output"], ""),
+            ["table", "json", "yaml", "plain"]
+        );
+        assert!(names(&["completions"], "").contains(&"fish".to_string()));
+    }
+
+    #[test]
+    fn skips_option_values() {
+        for before in [
+            &["variables", "-p", "abc"][..],
+            &["variables", "--project-id", "abc"],
+            &["variables", "--project-id=abc"],
+            &["variables", "-pabc"],
+        ] {
+            assert!(names(before, "--").contains(&"--reveal".to_string()));
+            assert_eq!(
+                names(&[before, &["--output"]].concat(), ""),
+                ["table", "json", "yaml", "plain"]
+            );
+        }
+    }
+
+    #[test]
+    fn does_not_take_option_values_for_subcommands() {
+        let root = names(&["--context", "get"], "");
+        assert!(root.contains(&"variables".to_string()));
+        assert!(!root.contains(&"var".to_string()));
+    }
+
+    #[test]
+    fn stops_at_trailing_arguments() {
+        assert!(names(&["run", "npm"], "").is_empty());
+        assert!(names(&["run", "npm"], "--").is_empty());
+        assert!(names(&["variables", "--"], "").is_empty());
+    }
+}
@@ -0,0 +1,28 @@
+use super::*;
+use crate::utils::{
+    config::get_config,
+    output,
+    settings::{display_value, Setting},
+};
+
+/// Print the value of a setting
+#[derive(Parser)]
+pub struct Args {
+    /// Name of the setting, see `envx config list`
+    setting: String,
+
+    /// Same as `--output json`
+    #[clap(long)]
+    json: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let setting = Setting::find(&args.setting)?;
+    let value = get_config()?.get_settings()?.get(setting.name)?;
+
+    output::json_if(args.json);
+    output::print(&value, |_| {
+        println!("{}", display_value(&value));
+        Ok(())
+    })
+}
@@ -0,0 +1,70 @@
+use super::*;
+use crate::utils::{
+    config::get_config,
+    output,
+    settings::{display_value, OutputFormat, REGISTRY},
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// List every setting with its value
+#[derive(Parser)]
+pub struct Args {
+    /// Same as `--output json`
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct SettingInfo {
+    name: &'static str,
+    #[serde(rename = "type")]
+    kind: String,
+    value: Value,
+    default: Value,
+    description: &'static str,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    output::json_if(args.json);
+    let settings = get_config()?.get_settings()?;
+
+    let mut list = vec![];
+    for setting in REGISTRY {
+        list.push(SettingInfo {
+            name: setting.name,
+            kind: setting.kind.to_string(),
+            value: settings.get(setting.name)?,
+            default: setting.default_value(),
+            description: setting.description,
+        });
+    }
+
+    output::print(&list, |format| {
+        for info in &list {
+            print_setting(info, format);
+        }
+        Ok(())
+    })
+}
+
+fn print_setting(info: &SettingInfo, format: OutputFormat) {
+    if format == OutputFormat::Plain {
+        println!("{}={}", info.name, display_value(&info.value));
+        return;
+    }
+
+    let value = display_value(&info.value);
+    let value = if info.value == info.default {
+        value.normal()
+    } else {
+        value.bold()
+    };
+    println!(
+        "{} = {} {}",
+        info.name,
+        value,
+        format!("({})", info.kind).dimmed()
+    );
+    println!("    {}", info.description.dimmed());
+}
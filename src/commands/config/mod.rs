@@ -7,16 +7,19 @@ pub(super) use colored::Colorize;
 use crate::commands_enum;
 use clap::Subcommand;
 
+pub mod get;
+pub mod list;
 pub mod set;
+pub mod unset;
 
-/// Configure envx
+/// Configure envx. (get, set, unset, list)
 #[derive(Parser)]
 pub struct Args {
     #[clap(subcommand)]
     command: Commands,
 }
 
-commands_enum!(set);
+commands_enum!(get, list, set, unset);
 
 pub async fn command(args: Args) -> Result<()> {
     Commands::exec(args).await?;
@@ -4,22 +4,75 @@ pub(super) use clap::Parser;
 #[allow(unused_imports)]
 pub(super) use colored::Colorize;
 
-use crate::commands_enum;
+use crate::info;
+use crate::utils::{
+    config::update_config,
+    settings::{display_value, Setting},
+};
 use clap::Subcommand;
 
 pub mod primary_key;
+pub mod require_signatures;
 pub mod unsafe_password;
 
-/// Delete a resource. (project, key)
+/// Set a setting, e.g. `envx config set session_ttl 8h`
 #[derive(Parser)]
+#[clap(args_conflicts_with_subcommands = true)]
 pub struct Args {
     #[clap(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
+
+    /// Name of the setting, see `envx config list`
+    #[clap(requires = "value")]
+    setting: Option<String>,
+
+    /// New value of the setting
+    value: Option<String>,
 }
 
-commands_enum!(primary_key, unsafe_password);
+// Settings are set with positional arguments, so the subcommands are
+// optional and can't be generated by `commands_enum!`
+#[derive(Subcommand)]
+enum Commands {
+    PrimaryKey(primary_key::Args),
+    RequireSignatures(require_signatures::Args),
+    UnsafePassword(unsafe_password::Args),
+}
 
 pub async fn command(args: Args) -> Result<()> {
-    Commands::exec(args).await?;
+    match args.command {
+        Some(Commands::PrimaryKey(args)) => primary_key::command(args).await?,
+        Some(Commands::RequireSignatures(args)) => {
+            require_signatures::command(args).await?
+        }
+        Some(Commands::UnsafePassword(args)) => {
+            unsafe_password::command(args).await?
+        }
+        None => {
+            let (Some(setting), Some(value)) = (args.setting, args.value)
+            else {
+                return Err(anyhow!(
+                    "Missing setting, see `envx config list` for all settings"
+                ));
+            };
+            set_setting(&setting, &value)?;
+        }
+    }
+    Ok(())
+}
+
+fn set_setting(name: &str, value: &str) -> Result<()> {
+    let setting = Setting::find(name)?;
+
+    let config = update_config(|config| {
+        let mut settings = config.get_settings()?;
+        settings.set(setting.name, value)?;
+        config.settings = Some(settings);
+        Ok(())
+    })?;
+
+    let value = config.get_settings()?.get(setting.name)?;
+    info!("{} set to {}", setting.name, display_value(&value));
+
     Ok(())
 }
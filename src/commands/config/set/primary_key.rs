@@ -1,5 +1,9 @@
 use super::*;
-use crate::utils::{config::get_config, prompt::prompt_select};
+use crate::interact_or;
+use crate::utils::{
+    config::{get_config, update_config},
+    prompt::prompt_select,
+};
 
 #[derive(Parser)]
 pub struct Args {
@@ -9,18 +13,18 @@ pub struct Args {
 }
 
 pub async fn command(args: Args) -> Result<()> {
-    let mut config = get_config()?;
+    let config = get_config()?;
 
     let fingerprint = match args.key {
         Some(k) => k,
         None => {
+            interact_or!("No key given, pass it with --key");
             prompt_select("Select key to set as primary", config.keys.clone())?
                 .fingerprint
         }
     };
 
-    config.set_primary_key(&fingerprint)?;
-    config.write()?;
+    update_config(|config| config.set_primary_key(&fingerprint))?;
 
     Ok(())
 }
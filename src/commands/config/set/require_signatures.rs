@@ -0,0 +1,47 @@
+use super::*;
+use crate::info;
+use crate::utils::{
+    choice::Choice,
+    config::{get_config, update_config},
+};
+
+/// Refuse unsigned or badly signed variables for a project
+#[derive(Parser)]
+pub struct Args {
+    /// Partial fingerprint of key to use
+    #[clap(short, long)]
+    key: Option<String>,
+
+    /// Project ID
+    #[clap(short, long)]
+    project_id: Option<String>,
+
+    /// Only warn about unsigned variables again
+    #[clap(long)]
+    disable: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let config = get_config()?;
+    let key = config.get_key_or_default(args.key)?;
+    let project_id =
+        Choice::try_project(args.project_id, &key.fingerprint).await?;
+
+    update_config(|config| {
+        let mut settings = config.get_settings()?;
+        settings.require_signatures.retain(|p| p != &project_id);
+        if !args.disable {
+            settings.require_signatures.push(project_id.clone());
+        }
+        config.settings = Some(settings);
+        Ok(())
+    })?;
+
+    if args.disable {
+        info!("Signatures are no longer required for {}", project_id);
+    } else {
+        info!("Signatures are now required for {}", project_id);
+    }
+
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use super::*;
+use crate::info;
+use crate::interact_or;
 use crate::utils::{
-    config::{get_config, get_config_path},
-    prompt::{prompt_confirm, prompt_password},
+    config::{get_config_path, update_config},
+    prompt::{is_interactive, prompt_confirm, prompt_password},
 };
 
 #[derive(Parser)]
@@ -12,30 +14,38 @@ pub struct Args {
 }
 
 pub async fn command(args: Args) -> Result<()> {
-    println!("This command is VERY insecure. It will store your password in PLAIN TEXT in the config file.");
-    prompt_confirm("Are you sure you want to continue?")?;
-
-    let mut config = get_config()?;
+    info!("This command is VERY insecure. It will store your password in PLAIN TEXT in the config file.");
+    // asking only makes sense in a terminal, scripts pass the password
+    if is_interactive()
+        && !prompt_confirm("Are you sure you want to continue?")?
+    {
+        anyhow::bail!("Aborted");
+    }
 
     let password = match args.password {
         Some(k) => k,
-        None => prompt_password("Enter the password to set")?,
+        None => {
+            interact_or!("No password given, pass it with --password");
+            prompt_password("Enter the password to set")?
+        }
     };
 
-    if password.is_empty() {
-        config.primary_key_password = None;
-        println!("Primary key password removed");
+    let removed = password.is_empty();
+    update_config(|config| {
+        config.primary_key_password = (!removed).then_some(password);
+        Ok(())
+    })?;
+
+    if removed {
+        info!("Primary key password removed");
     } else {
-        config.primary_key_password = Some(password);
-        println!("Primary key password set");
+        info!("Primary key password set");
     }
 
-    println!(
+    info!(
         "The config file is located at {}",
         get_config_path()?.to_str().unwrap_or("INVALID PATH")
     );
 
-    config.write()?;
-
     Ok(())
 }
@@ -0,0 +1,32 @@
+use super::*;
+use crate::info;
+use crate::utils::{
+    config::update_config,
+    settings::{display_value, Setting},
+};
+
+/// Reset a setting to its default
+#[derive(Parser)]
+pub struct Args {
+    /// Name of the setting, see `envx config list`
+    setting: String,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let setting = Setting::find(&args.setting)?;
+
+    update_config(|config| {
+        let mut settings = config.get_settings()?;
+        settings.unset(setting.name)?;
+        config.settings = Some(settings);
+        Ok(())
+    })?;
+
+    info!(
+        "{} reset to {}",
+        setting.name,
+        display_value(&setting.default_value())
+    );
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use super::set::ConnectionArgs;
+use super::*;
+use crate::info;
+use crate::utils::config::{update_config, ServerContext, DEFAULT_CONTEXT};
+use anyhow::bail;
+use url::Url;
+
+/// Add a server context
+#[derive(Parser)]
+pub struct Args {
+    /// Name of the context
+    name: String,
+
+    /// URL of the server
+    #[clap(short, long)]
+    url: String,
+
+    /// Partial fingerprint of the primary key for this context
+    #[clap(short, long)]
+    key: Option<String>,
+
+    #[clap(flatten)]
+    connection: ConnectionArgs,
+
+    /// Switch to the new context
+    #[clap(long = "use")]
+    use_context: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    if args.name == DEFAULT_CONTEXT {
+        bail!("The {} context already exists", DEFAULT_CONTEXT);
+    }
+
+    Url::parse(&args.url)
+        .with_context(|| format!("Invalid server URL '{}'", args.url))?;
+
+    update_config(|config| {
+        if config.contexts.contains_key(&args.name) {
+            bail!("Context '{}' already exists", args.name);
+        }
+
+        let primary_key = match &args.key {
+            Some(key) => config.get_key(key)?.fingerprint,
+            None => "".into(),
+        };
+
+        let mut context = ServerContext {
+            sdk_url: Some(args.url.clone()),
+            primary_key,
+            ..Default::default()
+        };
+        args.connection.apply(&mut context.connection)?;
+
+        config.contexts.insert(args.name.clone(), context);
+
+        if args.use_context {
+            config.current_context = Some(args.name.clone());
+        }
+
+        Ok(())
+    })?;
+
+    info!("{}", format!("Added context {}", args.name).green());
+    if args.use_context {
+        info!("Switched to context {}", args.name);
+    }
+
+    Ok(())
+}
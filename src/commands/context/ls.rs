@@ -0,0 +1,85 @@
+use super::*;
+use crate::utils::{
+    config::{get_config, Project, ServerContext, DEFAULT_SDK_URL},
+    http::ConnectionSettings,
+    output,
+    settings::OutputFormat,
+};
+use serde::Serialize;
+
+/// List server contexts
+#[derive(Parser)]
+pub struct Args {}
+
+#[derive(Serialize)]
+struct ContextInfo {
+    name: String,
+    current: bool,
+    url: String,
+    primary_key: String,
+    connection: ConnectionSettings,
+    projects: Vec<Project>,
+}
+
+pub async fn command(_args: Args) -> Result<()> {
+    let config = get_config()?;
+
+    let contexts = config
+        .all_contexts()?
+        .into_iter()
+        .map(|(name, context)| {
+            let ServerContext {
+                sdk_url,
+                primary_key,
+                projects,
+                connection,
+            } = context;
+            ContextInfo {
+                current: name == config.context_name(),
+                name,
+                url: sdk_url.unwrap_or_else(|| DEFAULT_SDK_URL.into()),
+                primary_key,
+                connection,
+                projects,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    output::print(&contexts, |format| {
+        for context in &contexts {
+            if format == OutputFormat::Plain {
+                println!("{}\t{}", context.name, context.url);
+                continue;
+            }
+            print_context(context);
+        }
+        Ok(())
+    })
+}
+
+fn print_context(context: &ContextInfo) {
+    let marker = if context.current { "*" } else { " " };
+    println!("{} {} {}", marker, context.name.bold(), context.url);
+    if !context.primary_key.is_empty() {
+        println!("    key: {}", context.primary_key);
+    }
+    let connection = &context.connection;
+    if let Some(ca_bundle) = &connection.ca_bundle {
+        println!("    ca bundle: {}", ca_bundle.display());
+    }
+    if let Some(client_cert) = &connection.client_cert {
+        println!("    client cert: {}", client_cert.display());
+    }
+    if let Some(proxy) = &connection.proxy {
+        println!("    proxy: {}", proxy);
+    }
+    if let Some(no_proxy) = &connection.no_proxy {
+        println!("    no proxy: {}", no_proxy);
+    }
+    if connection.insecure_skip_verify {
+        println!("    {}", "insecure: skipping TLS verification".yellow());
+    }
+    if !context.projects.is_empty() {
+        println!("    projects: {}", context.projects.len());
+    }
+}
@@ -0,0 +1,39 @@
+#[allow(unused_imports)]
+pub(super) use anyhow::{anyhow, Context, Result};
+pub(super) use clap::Parser;
+#[allow(unused_imports)]
+pub(super) use colored::Colorize;
+
+use clap::Subcommand;
+
+pub mod add;
+pub mod ls;
+pub mod set;
+pub mod use_context;
+
+/// Manage server contexts. (add, use, ls, set)
+#[derive(Parser)]
+pub struct Args {
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+// `use` is a keyword so this can't be generated by `commands_enum!`
+#[derive(Subcommand)]
+enum Commands {
+    Add(add::Args),
+    Ls(ls::Args),
+    Set(set::Args),
+    #[clap(name = "use")]
+    Use(use_context::Args),
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    match args.command {
+        Commands::Add(args) => add::command(args).await?,
+        Commands::Ls(args) => ls::command(args).await?,
+        Commands::Set(args) => set::command(args).await?,
+        Commands::Use(args) => use_context::command(args).await?,
+    }
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use super::*;
+use crate::info;
+use crate::utils::{
+    config::{update_config, DEFAULT_CONTEXT},
+    http::ConnectionSettings,
+};
+use anyhow::bail;
+use std::path::PathBuf;
+use url::Url;
+
+/// Change the server and connection settings of a context
+#[derive(Parser)]
+pub struct Args {
+    /// Name of the context, defaults to the current one
+    name: Option<String>,
+
+    /// URL of the server
+    #[clap(short, long)]
+    url: Option<String>,
+
+    /// Clear all connection settings before applying the new ones
+    #[clap(long)]
+    reset: bool,
+
+    #[clap(flatten)]
+    connection: ConnectionArgs,
+}
+
+/// Connection settings shared by `context add` and `context set`
+#[derive(clap::Args)]
+pub struct ConnectionArgs {
+    /// CA bundle (PEM) to trust when connecting to the server
+    #[clap(long)]
+    ca_bundle: Option<PathBuf>,
+
+    /// Client certificate (PEM) for mutual TLS
+    #[clap(long, requires = "client_key")]
+    client_cert: Option<PathBuf>,
+
+    /// PKCS#8 private key (PEM) of the client certificate
+    #[clap(long, requires = "client_cert")]
+    client_key: Option<PathBuf>,
+
+    /// HTTP(S) or SOCKS5 proxy, e.g. `http://proxy:3128`
+    #[clap(long)]
+    proxy: Option<String>,
+
+    /// Comma separated hosts that bypass the proxy, defaults to `NO_PROXY`
+    #[clap(long)]
+    no_proxy: Option<String>,
+
+    /// Don't verify the server's certificate, for local development only
+    #[clap(long, num_args = 0..=1, default_missing_value = "true")]
+    insecure_skip_verify: Option<bool>,
+}
+
+impl ConnectionArgs {
+    /// Validate the arguments and apply them on top of `settings`
+    pub fn apply(&self, settings: &mut ConnectionSettings) -> Result<()> {
+        for path in [&self.ca_bundle, &self.client_cert, &self.client_key]
+            .into_iter()
+            .flatten()
+        {
+            if !path.is_file() {
+                bail!("{} does not exist", path.display());
+            }
+        }
+
+        if let Some(proxy) = &self.proxy {
+            Url::parse(proxy)
+                .with_context(|| format!("Invalid proxy URL '{}'", proxy))?;
+        }
+
+        let absolute = |path: &PathBuf| {
+            path.canonicalize().with_context(|| {
+                format!("Failed to resolve {}", path.display())
+            })
+        };
+
+        if let Some(path) = &self.ca_bundle {
+            settings.ca_bundle = Some(absolute(path)?);
+        }
+        if let Some(path) = &self.client_cert {
+            settings.client_cert = Some(absolute(path)?);
+        }
+        if let Some(path) = &self.client_key {
+            settings.client_key = Some(absolute(path)?);
+        }
+        if let Some(proxy) = &self.proxy {
+            settings.proxy = Some(proxy.clone());
+        }
+        if let Some(no_proxy) = &self.no_proxy {
+            settings.no_proxy = Some(no_proxy.clone());
+        }
+        if let Some(insecure) = self.insecure_skip_verify {
+            settings.insecure_skip_verify = insecure;
+        }
+
+        // make sure the settings produce a working client
+        settings
+            .apply(reqwest::Client::builder())?
+            .build()
+            .context("Invalid connection settings")?;
+
+        Ok(())
+    }
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    if let Some(url) = &args.url {
+        Url::parse(url)
+            .with_context(|| format!("Invalid server URL '{}'", url))?;
+    }
+
+    let config = update_config(|config| {
+        let current = config.context_name().to_string();
+        let name = args.name.clone().unwrap_or(current.clone());
+
+        // the current context lives in the top level fields
+        let (sdk_url, connection) = if name == current {
+            (&mut config.sdk_url, &mut config.connection)
+        } else if name == DEFAULT_CONTEXT {
+            bail!(
+                "Switch to the {} context to change it, e.g. `envx --context {} context set`",
+                DEFAULT_CONTEXT,
+                DEFAULT_CONTEXT
+            );
+        } else {
+            let context =
+                config.contexts.get_mut(&name).with_context(|| {
+                    format!("Unknown context '{}', see `envx context ls`", name)
+                })?;
+            (&mut context.sdk_url, &mut context.connection)
+        };
+
+        if args.reset {
+            *connection = ConnectionSettings::default();
+        }
+        args.connection.apply(connection)?;
+
+        if let Some(url) = &args.url {
+            *sdk_url = Some(url.clone());
+        }
+
+        Ok(())
+    })?;
+
+    info!(
+        "{}",
+        format!(
+            "Updated context {}",
+            args.name.as_deref().unwrap_or(config.context_name())
+        )
+        .green()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use super::*;
+use crate::info;
+use crate::utils::config::{update_config, DEFAULT_CONTEXT};
+use anyhow::bail;
+
+/// Switch the default server context
+#[derive(Parser)]
+pub struct Args {
+    /// Name of the context
+    name: String,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    update_config(|config| {
+        if args.name == DEFAULT_CONTEXT {
+            config.current_context = None;
+        } else if config.contexts.contains_key(&args.name) {
+            config.current_context = Some(args.name.clone());
+        } else {
+            bail!("Unknown context '{}', see `envx context ls`", args.name);
+        }
+        Ok(())
+    })?;
+
+    info!("Switched to context {}", args.name);
+
+    if std::env::var("ENVX_CONTEXT").is_ok_and(|c| c != args.name) {
+        eprintln!(
+            "{}",
+            "ENVX_CONTEXT is set and takes precedence in this shell".yellow()
+        );
+    }
+
+    Ok(())
+}
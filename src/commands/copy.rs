@@ -0,0 +1,122 @@
+use super::agent::start::detach;
+use super::*;
+use crate::info;
+use crate::utils::clipboard::{self, Backend};
+use crate::utils::duration::{format_duration, parse_duration};
+use crypto_hash::{hex_digest, Algorithm};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Copy the value of a variable to the clipboard, which is cleared again
+/// after a while
+#[derive(Parser)]
+pub struct Args {
+    /// Name of the variable
+    #[clap(required_unless_present = "clear_later")]
+    name: Option<String>,
+
+    /// Partial fingerprint of key to use
+    #[clap(short, long)]
+    key: Option<String>,
+
+    /// Project ID
+    #[clap(short, long)]
+    project_id: Option<String>,
+
+    /// Clear the clipboard after this long, `0s` to leave it
+    #[clap(long, default_value = "45s")]
+    clear_after: String,
+
+    /// Wait, then clear the clipboard if it still holds the value hashed on
+    /// stdin. Run in the background by `envx copy`.
+    #[clap(long, hide = true)]
+    clear_later: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let timeout = parse_duration(&args.clear_after)?;
+    if args.clear_later {
+        return clear_later(timeout).await;
+    }
+
+    let name = args.name.context("No variable given")?;
+    let variable =
+        super::get::var::find(&name, args.key, args.project_id).await?;
+
+    let backend = clipboard::backend();
+    clipboard::copy(backend, &variable.value)
+        .context("Failed to copy to the clipboard")?;
+
+    if timeout.is_zero() {
+        info!("Copied {} to the clipboard with {}", variable.key, backend);
+        return Ok(());
+    }
+
+    // The hash goes through stdin as other users can see arguments
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .args(["copy", "--clear-later", "--clear-after", &args.clear_after])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null());
+    if backend == Backend::Osc52 {
+        // Clearing with OSC 52 takes the terminal, which a detached process
+        // no longer has. The helper keeps it and only leaves the process
+        // group, out of reach of Ctrl-C in the shell.
+        own_process_group(&mut command);
+    } else {
+        command.stderr(Stdio::null());
+        detach(&mut command);
+    }
+    let mut child = command
+        .spawn()
+        .context("Failed to schedule clearing the clipboard")?;
+    child
+        .stdin
+        .take()
+        .context("Failed to open stdin")?
+        .write_all(hash(&variable.value).as_bytes())?;
+
+    info!(
+        "Copied {} to the clipboard with {}, it will be cleared in {}{}",
+        variable.key,
+        backend,
+        format_duration(timeout),
+        match backend {
+            Backend::Osc52 => " unless the terminal is closed first",
+            _ => "",
+        }
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+fn own_process_group(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    command.process_group(0);
+}
+
+#[cfg(not(unix))]
+fn own_process_group(_command: &mut Command) {}
+
+/// Clear the clipboard unless something else was copied in the meantime,
+/// which can't be told with OSC 52
+async fn clear_later(timeout: Duration) -> Result<()> {
+    let mut expected = String::new();
+    std::io::stdin().read_to_string(&mut expected)?;
+
+    tokio::time::sleep(timeout).await;
+
+    let backend = clipboard::backend();
+    if let Some(current) = clipboard::paste(backend) {
+        if hash(&current) != expected.trim() {
+            return Ok(());
+        }
+    }
+    clipboard::clear(backend)
+}
+
+fn hash(value: &str) -> String {
+    hex_digest(Algorithm::SHA256, value.as_bytes())
+}
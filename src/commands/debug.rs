@@ -1,4 +1,4 @@
-use crate::utils::keyring::get_password;
+use crate::utils::{keyring::get_password, output};
 
 use super::*;
 
@@ -12,7 +12,10 @@ pub struct Args {
 pub async fn command(_args: Args) -> Result<()> {
     if let Some(key) = _args.key {
         let password = get_password(&key)?;
-        println!("Password: {}", password);
+        output::print(&serde_json::json!({ "password": password }), |_| {
+            println!("Password: {}", password);
+            Ok(())
+        })?;
     }
 
     Ok(())
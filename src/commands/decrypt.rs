@@ -1,6 +1,7 @@
 use super::*;
-use crate::utils::{config::get_config, rpgp::decrypt_full};
+use crate::utils::{config::get_config, output, rpgp::decrypt_full};
 use anyhow::{Context, Result};
+use serde::Serialize;
 
 /// Decrypt a string using GPG
 #[derive(Parser)]
@@ -8,12 +9,23 @@ pub struct Args {
     message: String,
 }
 
+#[derive(Serialize)]
+struct Decrypted<'a> {
+    decrypted: &'a str,
+}
+
 pub async fn command(args: Args) -> Result<()> {
     let config = get_config().context("Failed to get config")?;
 
     let decrypted = decrypt_full(args.message, &config)?;
 
-    println!("{}", decrypted);
-
-    Ok(())
+    output::print(
+        &Decrypted {
+            decrypted: &decrypted,
+        },
+        |_| {
+            println!("{}", decrypted);
+            Ok(())
+        },
+    )
 }
@@ -1,7 +1,10 @@
 use super::*;
+use crate::info;
+use crate::interact_or;
 use crate::{
     sdk::SDK,
     utils::{
+        config::update_config,
         key::Key,
         prompt::{prompt_confirm, prompt_multi_options},
     },
@@ -20,11 +23,8 @@ pub struct Args {
     force: bool,
 }
 
-// TODOS
-// TODO: fix configuration race condition while deleting multiple keys
-
 pub async fn command(args: Args) -> Result<()> {
-    let mut config =
+    let config =
         crate::utils::config::get_config().context("Failed to get config")?;
     let kl_arc = std::sync::Arc::new(&config.keys);
     let primary_key = &config.primary_key;
@@ -36,6 +36,7 @@ pub async fn command(args: Args) -> Result<()> {
         }
 
         None => {
+            interact_or!("No key given, pass it with --key");
             prompt_multi_options("Select keys to delete", config.keys.clone())?
                 .iter()
                 .map(|k| k.fingerprint.clone())
@@ -56,27 +57,28 @@ pub async fn command(args: Args) -> Result<()> {
         .collect::<Vec<_>>();
 
     if selected.contains(primary_key) {
-        println!("You have selected your primary key for deletion.");
-        println!(
+        info!("You have selected your primary key for deletion.");
+        info!(
             "You will not be able to use envx until you set a new primary key."
         );
 
         if args.force {
-            println!("Continuing because of --force");
+            info!("Continuing because of --force");
         } else {
+            interact_or!("Pass --force to delete the primary key");
             let confirmation =
                 prompt_confirm("Are you sure you want to continue?")?;
 
             if !confirmation {
-                println!("Aborting...");
+                info!("Aborting...");
                 return Ok(());
             }
 
-            println!("Set a new primary key with `envx change primary-key`");
+            info!("Set a new primary key with `envx change primary-key`");
         }
     }
 
-    println!("Deleting keys: {:?}", selected);
+    info!("Deleting keys: {:?}", selected);
 
     let keys = selected
         .iter()
@@ -91,23 +93,23 @@ pub async fn command(args: Args) -> Result<()> {
                     .join(&item.fingerprint);
 
                 if item.uuid.is_some() {
-                    println!("Deleting key {} on server...", &item);
+                    info!("Deleting key {} on server...", &item);
                     match SDK::delete_key(&item.fingerprint).await {
                         Ok(_) => {}
                         Err(e) => {
-                            println!("Failed to delete key on server: {}", e);
+                            info!("Failed to delete key on server: {}", e);
                             bail!("Failed to delete key on server");
                         }
                     }
                 } else {
-                    println!("Key {} not on server", item);
+                    info!("Key {} not on server", item);
                 }
 
                 if key_dir.exists() {
                     std::fs::remove_dir_all(key_dir)
                         .context("Failed to delete key directory")?
                 } else {
-                    println!("Key {} not on disk", item);
+                    info!("Key {} not on disk", item);
                 }
 
                 Ok(())
@@ -122,9 +124,13 @@ pub async fn command(args: Args) -> Result<()> {
             .context("Failed to delete key")?;
     }
 
-    config.keys.retain(|k| !&selected.contains(&k.fingerprint));
-
-    config.write().context("Failed to write config")?;
+    // re-read the config under the lock so changes made by other envx
+    // processes while the keys were being deleted aren't lost
+    update_config(|config| {
+        config.keys.retain(|k| !selected.contains(&k.fingerprint));
+        Ok(())
+    })
+    .context("Failed to write config")?;
 
     Ok(())
 }
@@ -4,6 +4,7 @@ use crate::{
 };
 
 use super::*;
+use crate::info;
 
 #[derive(Parser)]
 pub struct Args {
@@ -23,7 +24,7 @@ pub async fn command(args: Args) -> Result<()> {
         Choice::try_project(args.project, &key.fingerprint).await?;
     SDK::delete_project(&project_id, &key.fingerprint).await?;
     config.delete_project(&project_id)?;
-    println!("Project {} deleted", &project_id);
+    info!("Project {} deleted", &project_id);
 
     Ok(())
 }
@@ -0,0 +1,666 @@
+use super::*;
+use crate::info;
+use crate::{
+    sdk::{get_api_url, SDK},
+    utils::{
+        auth::get_token,
+        config::{get_config, get_config_path, update_config, Config},
+        env::{self, ENVX_PASSPHRASE},
+        http::http_client,
+        keyring::{check_keyring, clear_session, session_expiry},
+        migrations::{version_of, CONFIG_VERSION},
+        output,
+        rpgp::get_vault_location,
+        vault,
+    },
+};
+use anyhow::bail;
+use chrono::{DateTime, Local, Utc};
+use reqwest::header;
+use serde::Serialize;
+use std::{
+    collections::BTreeSet, fs, path::PathBuf, time::Duration, time::SystemTime,
+};
+
+/// Check the config, keys, keyring and server for problems
+#[derive(Parser)]
+pub struct Args {
+    /// Apply safe repairs for the problems found
+    #[clap(long)]
+    fix: bool,
+
+    /// With --fix, record checksums of key files that have none, after
+    /// making sure nobody else could have changed them
+    #[clap(long, requires = "fix")]
+    trust_key_files: bool,
+
+    /// With --fix, forget keys whose files are missing from the vault,
+    /// along with their user ids
+    #[clap(long, requires = "fix")]
+    remove_missing_keys: bool,
+
+    /// Skip the checks that talk to the server
+    #[clap(long)]
+    offline: bool,
+}
+
+/// A repair `--fix` knows how to make
+enum Fix {
+    /// Forget a key whose files are missing from the vault
+    RemoveKey(String),
+    /// Make another key the primary key
+    SetPrimaryKey(String),
+    /// Remove the link of a directory that no longer exists
+    UnlinkProject(String),
+    /// Remove an expired session and its stored passphrase
+    ClearSession(String),
+    /// Make a secret file readable by its owner only
+    RestrictPermissions(PathBuf),
+    /// Trust the files of a key that have no checksum in the vault
+    RecordChecksums(String, Vec<String>),
+}
+
+impl Fix {
+    /// The flag that has to go with `--fix` for repairs that aren't safe
+    /// to make without the user checking first
+    fn flag(&self) -> Option<&'static str> {
+        match self {
+            Fix::RemoveKey(_) => Some("--remove-missing-keys"),
+            Fix::RecordChecksums(..) => Some("--trust-key-files"),
+            _ => None,
+        }
+    }
+
+    fn allowed(&self, args: &Args) -> bool {
+        match self {
+            Fix::RemoveKey(_) => args.remove_missing_keys,
+            Fix::RecordChecksums(..) => args.trust_key_files,
+            _ => true,
+        }
+    }
+}
+
+impl std::fmt::Display for Fix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fix::RemoveKey(fp) => write!(f, "removed key {} from config", fp),
+            Fix::SetPrimaryKey(fp) => write!(f, "set primary key to {}", fp),
+            Fix::UnlinkProject(path) => write!(f, "unlinked {}", path),
+            Fix::ClearSession(fp) => {
+                write!(f, "cleared expired session of {}", fp)
+            }
+            Fix::RestrictPermissions(path) => {
+                write!(f, "set mode 600 on {}", path.display())
+            }
+            Fix::RecordChecksums(fp, names) => {
+                write!(
+                    f,
+                    "recorded checksums of {} of {}",
+                    names.join(", "),
+                    fp
+                )
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Skip,
+    Warn,
+    Fail,
+}
+
+#[derive(Serialize)]
+struct Check {
+    section: String,
+    status: Status,
+    message: String,
+    /// What `--fix` does about it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<String>,
+}
+
+/// What `--output json|yaml` prints
+#[derive(Serialize)]
+struct Summary<'a> {
+    checks: &'a [Check],
+    fixed: Vec<String>,
+    warnings: usize,
+    failures: usize,
+}
+
+/// Collects the checks, printing them as they come unless the output is
+/// machine readable
+#[derive(Default)]
+struct Report {
+    warnings: usize,
+    failures: usize,
+    fixes: Vec<Fix>,
+    section: String,
+    checks: Vec<Check>,
+}
+
+impl Report {
+    fn section(&mut self, title: &str) {
+        self.section = title.to_string();
+        if !output::is_machine_readable() {
+            println!("\n{}", title.bold());
+        }
+    }
+
+    fn ok(&mut self, message: impl AsRef<str>) {
+        self.record(Status::Ok, message.as_ref(), None);
+    }
+
+    fn skip(&mut self, message: impl AsRef<str>) {
+        self.record(Status::Skip, message.as_ref(), None);
+    }
+
+    fn warn(&mut self, message: impl AsRef<str>) {
+        self.warnings += 1;
+        self.record(Status::Warn, message.as_ref(), None);
+    }
+
+    fn fail(&mut self, message: impl AsRef<str>) {
+        self.failures += 1;
+        self.record(Status::Fail, message.as_ref(), None);
+    }
+
+    /// Report a failure `--fix` can repair
+    fn fixable(&mut self, message: impl AsRef<str>, fix: Fix) {
+        self.failures += 1;
+        let description = match fix.flag() {
+            Some(flag) => format!("{}, with --fix {}", fix, flag),
+            None => fix.to_string(),
+        };
+        self.record(Status::Fail, message.as_ref(), Some(description));
+        self.fixes.push(fix);
+    }
+
+    fn record(&mut self, status: Status, message: &str, fix: Option<String>) {
+        if !output::is_machine_readable() {
+            let message = match &fix {
+                Some(fix) => format!("{} (fix: {})", message, fix),
+                None => message.to_string(),
+            };
+            match status {
+                Status::Ok => println!("  {} {}", "✓".green(), message),
+                Status::Skip => {
+                    println!("  {} {}", "-".dimmed(), message.dimmed())
+                }
+                Status::Warn => {
+                    println!("  {} {}", "!".yellow(), message.yellow())
+                }
+                Status::Fail => println!("  {} {}", "✗".red(), message.red()),
+            }
+        }
+        self.checks.push(Check {
+            section: self.section.clone(),
+            status,
+            message: message.to_string(),
+            fix,
+        });
+    }
+}
+
+impl Args {
+    /// Whether the command is read-only, see `config::set_read_only`
+    pub fn read_only(&self) -> bool {
+        !self.fix
+    }
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let mut report = Report::default();
+
+    report.section("Config");
+    let Some(config) = check_config(&mut report) else {
+        bail!(
+            "The config could not be read, fix it before running doctor again"
+        );
+    };
+
+    report.section("Keys");
+    check_keys(&mut report, &config)?;
+
+    report.section("Projects");
+    check_projects(&mut report, &config);
+
+    report.section("Keyring");
+    check_sessions(&mut report, &config);
+
+    report.section("Server");
+    if args.offline {
+        report.skip("skipped (--offline)");
+    } else {
+        check_server(&mut report, &config).await;
+    }
+
+    let (allowed, held_back): (Vec<Fix>, Vec<Fix>) =
+        report.fixes.drain(..).partition(|fix| fix.allowed(&args));
+    let mut fixed = vec![];
+    if args.fix && !allowed.is_empty() {
+        apply_fixes(&allowed)?;
+        fixed = allowed.iter().map(|f| f.to_string()).collect();
+        report.failures -= allowed.len();
+    }
+
+    if output::is_machine_readable() {
+        let summary = Summary {
+            checks: &report.checks,
+            fixed,
+            warnings: report.warnings,
+            failures: report.failures,
+        };
+        output::print(&summary, |_| Ok(()))?;
+    } else {
+        println!();
+        for fix in &fixed {
+            println!("{} {}", "Fixed:".green(), fix);
+        }
+        if fixed.is_empty() && !allowed.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "{} problem(s) can be repaired with `envx doctor --fix`",
+                    allowed.len()
+                )
+                .yellow()
+            );
+        }
+        let flags = held_back
+            .iter()
+            .filter_map(Fix::flag)
+            .collect::<BTreeSet<_>>();
+        if !flags.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "{} problem(s) need a closer look, repair them with `envx doctor --fix {}`",
+                    held_back.len(),
+                    flags.into_iter().collect::<Vec<_>>().join(" ")
+                )
+                .yellow()
+            );
+        }
+    }
+
+    if report.failures > 0 {
+        bail!(
+            "{} problem(s) and {} warning(s) found",
+            report.failures,
+            report.warnings
+        );
+    }
+
+    if report.warnings > 0 {
+        info!("{}", format!("{} warning(s)", report.warnings).yellow());
+    } else {
+        info!("{}", "No problems found".green());
+    }
+
+    Ok(())
+}
+
+fn check_config(report: &mut Report) -> Option<Config> {
+    let path = match get_config_path() {
+        Ok(path) => path,
+        Err(e) => {
+            report.fail(format!("config path: {:#}", e));
+            return None;
+        }
+    };
+
+    let raw = fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|c| Ok(serde_json::from_str::<serde_json::Value>(&c)?));
+    match raw {
+        Ok(raw) => {
+            report.ok(format!("{} is valid JSON", path.display()));
+            let version = version_of(&raw);
+            if version > CONFIG_VERSION {
+                report.fail(format!(
+                    "config version {} is newer than this envx ({}), upgrade envx",
+                    version, CONFIG_VERSION
+                ));
+                return None;
+            } else if version < CONFIG_VERSION {
+                report.ok(format!(
+                    "config version {} will be migrated to {}",
+                    version, CONFIG_VERSION
+                ));
+            } else {
+                report.ok(format!("config version {}", version));
+            }
+        }
+        Err(e) => {
+            report.fail(format!("{}: {:#}", path.display(), e));
+            return None;
+        }
+    }
+
+    match get_config() {
+        Ok(config) => {
+            report.ok(format!("context '{}'", config.context_name()));
+            Some(config)
+        }
+        Err(e) => {
+            report.fail(format!("{:#}", e));
+            None
+        }
+    }
+}
+
+fn check_keys(report: &mut Report, config: &Config) -> Result<()> {
+    let vault = get_vault_location()?;
+    let env_key = env::private_key().ok().flatten().map(|(_, fp)| fp);
+    let mut usable = vec![];
+    let mut removed = vec![];
+
+    for key in &config.keys {
+        let fp = &key.fingerprint;
+        if env_key.as_deref() == Some(fp.as_str()) {
+            report.ok(format!("{} is given in the environment", fp));
+            usable.push(fp.clone());
+            continue;
+        }
+
+        let dir = vault.join(fp);
+        let mut missing = vec![];
+        if !dir.join(vault::PUBLIC_KEY).is_file() {
+            missing.push("public.key");
+        }
+        if !key.pubkey_only.unwrap_or(false)
+            && !dir.join(vault::PRIVATE_KEY).is_file()
+        {
+            missing.push("private.key");
+        }
+        if !missing.is_empty() {
+            report.fixable(
+                format!("{} is missing {}", fp, missing.join(" and ")),
+                Fix::RemoveKey(fp.clone()),
+            );
+            removed.push(fp.clone());
+            continue;
+        }
+
+        for path in vault::insecure_files(fp)? {
+            report.fixable(
+                format!("{} can be read by other users", path.display()),
+                Fix::RestrictPermissions(path),
+            );
+        }
+        match vault::verify(fp) {
+            Ok(unrecorded) if unrecorded.is_empty() => {}
+            Ok(unrecorded) => {
+                // Can't tell a key written before checksums from a
+                // tampered one. The key stays usable, reading it records
+                // them, but the user should vouch for the files first.
+                report.fixable(
+                    format!(
+                        "{} of {} have no checksum, make sure nobody else could have changed them",
+                        unrecorded.join(", "),
+                        fp
+                    ),
+                    Fix::RecordChecksums(fp.clone(), unrecorded),
+                );
+            }
+            Err(e) => {
+                report.fail(format!("{:#}", e));
+                continue;
+            }
+        }
+
+        if key.is_revoked() {
+            report.warn(format!("{} is revoked", fp));
+            continue;
+        }
+        match key.expires_at() {
+            Ok(Some(expires)) if expires < Utc::now() => {
+                report.warn(format!("{} expired on {}", fp, expires));
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                report.fail(format!("{}: {:#}", fp, e));
+                continue;
+            }
+        }
+
+        if key.uuid.is_none() {
+            report.warn(format!("{} is not uploaded, run `envx upload`", fp));
+        } else {
+            report.ok(fp);
+        }
+        if !key.pubkey_only.unwrap_or(false) {
+            usable.push(fp.clone());
+        }
+    }
+
+    // keys in the vault the config doesn't know about
+    let known = config.keys.iter().map(|k| k.fingerprint.as_str());
+    let known = known.collect::<BTreeSet<&str>>();
+    if let Ok(entries) = fs::read_dir(&vault) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.path().is_dir() && !known.contains(name.as_str()) {
+                report.warn(format!(
+                    "{} is in the vault but not in the config",
+                    entry.path().display()
+                ));
+            }
+        }
+    }
+
+    if config.primary_key.is_empty() {
+        match usable.first() {
+            Some(fp) => report.fixable(
+                "no primary key is set",
+                Fix::SetPrimaryKey(fp.clone()),
+            ),
+            None => {
+                report.warn("no primary key, create one with `envx new key`")
+            }
+        }
+    } else if !usable.contains(&config.primary_key) {
+        match usable.first() {
+            Some(fp) => report.fixable(
+                format!("primary key {} is not usable", config.primary_key),
+                Fix::SetPrimaryKey(fp.clone()),
+            ),
+            // unset along with the key by `Fix::RemoveKey`
+            None if removed.contains(&config.primary_key) => {
+                report.warn(format!(
+                    "primary key {} will be unset by --remove-missing-keys",
+                    config.primary_key
+                ))
+            }
+            None => report.fail(format!(
+                "primary key {} is not usable and there is no other key",
+                config.primary_key
+            )),
+        }
+    } else {
+        report.ok(format!("primary key {}", config.primary_key));
+    }
+
+    Ok(())
+}
+
+fn check_projects(report: &mut Report, config: &Config) {
+    if config.projects.is_empty() {
+        report.skip("no linked directories");
+    }
+    for project in &config.projects {
+        if project.path.is_dir() {
+            report.ok(format!(
+                "{} → {}",
+                project.path.display(),
+                project.project_id
+            ));
+        } else {
+            report.fixable(
+                format!("{} no longer exists", project.path.display()),
+                Fix::UnlinkProject(project.path.display().to_string()),
+            );
+        }
+    }
+}
+
+fn check_sessions(report: &mut Report, config: &Config) {
+    match check_keyring() {
+        Ok(backend) => report.ok(format!("keyring is available ({})", backend)),
+        Err(e) => report.warn(format!(
+            "keyring is not available ({}), set {} instead",
+            e, ENVX_PASSPHRASE
+        )),
+    }
+
+    for key in &config.keys {
+        let fp = &key.fingerprint;
+        match session_expiry(fp) {
+            None => report.skip(format!("{} is locked", fp)),
+            Some(expiry) if expiry < SystemTime::now() => report.fixable(
+                format!("session of {} has expired", fp),
+                Fix::ClearSession(fp.clone()),
+            ),
+            Some(expiry) => report.ok(format!(
+                "{} is unlocked until {}",
+                fp,
+                DateTime::<Local>::from(expiry).format("%Y-%m-%d %H:%M")
+            )),
+        }
+    }
+}
+
+/// Whether the passphrase of a key can be read without prompting
+fn has_passphrase(config: &Config, fingerprint: &str) -> bool {
+    env::var(ENVX_PASSPHRASE).is_some()
+        || (fingerprint == config.primary_key
+            && config.primary_key_password.is_some())
+        || session_expiry(fingerprint).is_some_and(|e| e > SystemTime::now())
+}
+
+async fn check_server(report: &mut Report, config: &Config) {
+    let url = match get_api_url() {
+        Ok(url) => url,
+        Err(e) => return report.fail(format!("{:#}", e)),
+    };
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(e) => return report.fail(format!("{:#}", e)),
+    };
+
+    let response = client
+        .get(url.clone())
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await;
+    match response {
+        Ok(_) => report.ok(format!("{} is reachable", url)),
+        Err(e) => {
+            return report.fail(format!("{} is not reachable: {:#}", url, e))
+        }
+    }
+
+    let Ok(key) = config.get_key(&config.primary_key) else {
+        return report.skip("no primary key to authenticate with");
+    };
+    let Some(uuid) = key.uuid.clone() else {
+        return report.skip("primary key is not uploaded");
+    };
+    if !has_passphrase(config, &key.fingerprint) {
+        return report.skip(format!(
+            "{} is locked, unlock it to check authentication",
+            key.fingerprint
+        ));
+    }
+
+    let token = match get_token(&key.fingerprint, &uuid).await {
+        Ok(token) => token,
+        Err(e) => return report.fail(format!("auth token: {:#}", e)),
+    };
+    let test_auth_url = match url.join("test-auth") {
+        Ok(url) => url,
+        Err(e) => return report.fail(format!("{:#}", e)),
+    };
+    let response = client
+        .post(test_auth_url)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .send()
+        .await;
+    match response {
+        Ok(res) if res.status().is_success() => {
+            report.ok(format!("authenticated as {}", key.fingerprint))
+        }
+        Ok(res) => {
+            return report
+                .fail(format!("authentication failed: {}", res.status()))
+        }
+        Err(e) => {
+            return report.fail(format!("authentication failed: {:#}", e))
+        }
+    }
+
+    let projects = match SDK::list_projects(&key.fingerprint).await {
+        Ok(projects) => projects,
+        Err(e) => return report.fail(format!("listing projects: {:#}", e)),
+    };
+    if projects.is_empty() {
+        report.skip("no projects");
+    }
+    for project_id in projects {
+        match SDK::get_variables(&project_id, &key.fingerprint).await {
+            Ok(variables) => report.ok(format!(
+                "project {} ({} variables decrypted)",
+                project_id,
+                variables.len()
+            )),
+            Err(e) => report.fail(format!(
+                "project {} can't be decrypted: {:#}",
+                project_id, e
+            )),
+        }
+    }
+}
+
+fn apply_fixes(fixes: &[Fix]) -> Result<()> {
+    for fix in fixes {
+        match fix {
+            Fix::ClearSession(fp) => clear_session(fp)?,
+            Fix::RestrictPermissions(path) => {
+                vault::restrict_permissions(path)?
+            }
+            Fix::RecordChecksums(fp, names) => {
+                vault::record_checksums(fp, names, &get_config()?.salt)?
+            }
+            _ => {}
+        }
+    }
+
+    update_config(|config| {
+        for fix in fixes {
+            match fix {
+                Fix::RemoveKey(fp) => {
+                    config.keys.retain(|k| &k.fingerprint != fp);
+                    if &config.primary_key == fp {
+                        config.primary_key.clear();
+                    }
+                }
+                Fix::SetPrimaryKey(fp) => config.primary_key = fp.clone(),
+                Fix::UnlinkProject(path) => config
+                    .projects
+                    .retain(|p| p.path.display().to_string() != *path),
+                Fix::ClearSession(_)
+                | Fix::RestrictPermissions(_)
+                | Fix::RecordChecksums(..) => {}
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(())
+}
@@ -1,9 +1,7 @@
 use anyhow::Context;
 
-use crate::utils::{
-    config::get_config,
-    rpgp::{encrypt, get_vault_location},
-};
+use crate::utils::{config::get_config, output, rpgp::encrypt};
+use serde::Serialize;
 
 use super::*;
 
@@ -17,20 +15,28 @@ pub struct Args {
     message: String,
 }
 
+#[derive(Serialize)]
+struct Encrypted<'a> {
+    encrypted: &'a str,
+}
+
 pub async fn command(args: Args) -> Result<()> {
     let config = get_config().context("Failed to get config")?;
 
-    let primary_key = config.primary_key.clone();
-
-    let primary_key_location =
-        get_vault_location()?.join(primary_key).join("public.key");
-
-    let primary_public_key = std::fs::read_to_string(primary_key_location)
+    let primary_public_key = config
+        .get_key(&config.primary_key)?
+        .public_key()
         .context("Failed to read primary key")?;
 
     let encrypted = encrypt(&args.message, primary_public_key.as_str())?;
 
-    println!("{}", encrypted);
-
-    Ok(())
+    output::print(
+        &Encrypted {
+            encrypted: &encrypted,
+        },
+        |_| {
+            println!("{}", encrypted);
+            Ok(())
+        },
+    )
 }
@@ -1,7 +1,9 @@
 use super::*;
+use crate::interact_or;
 use crate::utils::{
-    config::get_config, key::VecKeyTrait, prompt::prompt_options,
+    config::get_config, key::VecKeyTrait, output, prompt::prompt_options,
 };
+use serde::Serialize;
 
 /// Export a public or secret key
 #[derive(Parser)]
@@ -15,6 +17,14 @@ pub struct Args {
     secret_key: bool,
 }
 
+#[derive(Serialize)]
+struct ExportedKey {
+    fingerprint: String,
+    secret: bool,
+    /// Armored key
+    key: String,
+}
+
 pub async fn command(args: Args) -> Result<()> {
     let config = get_config().context("Failed to get config")?;
 
@@ -22,11 +32,14 @@ pub async fn command(args: Args) -> Result<()> {
 
     let fingerprint = match args.fingerprint {
         Some(fingerprint) => fingerprint.to_uppercase(),
-        None => prompt_options(
-            "Select key to export",
-            keys.iter().map(|e| e[..8].to_string()).collect(),
-        )?
-        .to_string(),
+        None => {
+            interact_or!("No key given, pass it with --fingerprint");
+            prompt_options(
+                "Select key to export",
+                keys.iter().map(|e| e[..8].to_string()).collect(),
+            )?
+            .to_string()
+        }
     };
 
     let key = config
@@ -35,13 +48,18 @@ pub async fn command(args: Args) -> Result<()> {
         .find(|k| k.fingerprint.starts_with(&fingerprint))
         .context("Failed to find key".red())?;
 
-    let key = if args.secret_key {
-        key.secret_key()?
-    } else {
-        key.public_key()?
+    let exported = ExportedKey {
+        fingerprint: key.fingerprint.clone(),
+        secret: args.secret_key,
+        key: if args.secret_key {
+            key.secret_key()?
+        } else {
+            key.public_key()?
+        },
     };
 
-    println!("{}", key);
-
-    Ok(())
+    output::print(&exported, |_| {
+        println!("{}", exported.key);
+        Ok(())
+    })
 }
@@ -1,27 +1,35 @@
 // TODO: add uuid to config after uploading
 
 use super::*;
+use crate::info;
 use crate::sdk::SDK;
-use crate::utils::config::{self};
+use crate::utils::config::{self, update_config};
 use crate::utils::key::Key;
 use crate::utils::keyring::set_password;
 // use crate::utils::prompt::prompt_password;
 use crate::constants::MINIMUM_PASSWORD_LENGTH;
-use crate::utils::prompt::{prompt_email, prompt_password, prompt_text};
+use crate::interact_or;
+use crate::utils::duration::parse_duration;
+use crate::utils::env::{self, ENVX_PASSPHRASE};
+use crate::utils::prompt::{
+    prompt_email, prompt_password, prompt_text, read_stdin_lines,
+};
 use crate::utils::rpgp::{
-    generate_hashed_primary_user_id, generate_key_pair, get_vault_location,
+    generate_hashed_primary_user_id, generate_key_pair,
+    generate_revocation_certificate,
 };
+use crate::utils::vault;
 use crate::utils::vecu8::ToHex;
 use anyhow::Context;
 use pgp::types::KeyTrait;
-use std::fs;
 use std::str;
+use std::time::Duration;
 
 extern crate keyring;
 use keyring::Error as KeyringError;
 
 /// Generate a key using GPG
-/// Saves the key to ~/.envcli/keys/<fingerprint>
+/// Saves the key to ~/.local/share/envx/keys/<fingerprint>
 #[derive(Parser)]
 pub struct Args {
     /// Interactive mode
@@ -40,10 +48,15 @@ pub struct Args {
     #[clap(short, long)]
     email: Option<String>,
 
-    /// Passphrase to encrypt the key with
+    /// Passphrase to encrypt the key with, visible to other users and kept
+    /// in the shell history, prefer ENVX_PASSPHRASE or --passphrase-stdin
     #[clap(short, long)]
     passphrase: Option<String>,
 
+    /// Read the passphrase from the first line of stdin
+    #[clap(long, conflicts_with = "passphrase")]
+    passphrase_stdin: bool,
+
     /// force overwrite of existing key
     #[clap(long = "force", short = 'f')]
     force_overwrite: bool,
@@ -54,6 +67,10 @@ pub struct Args {
 
     #[clap(long)]
     export: bool,
+
+    /// Expire the key after this long, e.g. `90d`, `6mo` or `2y`
+    #[clap(long, value_parser = parse_duration)]
+    expires: Option<Duration>,
 }
 
 fn email_validator(email: &str) -> anyhow::Result<(), anyhow::Error> {
@@ -74,18 +91,32 @@ fn email_validator(email: &str) -> anyhow::Result<(), anyhow::Error> {
 }
 
 pub async fn command(args: Args) -> Result<()> {
-    let mut config = config::get_config().context("Failed to get config")?;
+    let config = config::get_config().context("Failed to get config")?;
     let settings = config.get_settings()?;
 
-    let name = args
-        .name
-        .unwrap_or_else(|| prompt_text("What is your name?").unwrap());
+    let name = match args.name {
+        Some(name) => name,
+        None => {
+            interact_or!("No name given, pass it with --name");
+            prompt_text("What is your name?")?
+        }
+    };
 
-    let username = args
-        .username
-        .unwrap_or_else(|| prompt_text("What is your username?").unwrap());
+    let username = match args.username {
+        Some(username) => username,
+        None => {
+            interact_or!("No username given, pass it with --username");
+            prompt_text("What is your username?")?
+        }
+    };
 
-    let email = args.email.unwrap_or_else(|| prompt_email("email").unwrap());
+    let email = match args.email {
+        Some(email) => email,
+        None => {
+            interact_or!("No email given, pass it with --email");
+            prompt_email("email")?
+        }
+    };
 
     match email_validator(&email) {
         Ok(_) => {}
@@ -95,21 +126,43 @@ pub async fn command(args: Args) -> Result<()> {
         }
     }
 
-    let passphrase = args
-        .passphrase
-        .unwrap_or_else(|| prompt_password("password").unwrap());
+    let passphrase = if args.passphrase_stdin {
+        read_stdin_lines(1)?.pop()
+    } else {
+        args.passphrase.or_else(|| env::var(ENVX_PASSPHRASE))
+    };
+    let passphrase = match passphrase {
+        Some(passphrase) => passphrase,
+        None => {
+            interact_or!(
+                "No passphrase given, set {} or pass --passphrase-stdin",
+                ENVX_PASSPHRASE
+            );
+            prompt_password("password")?
+        }
+    };
 
     if settings.warn_on_short_passwords
         && passphrase.len() < MINIMUM_PASSWORD_LENGTH
     {
         eprintln!("WARNING: Your password is short");
         eprintln!("This is not recommended");
-        eprintln!("You can disable this warning with `envx config --no-warn-on-short-passwords`");
+        eprintln!("You can disable this warning with `envx config set warn_on_short_passwords false`");
     }
 
-    let key_pair =
-        generate_key_pair(name.clone(), email.clone(), passphrase.to_owned())
-            .expect("Failed to generate key pair");
+    let key_pair = generate_key_pair(
+        name.clone(),
+        email.clone(),
+        passphrase.to_owned(),
+        args.expires,
+    )
+    .expect("Failed to generate key pair");
+
+    let revocation_certificate = generate_revocation_certificate(
+        &key_pair.secret_key,
+        passphrase.to_owned(),
+    )
+    .context("Failed to generate revocation certificate")?;
 
     let priv_key = key_pair
         .secret_key
@@ -154,22 +207,25 @@ pub async fn command(args: Args) -> Result<()> {
         }
     }
 
-    println!("Fingerprint: {}", fingerprint);
+    info!("Fingerprint: {}", fingerprint);
 
     if args.export {
         println!("PRIVATE:\n{}", priv_key);
         println!("\nPUBLIC:\n{}", pub_key);
+        println!("\nREVOCATION CERTIFICATE:\n{}", revocation_certificate);
         return Ok(());
     }
 
-    let key_dir = get_vault_location()?.join(fingerprint.clone());
-
-    fs::create_dir_all(&key_dir).context("Failed to create key directory")?;
-
-    fs::write(key_dir.join("private.key"), &priv_key)
-        .expect("Failed to write private key to file");
-    fs::write(key_dir.join("public.key"), &pub_key)
-        .expect("Failed to write public key to file");
+    vault::write(&fingerprint, vault::PRIVATE_KEY, priv_key.as_bytes())
+        .context("Failed to write private key to file")?;
+    vault::write(&fingerprint, vault::PUBLIC_KEY, pub_key.as_bytes())
+        .context("Failed to write public key to file")?;
+    vault::write(
+        &fingerprint,
+        vault::REVOCATION_CERTIFICATE,
+        revocation_certificate.as_bytes(),
+    )
+    .context("Failed to write revocation certificate to file")?;
 
     let hashed_note =
         generate_hashed_primary_user_id(name.clone(), email.clone());
@@ -180,12 +236,13 @@ pub async fn command(args: Args) -> Result<()> {
         hashed_note: hashed_note.clone(),
         pubkey_only: None,
         uuid: None,
+        revoked: None,
     };
 
     if config.online {
         match SDK::new_user(&username, &pub_key).await {
             Ok(id) => {
-                println!("User ID: {}", id);
+                info!("User ID: {}", id);
                 key_to_insert.uuid = Some(id);
             }
             Err(_) => {
@@ -196,14 +253,16 @@ pub async fn command(args: Args) -> Result<()> {
         };
     }
 
-    config.keys.push(key_to_insert);
-
-    if config.primary_key.is_empty() {
-        println!("Setting primary key to {}...", &fingerprint);
-        config.primary_key = fingerprint;
-    }
+    update_config(|config| {
+        config.keys.push(key_to_insert);
 
-    config.write().context("Failed to write config")?;
+        if config.primary_key.is_empty() {
+            info!("Setting primary key to {}...", &fingerprint);
+            config.primary_key = fingerprint;
+        }
+        Ok(())
+    })
+    .context("Failed to write config")?;
 
     Ok(())
 }
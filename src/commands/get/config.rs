@@ -1,9 +1,11 @@
 use super::*;
 use crate::utils::btreemap::ToBTreeMap;
 use crate::utils::config::get_config;
+use crate::utils::output;
+use crate::utils::settings::OutputFormat;
 use crate::utils::table::Table;
-use anyhow::Context;
 use anyhow::Result;
+use std::collections::BTreeMap;
 
 /// Get the configuration either as a table or as a JSON output
 #[derive(Parser)]
@@ -11,27 +13,37 @@ pub struct Args {
     #[clap(short, long)]
     keys_only: bool,
 
+    /// Same as `--output json`
     #[clap(long)]
     json: bool,
 }
 
 pub async fn command(args: Args) -> Result<()> {
+    output::json_if(args.json);
     let config = get_config()?;
 
-    if args.json {
-        let json = serde_json::to_string_pretty(&config)
-            .context("Failed to serialize")?;
-        println!("{}", json);
-        return Ok(());
-    };
-
     if args.keys_only {
         let key_map = config.keys.to_btreemap()?;
-        Table::new("Fingerprint | Key ID".into(), key_map).print()?;
-        return Ok(());
+        return output::print(&key_map, |format| {
+            print_map("Fingerprint | Key ID", &key_map, format)
+        });
     };
 
-    Table::new("Configuration".into(), config.to_btreemap()?).print()?;
+    output::print(&config, |format| {
+        print_map("Configuration", &config.to_btreemap()?, format)
+    })
+}
 
-    Ok(())
+fn print_map(
+    title: &str,
+    map: &BTreeMap<String, String>,
+    format: OutputFormat,
+) -> Result<()> {
+    if format == OutputFormat::Plain {
+        for (name, value) in map {
+            println!("{}\t{}", name, value);
+        }
+        return Ok(());
+    }
+    Table::new(title.into(), map.clone()).print()
 }
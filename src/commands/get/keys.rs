@@ -1,5 +1,10 @@
 use super::*;
-use crate::utils::config::get_config;
+use crate::utils::{
+    config::get_config, duration::format_duration, output,
+    rpgp::warn_on_expiry, settings::OutputFormat,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 
 #[derive(Parser)]
 pub struct Args {
@@ -8,23 +13,91 @@ pub struct Args {
     full: bool,
 }
 
-pub async fn command(_args: Args) -> Result<()> {
+#[derive(Serialize)]
+struct KeyInfo {
+    fingerprint: String,
+    user_id: String,
+    /// Id of the key on the server, `None` until it's uploaded
+    uuid: Option<String>,
+    primary: bool,
+    revoked: bool,
+    expires_at: Option<DateTime<Utc>>,
+    /// Why the expiry couldn't be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub async fn command(args: Args) -> Result<()> {
     let config = get_config().context("Failed to get config")?;
 
-    println!("Keys:");
-    for key in config.keys.iter() {
-        let fingerprint = match _args.full {
-            true => &key.fingerprint,
-            false => &key.fingerprint[..8],
-        };
+    let keys = config
+        .keys
+        .iter()
+        .map(|key| {
+            let (expires_at, error) = match key.expires_at() {
+                Ok(expires_at) => (expires_at, None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            KeyInfo {
+                fingerprint: key.fingerprint.clone(),
+                user_id: key.primary_user_id.clone(),
+                uuid: key.uuid.clone(),
+                primary: key.fingerprint == config.primary_key,
+                revoked: key.is_revoked(),
+                expires_at,
+                error,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    for key in keys.iter().filter(|k| !k.revoked) {
+        warn_on_expiry(&key.fingerprint, key.expires_at);
+    }
+
+    output::print(&keys, |format| {
+        if format == OutputFormat::Plain {
+            for key in &keys {
+                println!("{}\t{}", key.fingerprint, key.user_id);
+            }
+            return Ok(());
+        }
 
-        let uuid = match &key.uuid {
-            Some(uuid) => uuid,
-            None => "Not on remote",
-        };
+        println!("Keys:");
+        for key in &keys {
+            print_key(key, args.full);
+        }
+        Ok(())
+    })
+}
+
+fn print_key(key: &KeyInfo, full: bool) {
+    let fingerprint = match full {
+        true => &key.fingerprint,
+        false => &key.fingerprint[..8],
+    };
+    let uuid = key.uuid.as_deref().unwrap_or("Not on remote");
+
+    println!("\t{} {} | {}", fingerprint, key.user_id, uuid);
 
-        println!("\t{} {} | {}", fingerprint, key.primary_user_id, uuid);
+    if key.revoked {
+        println!("\t  {}", "revoked".red());
+        return;
+    }
+    if let Some(error) = &key.error {
+        println!("\t  {}", error.red());
     }
 
-    Ok(())
+    let Some(expires_at) = key.expires_at else {
+        return;
+    };
+    match (expires_at - Utc::now()).to_std() {
+        Ok(remaining) => println!(
+            "\t  expires {} (in {})",
+            expires_at,
+            format_duration(remaining)
+        ),
+        Err(_) => {
+            println!("\t  {}", format!("expired {}", expires_at).red())
+        }
+    }
 }
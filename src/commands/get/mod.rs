@@ -8,23 +8,26 @@ pub mod config;
 pub mod keys;
 pub mod project;
 pub mod projects;
+pub mod var;
 
 use crate::commands_enum;
 use clap::Subcommand;
 
-/// Get a resource. (project, key, config)
+/// Get a resource. (project, key, config, variable)
 #[derive(Parser)]
 pub struct Args {
     #[clap(subcommand)]
     command: Commands,
 
+    /// Same as `--output json`
     #[clap(global = true, long)]
     json: bool,
 }
 
-commands_enum!(project, config, keys, projects);
+commands_enum!(project, config, keys, projects, var);
 
 pub async fn command(args: Args) -> Result<()> {
+    crate::utils::output::json_if(args.json);
     Commands::exec(args).await?;
     Ok(())
 }
@@ -1,5 +1,5 @@
 use super::*;
-use crate::utils::config::get_config;
+use crate::utils::{config::get_config, output, settings::OutputFormat};
 use crate::{sdk::SDK, utils::choice::Choice};
 
 /// Get all environment variables for a project
@@ -13,19 +13,32 @@ pub struct Args {
     #[clap(short, long)]
     project_id: Option<String>,
 
-    /// Output in JSON format
+    /// Same as `--output json`
     #[clap(long)]
     json: bool,
 }
 
-// TODO: Pretty print project info (in a table?)
 pub async fn command(args: Args) -> Result<()> {
+    output::json_if(args.json);
     let config = get_config()?;
     let key = config.get_key_or_default(args.key)?;
     let project_id =
         Choice::try_project(args.project_id, &key.fingerprint).await?;
     let project_info =
         SDK::get_project_info(&project_id, &key.fingerprint).await?;
-    println!("{:?}", project_info);
-    Ok(())
+
+    output::print(&project_info, |format| {
+        if format == OutputFormat::Plain {
+            for user in &project_info.users {
+                println!("{}\t{}", user.id, user.username);
+            }
+            return Ok(());
+        }
+
+        println!("{} {}", "Project".bold(), project_info.id);
+        for user in &project_info.users {
+            println!("  {}", user);
+        }
+        Ok(())
+    })
 }
@@ -1,39 +1,61 @@
-use crate::{sdk::SDK, utils::config::get_config};
+use crate::{
+    sdk::SDK,
+    utils::{config::get_config, output, settings::OutputFormat},
+};
+use serde::Serialize;
+use std::path::PathBuf;
 
 use super::*;
 
 #[derive(Parser)]
 pub struct Args {
+    /// Same as `--output json`
     #[clap(long)]
     json: bool,
 }
 
+#[derive(Serialize)]
+struct Project {
+    id: String,
+    /// Directory the project is linked to, `None` for projects that are
+    /// only on the server
+    path: Option<PathBuf>,
+}
+
 pub async fn command(args: Args) -> Result<()> {
+    output::json_if(args.json);
+
     let config = get_config()?;
     let key = config.get_key_or_default(None)?;
 
     let local_projects = config.projects.clone();
     let remote_projects = SDK::list_projects(&key.fingerprint)
         .await
-        .context("Failed to get projects from server".red())?;
+        .context("Failed to get projects from server")?;
     let remote_projects = remote_projects
-        .iter()
-        .filter(|p| !local_projects.iter().any(|lp| lp.project_id == **p))
-        .map(|p| format!("{} - {}", p, "Remote".green()));
-    let local_projects = local_projects
-        .iter()
-        .map(|p| format!("{} - {}", p.project_id, p.path.display()));
-
-    let combined = local_projects
+        .into_iter()
+        .filter(|p| !local_projects.iter().any(|lp| lp.project_id == *p))
+        .map(|id| Project { id, path: None })
+        .collect::<Vec<_>>();
+    let projects = local_projects
+        .into_iter()
+        .map(|p| Project {
+            id: p.project_id,
+            path: Some(p.path),
+        })
         .chain(remote_projects)
-        .collect::<Vec<String>>();
-
-    if args.json {
-        println!("{}", serde_json::to_string(&combined)?);
-        return Ok(());
-    } else {
-        println!("{}", combined.join("\n"));
-    }
+        .collect::<Vec<_>>();
 
-    Ok(())
+    output::print(&projects, |format| {
+        for project in &projects {
+            match (&project.path, format) {
+                (Some(path), _) => {
+                    println!("{} - {}", project.id, path.display())
+                }
+                (None, OutputFormat::Plain) => println!("{}", project.id),
+                (None, _) => println!("{} - {}", project.id, "Remote".green()),
+            }
+        }
+        Ok(())
+    })
 }
@@ -0,0 +1,80 @@
+use super::*;
+use crate::sdk::SDK;
+use crate::utils::{
+    choice::Choice, config::get_config, kvpair::KVPair, output,
+    project_file::ProjectFile,
+};
+use anyhow::bail;
+use serde::Serialize;
+use std::io::IsTerminal;
+
+/// Print the value of a single variable, unmasked, for use in scripts
+#[derive(Parser)]
+pub struct Args {
+    /// Name of the variable
+    name: String,
+
+    /// Partial fingerprint of key to use
+    #[clap(short, long)]
+    key: Option<String>,
+
+    /// Project ID
+    #[clap(short, long)]
+    project_id: Option<String>,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let variable = find(&args.name, args.key, args.project_id).await?;
+    let secret =
+        ProjectFile::find()?.is_some_and(|f| f.is_secret(&variable.key));
+    if secret && std::io::stdout().is_terminal() {
+        bail!(
+            "{} is secret, copy it with `envx copy {}` or redirect the output",
+            variable.key,
+            variable.key
+        );
+    }
+
+    let output = Variable {
+        variable: &variable,
+        secret,
+    };
+    output::print(&output, |_| {
+        println!("{}", variable.value);
+        Ok(())
+    })
+}
+
+#[derive(Serialize)]
+struct Variable<'a> {
+    #[serde(flatten)]
+    variable: &'a KVPair,
+    /// Marked secret in the project file
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    secret: bool,
+}
+
+/// Look up a variable of a project, as `envx variables` would show it
+pub async fn find(
+    name: &str,
+    key: Option<String>,
+    project_id: Option<String>,
+) -> Result<KVPair> {
+    let config = get_config()?;
+    let key = config.get_key_or_default(key)?;
+    let project_id = Choice::try_project(project_id, &key.fingerprint).await?;
+
+    let mut variables =
+        SDK::get_variables_pruned(&project_id, &key.fingerprint).await?;
+    if let Some(project_file) = ProjectFile::find()? {
+        variables = project_file.filter("variables", variables, |v| &v.key)?;
+    }
+
+    let name = name.to_uppercase();
+    variables
+        .into_iter()
+        .find(|v| v.key == name)
+        .with_context(|| {
+            format!("Variable {} is not set in project {}", name, project_id)
+        })
+}
@@ -0,0 +1,62 @@
+use super::*;
+use crate::{
+    sdk::SDK,
+    utils::{
+        choice::Choice, config::get_config, output, settings::OutputFormat,
+        signing,
+    },
+};
+
+/// Show every version of the project's variables and who wrote it
+#[derive(Parser)]
+pub struct Args {
+    /// Only show the history of this variable
+    variable: Option<String>,
+
+    #[clap(short, long)]
+    key: Option<String>,
+
+    #[clap(short, long)]
+    project_id: Option<String>,
+
+    /// Same as `--output json`
+    #[clap(long)]
+    json: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    output::json_if(args.json);
+    let config = get_config()?;
+    let key = config.get_key_or_default(args.key)?;
+    let project_id =
+        Choice::try_project(args.project_id, &key.fingerprint).await?;
+
+    let variables = SDK::get_variables(&project_id, &key.fingerprint).await?;
+
+    let filter = args.variable.map(|v| v.to_uppercase());
+    let mut history = variables
+        .into_iter()
+        .filter(|v| filter.as_ref().map_or(true, |f| &v.value.key == f))
+        .collect::<Vec<_>>();
+    history.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    output::print(&history, |format| {
+        for variable in history.iter() {
+            if format == OutputFormat::Plain {
+                println!(
+                    "{}\t{}\t{}",
+                    variable.created_at, variable.value.key, variable.signer
+                );
+                continue;
+            }
+            println!(
+                "{} {} {} {}",
+                variable.created_at.dimmed(),
+                variable.value.key.bold(),
+                "by".dimmed(),
+                signing::describe(&variable.signer)
+            );
+        }
+        Ok(())
+    })
+}
@@ -1,7 +1,9 @@
 use super::*;
+use crate::info;
+use crate::interact_or;
 use crate::utils::key::Key;
 use crate::utils::prompt::prompt_text;
-use crate::utils::rpgp::get_vault_location;
+use crate::utils::vault;
 use crate::utils::vecu8::ToHex;
 use clap::Subcommand;
 use pgp::{types::KeyTrait, Deserializable};
@@ -18,13 +20,22 @@ pub struct Args {
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Import a public key
-    Pubkey { path: String },
+    Pubkey {
+        path: String,
+
+        /// Name of the owner, for keys without a user id
+        #[clap(long)]
+        name: Option<String>,
+
+        /// Email of the owner, for keys without a user id
+        #[clap(long)]
+        email: Option<String>,
+    },
 }
 
 pub async fn command(args: Args) -> Result<()> {
-    let mut vault_path = get_vault_location()?;
     match args.command {
-        Commands::Pubkey { path } => {
+        Commands::Pubkey { path, name, email } => {
             let buf = Cursor::new(
                 fs::read_to_string(path).context("Failed to read file")?,
             );
@@ -34,7 +45,7 @@ pub async fn command(args: Args) -> Result<()> {
 
             let fingerprint = pubkey.fingerprint().to_hex().to_uppercase();
 
-            println!("Importing key: {}", fingerprint);
+            info!("Importing key: {}", fingerprint);
 
             let first_user_id = pubkey
                 .details
@@ -48,16 +59,30 @@ pub async fn command(args: Args) -> Result<()> {
             let (primary_user_id, hashed_note) = if only_hex(&first_user_id)
                 && first_user_id.len() == 128
             {
-                println!("This key has no user id because it was generated by env-cli.");
-                println!(
+                info!("This key has no user id because it was generated by env-cli.");
+                info!(
                     "Please enter the name and email of the owner of this key."
                 );
-                println!("User Id: {}", first_user_id);
+                info!("User Id: {}", first_user_id);
 
-                let name =
-                    prompt_text("What is the name of the owner of this key?")?;
-                let email =
-                    prompt_text("What is the email of the owner of this key?")?;
+                let name = match name {
+                    Some(name) => name,
+                    None => {
+                        interact_or!("No owner given, pass it with --name");
+                        prompt_text(
+                            "What is the name of the owner of this key?",
+                        )?
+                    }
+                };
+                let email = match email {
+                    Some(email) => email,
+                    None => {
+                        interact_or!("No email given, pass it with --email");
+                        prompt_text(
+                            "What is the email of the owner of this key?",
+                        )?
+                    }
+                };
                 (format!("{} <{}>", name, email), first_user_id)
             } else {
                 (first_user_id, "".to_string())
@@ -70,10 +95,14 @@ pub async fn command(args: Args) -> Result<()> {
                 primary_user_id,
                 hashed_note,
                 uuid: None,
+                revoked: None,
             };
 
-            vault_path.push(format!("{}/public.key", &key.fingerprint));
-            fs::write(vault_path, pubkey.to_armored_string(None)?)?;
+            vault::write(
+                &key.fingerprint,
+                vault::PUBLIC_KEY,
+                pubkey.to_armored_string(None)?.as_bytes(),
+            )?;
         }
     }
 
@@ -0,0 +1,114 @@
+use super::*;
+use crate::info;
+use crate::interact_or;
+use crate::utils::{
+    atomic::write_atomic_private,
+    backup::{Backup, BackupFormat},
+    config::get_config,
+    prompt::prompt_select,
+    rpgp::get_key,
+};
+use anyhow::bail;
+use pgp::ser::Serialize;
+use std::path::PathBuf;
+
+/// Back up a secret key to paper or QR codes
+///
+/// The key stays encrypted with its passphrase. With `--shares` the backup
+/// is split so that any `--threshold` of the shares can restore it.
+#[derive(Parser)]
+pub struct Args {
+    /// Partial fingerprint of the key to back up
+    #[clap(short, long)]
+    key: Option<String>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "paper")]
+    format: BackupFormat,
+
+    /// Bytes per chunk with `--format chunks`
+    #[clap(long, default_value_t = 256)]
+    chunk_size: usize,
+
+    /// Split the backup into this many shares
+    #[clap(long, requires = "threshold")]
+    shares: Option<u8>,
+
+    /// Number of shares needed to restore the key
+    #[clap(long, requires = "shares")]
+    threshold: Option<u8>,
+
+    /// Write the backup to this file instead of stdout
+    ///
+    /// Shares are written to <OUT>.share<N>
+    #[clap(short = 'o', long)]
+    out: Option<PathBuf>,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let config = get_config()?;
+
+    let key = match args.key {
+        Some(key) => config.get_key(&key)?,
+        None => {
+            interact_or!("No key given, pass it with --key");
+            prompt_select("Select key to back up", config.keys.clone())?
+        }
+    };
+
+    if key.pubkey_only.unwrap_or(false) {
+        bail!("Key {} has no secret key", key.fingerprint);
+    }
+
+    let (seckey, fingerprint) = get_key(key.fingerprint.clone())?;
+
+    let backup = Backup::new(
+        fingerprint,
+        key.primary_user_id.clone(),
+        key.hashed_note.clone(),
+        key.uuid.clone(),
+        seckey
+            .to_bytes()
+            .context("Failed to serialize secret key")?,
+    );
+
+    let backups = match (args.shares, args.threshold) {
+        (Some(shares), Some(threshold)) => backup.split(shares, threshold)?,
+        _ => vec![backup],
+    };
+
+    for (i, backup) in backups.iter().enumerate() {
+        let encoded = backup.encode(args.format, args.chunk_size)?;
+
+        match &args.out {
+            Some(output) => {
+                let path = match backups.len() {
+                    1 => output.clone(),
+                    _ => {
+                        let mut path = output.clone().into_os_string();
+                        path.push(format!(".share{}", i + 1));
+                        PathBuf::from(path)
+                    }
+                };
+                write_atomic_private(&path, encoded.as_bytes()).with_context(
+                    || format!("Failed to write backup to {}", path.display()),
+                )?;
+                info!("Wrote {}", path.display());
+            }
+            None => {
+                if i > 0 {
+                    println!();
+                }
+                println!("{}", encoded);
+            }
+        }
+    }
+
+    eprintln!(
+        "{}",
+        "Keep your backup somewhere safe, it is still protected by your passphrase"
+            .yellow()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+#[allow(unused_imports)]
+pub(super) use anyhow::{anyhow, Context, Result};
+pub(super) use clap::Parser;
+#[allow(unused_imports)]
+pub(super) use colored::Colorize;
+
+use crate::commands_enum;
+use clap::Subcommand;
+
+pub mod backup;
+pub mod passwd;
+pub mod restore;
+pub mod revoke;
+
+/// Manage your keys. (backup, passwd, restore, revoke)
+#[derive(Parser)]
+pub struct Args {
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+commands_enum!(backup, passwd, restore, revoke);
+
+pub async fn command(args: Args) -> Result<()> {
+    Commands::exec(args).await?;
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use super::*;
+use crate::info;
+use crate::interact_or;
+use crate::{
+    constants::MINIMUM_PASSWORD_LENGTH,
+    utils::{
+        config::{get_config, update_config},
+        env::{self, ENVX_NEW_PASSPHRASE, ENVX_PASSPHRASE},
+        keyring::set_password,
+        prompt::{prompt_password, prompt_select, read_stdin_lines},
+        rpgp::{change_passphrase, get_key},
+        vault,
+    },
+};
+use anyhow::bail;
+
+/// Change the passphrase of a secret key
+///
+/// The passphrases are taken from ENVX_PASSPHRASE and ENVX_NEW_PASSPHRASE,
+/// or from stdin with --passphrase-stdin, and prompted for otherwise
+#[derive(Parser)]
+pub struct Args {
+    /// Partial fingerprint of the key
+    fingerprint: Option<String>,
+
+    /// Read the current and the new passphrase from the first two lines of
+    /// stdin
+    #[clap(long)]
+    passphrase_stdin: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let config = get_config()?;
+    let settings = config.get_settings()?;
+
+    let key = match args.fingerprint {
+        Some(fingerprint) => config.get_key(&fingerprint)?,
+        None => {
+            interact_or!("No key given, pass its fingerprint");
+            prompt_select(
+                "Select key to change the passphrase of",
+                config.keys.clone(),
+            )?
+        }
+    };
+
+    if key.pubkey_only.unwrap_or(false) {
+        bail!("Key {} has no secret key", key.fingerprint);
+    }
+
+    let (old_passphrase, new_passphrase) = if args.passphrase_stdin {
+        let mut lines = read_stdin_lines(2)?.into_iter();
+        (lines.next(), lines.next())
+    } else {
+        (env::var(ENVX_PASSPHRASE), env::var(ENVX_NEW_PASSPHRASE))
+    };
+
+    let old_passphrase = match old_passphrase {
+        Some(p) => p,
+        None => {
+            interact_or!(
+                "No passphrase given, set {} or pass --passphrase-stdin",
+                ENVX_PASSPHRASE
+            );
+            prompt_password("Current passphrase: ")?
+        }
+    };
+
+    let new_passphrase = match new_passphrase {
+        Some(p) => p,
+        None => {
+            interact_or!(
+                "No new passphrase given, set {} or pass --passphrase-stdin",
+                ENVX_NEW_PASSPHRASE
+            );
+            let new = prompt_password("New passphrase: ")?;
+            if new != prompt_password("Repeat new passphrase: ")? {
+                bail!("Passphrases do not match");
+            }
+            new
+        }
+    };
+
+    if settings.warn_on_short_passwords
+        && new_passphrase.len() < MINIMUM_PASSWORD_LENGTH
+    {
+        eprintln!("WARNING: Your password is short");
+        eprintln!("This is not recommended");
+    }
+
+    let (seckey, fingerprint) = get_key(key.fingerprint.clone())?;
+    let new_key = change_passphrase(&seckey, old_passphrase, &new_passphrase)?;
+
+    let armored = new_key
+        .to_armored_string(None)
+        .context("Failed to convert private key to armored ASCII string")?;
+
+    vault::write(&fingerprint, vault::PRIVATE_KEY, armored.as_bytes())
+        .context("Failed to write private key, the old key is unchanged")?;
+
+    info!("{}", "Passphrase changed".green());
+
+    if let Err(e) = set_password(&fingerprint, &new_passphrase) {
+        eprintln!("Failed to update the passphrase in the keyring: {}", e);
+    }
+
+    if config.primary_key == fingerprint
+        && config.primary_key_password.is_some()
+    {
+        info!("Updating the unsafe primary key password in the config");
+        update_config(|config| {
+            config.primary_key_password = Some(new_passphrase);
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
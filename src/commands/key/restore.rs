@@ -0,0 +1,135 @@
+use super::*;
+use crate::info;
+use crate::utils::{
+    backup::Backup,
+    config::{get_config, update_config},
+    env::{self, ENVX_PASSPHRASE},
+    key::Key,
+    keyring::set_password,
+    prompt::read_stdin_lines,
+    rpgp::public_key_from_secret,
+    vault,
+    vecu8::ToHex,
+};
+use anyhow::bail;
+use pgp::{
+    types::{KeyTrait, SecretKeyTrait},
+    Deserializable, SignedSecretKey,
+};
+use std::{fs, io::Read, path::PathBuf};
+
+/// Restore a secret key from a paper backup or from shares
+///
+/// Reads the backup from stdin if no files are given. The passphrase of
+/// the key is checked and stored in the keyring if it's given in
+/// ENVX_PASSPHRASE or with --passphrase-stdin.
+#[derive(Parser)]
+pub struct Args {
+    /// Files containing the backup or its shares
+    files: Vec<PathBuf>,
+
+    /// Read the passphrase of the key from the first line of stdin
+    #[clap(long)]
+    passphrase_stdin: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let config = get_config()?;
+
+    let passphrase = if args.passphrase_stdin {
+        if args.files.is_empty() {
+            bail!("Give the backup as files when using --passphrase-stdin");
+        }
+        read_stdin_lines(1)?.pop()
+    } else {
+        env::var(ENVX_PASSPHRASE)
+    };
+
+    let text = if args.files.is_empty() {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .context("Failed to read backup from stdin")?;
+        text
+    } else {
+        args.files
+            .iter()
+            .map(|path| {
+                fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read {}", path.display())
+                })
+            })
+            .collect::<Result<Vec<String>>>()?
+            .join("\n")
+    };
+
+    let backups = Backup::decode_all(&text)?;
+    let backup = Backup::combine(&backups)?;
+
+    let seckey = SignedSecretKey::from_bytes(backup.data.as_slice())
+        .context("Failed to parse the restored secret key")?;
+    let fingerprint = seckey.fingerprint().to_hex();
+
+    if !fingerprint.eq_ignore_ascii_case(&backup.fingerprint) {
+        bail!(
+            "The restored key has fingerprint {}, expected {}",
+            fingerprint,
+            backup.fingerprint
+        );
+    }
+
+    if let Some(passphrase) = &passphrase {
+        seckey
+            .unlock(|| passphrase.clone(), |_| Ok(()))
+            .context("Failed to unlock key, is the passphrase correct?")?;
+    }
+
+    if config.keys.iter().any(|k| k.fingerprint == fingerprint) {
+        bail!("Key {} already exists", fingerprint);
+    }
+
+    let priv_key = seckey
+        .to_armored_string(None)
+        .context("Failed to convert private key to armored ASCII string")?;
+    let pub_key = public_key_from_secret(&seckey)
+        .to_armored_string(None)
+        .context("Failed to convert public key to armored ASCII string")?;
+
+    vault::write(&fingerprint, vault::PRIVATE_KEY, priv_key.as_bytes())
+        .context("Failed to write private key")?;
+    vault::write(&fingerprint, vault::PUBLIC_KEY, pub_key.as_bytes())
+        .context("Failed to write public key")?;
+
+    if let Some(passphrase) = &passphrase {
+        if let Err(e) = set_password(&fingerprint, passphrase) {
+            eprintln!("Failed to store the passphrase in the keyring: {}", e);
+        }
+    }
+
+    update_config(|config| {
+        if config.keys.iter().any(|k| k.fingerprint == fingerprint) {
+            bail!("Key {} already exists", fingerprint);
+        }
+
+        config.keys.push(Key {
+            fingerprint: fingerprint.clone(),
+            note: "".to_string(),
+            primary_user_id: backup.primary_user_id,
+            hashed_note: backup.hashed_note,
+            pubkey_only: None,
+            uuid: backup.uuid,
+            revoked: None,
+        });
+
+        if config.primary_key.is_empty() {
+            info!("Setting primary key to {}...", &fingerprint);
+            config.primary_key = fingerprint.clone();
+        }
+        Ok(())
+    })
+    .context("Failed to write config")?;
+
+    info!("{}", format!("Restored key {}", fingerprint).green());
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use super::*;
+use crate::info;
+use crate::interact_or;
+use crate::{
+    sdk::SDK,
+    utils::{
+        config::{get_config, update_config},
+        keyring::try_get_password,
+        known_keys::trusted_public_keys,
+        prompt::{prompt_confirm, prompt_select},
+        rpgp::{generate_revocation_certificate, get_key, revoke_public_key},
+    },
+};
+use anyhow::bail;
+
+/// Revoke a key, remove it from all projects and publish the revocation
+#[derive(Parser)]
+pub struct Args {
+    /// Partial fingerprint of the key to revoke
+    #[clap(short, long)]
+    key: Option<String>,
+
+    /// Don't prompt for confirmation
+    #[clap(short, long)]
+    force: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let config = get_config()?;
+
+    let key = match args.key {
+        Some(key) => config.get_key(&key)?,
+        None => {
+            interact_or!("No key given, pass it with --key");
+            prompt_select("Select key to revoke", config.keys.clone())?
+        }
+    };
+
+    if key.is_revoked() {
+        info!("Key {} is already revoked", key.fingerprint);
+        return Ok(());
+    }
+
+    info!("This will permanently revoke {}", key);
+    info!("The key will be removed from all of its projects.");
+    if !args.force {
+        interact_or!("Pass --force to revoke without confirmation");
+    }
+    if !args.force && !prompt_confirm("Are you sure you want to continue?")? {
+        info!("Aborting...");
+        return Ok(());
+    }
+
+    let certificate = match key.revocation_certificate() {
+        Ok(certificate) => certificate,
+        Err(_) => {
+            info!("No revocation certificate found, generating one...");
+            let (seckey, fingerprint) = get_key(key.fingerprint.clone())?;
+            let passphrase = try_get_password(&fingerprint, &config)?;
+            generate_revocation_certificate(&seckey, passphrase)?
+        }
+    };
+
+    let revoked_public_key =
+        revoke_public_key(&key.public_key()?, &certificate)?;
+
+    let mut failed = vec![];
+    if let Some(uuid) = &key.uuid {
+        // Check every project before changing any of them, so an untrusted
+        // member doesn't leave the key removed from only some projects
+        let mut projects = vec![];
+        for project_id in SDK::list_projects(&key.fingerprint).await? {
+            let project_info =
+                SDK::get_project_info(&project_id, &key.fingerprint).await?;
+
+            let remaining = project_info
+                .users
+                .into_iter()
+                .filter(|u| &u.id != uuid)
+                .collect::<Vec<_>>();
+
+            if remaining.is_empty() {
+                info!(
+                    "{} You are the only member of {}, delete it with `envx delete project {}`",
+                    "Skipping:".yellow(),
+                    project_id,
+                    project_id
+                );
+                continue;
+            }
+
+            let pubkeys =
+                trusted_public_keys(&project_id, &remaining, &config)?;
+            projects.push((project_id, pubkeys));
+        }
+
+        for (project_id, pubkeys) in projects {
+            let removed = async {
+                let updated = SDK::rekey_variables(
+                    &project_id,
+                    &key.fingerprint,
+                    &pubkeys,
+                )
+                .await
+                .context("Failed to re-encrypt variables")?;
+                SDK::remove_users_from_project(
+                    &key.fingerprint,
+                    vec![uuid.clone()],
+                    &project_id,
+                )
+                .await?;
+                anyhow::Ok(updated.len())
+            }
+            .await;
+
+            match removed {
+                Ok(updated) => info!(
+                    "Removed key from project {} ({} variables re-encrypted)",
+                    project_id, updated
+                ),
+                Err(e) => {
+                    eprintln!(
+                        "{} project {}: {:#}",
+                        "Failed to remove key from".red(),
+                        project_id,
+                        e
+                    );
+                    failed.push(project_id);
+                }
+            }
+        }
+
+        // Publish even if some projects failed, the key must stop being
+        // trusted either way
+        SDK::revoke_key(&key.fingerprint, &revoked_public_key).await?;
+        info!("Published revocation to the server");
+    } else {
+        info!("Key {} is not on the server", key.fingerprint);
+    }
+
+    let config = update_config(|config| {
+        for k in config.keys.iter_mut() {
+            if k.fingerprint == key.fingerprint {
+                k.revoked = Some(true);
+            }
+        }
+        Ok(())
+    })?;
+
+    if config.primary_key == key.fingerprint {
+        info!("You have revoked your primary key.");
+        info!("Set a new primary key with `envx config set primary-key`");
+    }
+
+    info!("{}", format!("Revoked key {}", key.fingerprint).green());
+
+    if !failed.is_empty() {
+        bail!(
+            "The key is revoked but is still a member of {}, remove it with `envx project remove-user`",
+            failed.join(", ")
+        );
+    }
+
+    Ok(())
+}
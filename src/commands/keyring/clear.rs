@@ -3,6 +3,7 @@ use crate::utils::{
 };
 
 use super::*;
+use crate::interact_or;
 
 #[derive(Parser)]
 pub struct Args {
@@ -17,6 +18,7 @@ pub async fn command(args: Args) -> Result<()> {
     let fingerprint = match args.key {
         Some(key) => config.get_key(&key)?.fingerprint,
         None => {
+            interact_or!("No key given, pass it with --key");
             prompt_select("Select key to clear password", config.keys.clone())?
                 .fingerprint
         }
@@ -1,10 +1,16 @@
 use crate::utils::{
     config::get_config,
-    keyring::get_password,
+    duration::format_duration,
+    keyring::{get_password, session_expiry},
+    output,
     prompt::{prompt_confirm, prompt_select},
 };
+use chrono::{DateTime, Local, Utc};
+use serde::Serialize;
+use std::time::SystemTime;
 
 use super::*;
+use crate::{info, interact_or};
 
 #[derive(Parser)]
 pub struct Args {
@@ -17,32 +23,54 @@ pub struct Args {
     force: bool,
 }
 
+#[derive(Serialize)]
+struct Session<'a> {
+    fingerprint: &'a str,
+    password: &'a str,
+    expires_at: Option<DateTime<Utc>>,
+}
+
 pub async fn command(args: Args) -> Result<()> {
     let config = get_config()?;
 
     let fingerprint = match args.key {
         Some(key) => config.get_key(&key)?.fingerprint,
         None => {
+            interact_or!("No key given, pass it with --key");
             prompt_select("Select key to clear password", config.keys)?
                 .fingerprint
         }
     };
 
     let password = get_password(&fingerprint)?;
+    let expiry = session_expiry(&fingerprint);
 
-    if args.force {
-        println!("{}", password);
-        return Ok(());
+    if let Some(expiry) = expiry {
+        let remaining =
+            expiry.duration_since(SystemTime::now()).unwrap_or_default();
+        info!(
+            "Session expires in {} ({})",
+            format_duration(remaining),
+            DateTime::<Local>::from(expiry).format("%Y-%m-%d %H:%M")
+        );
     }
 
-    println!("This will print the saved password in PLAIN TEXT");
-    match prompt_confirm("Are you sure you want to continue?") {
-        Ok(true) => {
-            println!("Password:");
-            println!("{}", password)
+    if !args.force {
+        interact_or!("Pass --force to print the password without confirmation");
+        info!("This will print the saved password in PLAIN TEXT");
+        if !prompt_confirm("Are you sure you want to continue?")? {
+            info!("Aborting");
+            return Ok(());
         }
-        _ => println!("Aborting"),
     }
 
-    Ok(())
+    let session = Session {
+        fingerprint: &fingerprint,
+        password: &password,
+        expires_at: expiry.map(DateTime::<Utc>::from),
+    };
+    output::print(&session, |_| {
+        println!("{}", password);
+        Ok(())
+    })
 }
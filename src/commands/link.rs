@@ -1,6 +1,7 @@
 use super::*;
+use crate::info;
 use crate::utils::choice::Choice;
-use crate::utils::config::get_config;
+use crate::utils::config::{get_config, update_config};
 
 /// Get all environment variables for a project
 #[derive(Parser)]
@@ -19,27 +20,30 @@ pub struct Args {
 }
 
 pub async fn command(args: Args) -> Result<()> {
-    let mut config = get_config()?;
+    let config = get_config()?;
 
     let projects = &config.projects;
     let cwd = std::env::current_dir()?;
 
     if let Some(project) = projects.iter().find(|p| p.path == cwd) {
         if args.force {
-            println!("Forced new project");
-            println!("Unlinking current project...");
-            let old = config.unset_project()?;
-            config.write()?;
-            println!(
+            info!("Forced new project");
+            info!("Unlinking current project...");
+            let mut old = vec![];
+            update_config(|config| {
+                old = config.unset_project()?;
+                Ok(())
+            })?;
+            info!(
                 "{} {}",
                 "Unset project(s):".green(),
                 serde_json::to_string(&old)?
             );
         } else {
-            println!("A project is already linked to this directory");
-            println!("  Use `envcli unlink` to unlink the current project");
-            println!("  Or force a new project with `envcli link --force`");
-            println!("{} {}", "Current project:".green(), project.project_id);
+            info!("A project is already linked to this directory");
+            info!("  Use `envcli unlink` to unlink the current project");
+            info!("  Or force a new project with `envcli link --force`");
+            info!("{} {}", "Current project:".green(), project.project_id);
             return Ok(());
         }
     }
@@ -51,8 +55,7 @@ pub async fn command(args: Args) -> Result<()> {
         None => Choice::choose_project(&key.fingerprint).await?,
     };
 
-    config.set_project(&project_id)?;
-    config.write()?;
+    update_config(|config| config.set_project(&project_id))?;
 
     Ok(())
 }
@@ -0,0 +1,54 @@
+use super::*;
+use crate::info;
+use crate::utils::{
+    agent::{self, expect_ok, Request},
+    config::get_config,
+    keyfile,
+    keyring::clear_session,
+};
+
+/// Lock a key again, forgetting its stored passphrase
+#[derive(Parser)]
+pub struct Args {
+    /// Partial fingerprint of the key to lock, defaults to the primary key
+    #[clap(short, long, conflicts_with = "all")]
+    key: Option<String>,
+
+    /// Lock every key, and forget the passphrase of the encrypted keyring
+    /// file
+    #[clap(long)]
+    all: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let config = get_config()?;
+    let fingerprints = if args.all {
+        config.keys.iter().map(|k| k.fingerprint.clone()).collect()
+    } else {
+        vec![config.get_key_or_default(args.key)?.fingerprint]
+    };
+
+    if agent::is_running() {
+        let fingerprint = if args.all {
+            None
+        } else {
+            fingerprints.first().cloned()
+        };
+        expect_ok(agent::request(&Request::Lock { fingerprint })?)?;
+    }
+
+    for fingerprint in &fingerprints {
+        clear_session(fingerprint)?;
+    }
+    if args.all {
+        keyfile::forget_key()?;
+    }
+
+    if args.all {
+        info!("Locked all keys");
+    } else {
+        info!("Locked {}", fingerprints.join(", "));
+    }
+
+    Ok(())
+}
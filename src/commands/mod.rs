@@ -3,28 +3,39 @@ pub(super) use clap::Parser;
 pub(super) use colored::Colorize;
 
 // With subcommands
+pub mod agent;
 pub mod config;
+pub mod context;
 pub mod delete;
 pub mod get;
+pub mod key;
 pub mod keyring;
 pub mod new;
+pub mod plugins;
 pub mod project;
 
 // No subcommands
 pub mod auth;
+pub mod check;
+pub mod completions;
+pub mod copy;
 pub mod debug;
 pub mod decrypt;
+pub mod doctor;
 pub mod encrypt;
 pub mod export;
 pub mod gen;
+pub mod history;
 pub mod import;
 pub mod link;
 pub mod list_keys;
+pub mod lock;
 pub mod run;
 pub mod set;
 pub mod shell;
 pub mod sign;
 pub mod unlink;
+pub mod unlock;
 pub mod unset;
 pub mod upload;
 pub mod variables;
@@ -15,6 +15,7 @@ pub struct Args {
     #[clap(subcommand)]
     command: Commands,
 
+    /// Same as `--output json`
     #[clap(global = true, long)]
     json: bool,
 }
@@ -22,6 +23,7 @@ pub struct Args {
 commands_enum!(project);
 
 pub async fn command(args: Args) -> Result<()> {
+    crate::utils::output::json_if(args.json);
     Commands::exec(args).await?;
     Ok(())
 }
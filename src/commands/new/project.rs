@@ -1,5 +1,9 @@
 use super::*;
-use crate::{sdk::SDK, utils::config::get_config};
+use crate::{
+    sdk::SDK,
+    utils::{config::get_config, output, settings::OutputFormat},
+};
+use serde::Serialize;
 
 /// Create a new project
 #[derive(Parser)]
@@ -9,10 +13,27 @@ pub struct Args {
     key: Option<String>,
 }
 
+#[derive(Serialize)]
+struct NewProject<'a> {
+    id: &'a str,
+}
+
 pub async fn command(args: Args) -> Result<()> {
     let config = get_config()?;
     let key = config.get_key_or_default(args.key)?;
     let new_project_id = SDK::new_project(&key.fingerprint).await?;
-    println!("Created new project with ID: {}", new_project_id);
-    Ok(())
+    output::print(
+        &NewProject {
+            id: &new_project_id,
+        },
+        |format| {
+            match format {
+                OutputFormat::Plain => println!("{}", new_project_id),
+                _ => {
+                    println!("Created new project with ID: {}", new_project_id)
+                }
+            }
+            Ok(())
+        },
+    )
 }
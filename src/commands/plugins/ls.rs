@@ -0,0 +1,26 @@
+use super::*;
+use crate::utils::{output, plugin, settings::OutputFormat};
+
+/// List the plugins found on PATH
+#[derive(Parser)]
+pub struct Args {}
+
+pub async fn command(_args: Args) -> Result<()> {
+    let plugins = plugin::list();
+
+    output::print(&plugins, |format| {
+        if plugins.is_empty() && format == OutputFormat::Table {
+            println!(
+                "No plugins found, name them envx-<name> and put them on PATH"
+            );
+        }
+        for plugin in &plugins {
+            if format == OutputFormat::Plain {
+                println!("{}\t{}", plugin.name, plugin.path.display());
+                continue;
+            }
+            println!("{} {}", plugin.name.bold(), plugin.path.display());
+        }
+        Ok(())
+    })
+}
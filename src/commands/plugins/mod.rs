@@ -0,0 +1,25 @@
+#[allow(unused_imports)]
+pub(super) use anyhow::{anyhow, Context, Result};
+pub(super) use clap::Parser;
+#[allow(unused_imports)]
+pub(super) use colored::Colorize;
+
+use crate::commands_enum;
+use clap::Subcommand;
+
+pub mod ls;
+
+/// Manage plugins, `envx-<name>` executables on PATH run as `envx <name>`.
+/// (ls)
+#[derive(Parser)]
+pub struct Args {
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+commands_enum!(ls);
+
+pub async fn command(args: Args) -> Result<()> {
+    Commands::exec(args).await?;
+    Ok(())
+}
@@ -1,17 +1,18 @@
 use super::*;
+use crate::info;
+use crate::interact_or;
 use crate::{
-    sdk::{get_api_url, SDK},
+    sdk::SDK,
+    types::User,
     utils::{
-        auth::get_token, choice::Choice, config::get_config,
-        partial_variable::PartialVariable, prompt::prompt_text,
-        rpgp::encrypt_multi,
+        choice::Choice,
+        config::get_config,
+        known_keys::{self, format_fingerprint, trusted_public_keys},
+        prompt::{prompt_confirm, prompt_text},
     },
 };
-use pgp::{Deserializable, SignedPublicKey};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use reqwest::header;
-use serde_json::json;
-use std::collections::HashSet;
+use anyhow::bail;
+use pgp::types::KeyTrait;
 
 /// Add a user to a project
 #[derive(Parser)]
@@ -27,22 +28,30 @@ pub struct Args {
     /// User ID to add to project
     #[clap(short, long)]
     user_id: Option<String>,
+
+    /// Trust the key of the user without asking, after verifying its
+    /// fingerprint some other way
+    #[clap(short, long)]
+    yes: bool,
 }
 
 pub async fn command(args: Args) -> Result<()> {
     let user_id = match args.user_id {
         Some(u) => u,
-        None => prompt_text("User ID: ")?,
+        None => {
+            interact_or!("No user given, pass it with --user-id");
+            prompt_text("User ID: ")?
+        }
     };
     let user_id = user_id.trim().to_string();
 
     let config = get_config()?;
     let key = config.get_key_or_default(args.key)?;
 
-    let uuid = key
-        .uuid
+    key.uuid
+        .as_ref()
         .context("Key does not have a UUID, try `envx upload`")?;
-    let (_, public_key) = SDK::get_user(&key.fingerprint, &user_id)
+    let public_key = SDK::get_user(&key.fingerprint, &user_id)
         .await
         .context("Failed to get user, is the user ID correct?")?;
 
@@ -52,64 +61,55 @@ pub async fn command(args: Args) -> Result<()> {
     let project_info =
         SDK::get_project_info(&project_id, &key.fingerprint).await?;
 
-    let (kvpairs, mut partials) =
-        SDK::get_variables(&project_id, &key.fingerprint).await?;
-
-    let mut recipients = project_info
-        .users
+    let mut pubkeys =
+        trusted_public_keys(&project_id, &project_info.users, &config)?;
+
+    let new_member = known_keys::check(
+        &project_id,
+        &[User {
+            id: user_id.clone(),
+            username: user_id.clone(),
+            created_at: "".into(),
+            public_key,
+        }],
+        &config,
+    )?
+    .remove(0);
+
+    if !new_member.is_trusted() {
+        info!("Adding user {} with key:", user_id);
+        info!("  {}", format_fingerprint(&new_member.fingerprint));
+        if !args.yes {
+            interact_or!(
+                "Verify the fingerprint with the user, then pass --yes to trust it"
+            );
+            info!("Verify this fingerprint with the user before continuing.");
+            if !prompt_confirm("Is this fingerprint correct?")? {
+                bail!("Aborted");
+            }
+        }
+    }
+
+    if !pubkeys
         .iter()
-        .map(|e| e.public_key.clone())
-        .collect::<Vec<String>>();
+        .any(|k| k.fingerprint() == new_member.public_key.fingerprint())
+    {
+        pubkeys.push(new_member.public_key.clone());
+    }
 
-    recipients.push(public_key);
+    let res =
+        SDK::rekey_variables(&project_id, &key.fingerprint, &pubkeys).await?;
 
-    let recipients = recipients
-        .into_iter()
-        .collect::<HashSet<String>>()
-        .into_iter()
-        .collect::<Vec<String>>();
-
-    let pubkeys = recipients
-        .iter()
-        .map(|k| Ok(SignedPublicKey::from_string(k)?.0))
-        .collect::<Result<Vec<SignedPublicKey>>>()?;
-
-    let messages = kvpairs
-        .par_iter()
-        .map(|k| encrypt_multi(&k.to_json()?, &pubkeys))
-        .collect::<Result<Vec<String>>>()?;
-
-    let partials = partials
-        .iter_mut()
-        .zip(messages.iter())
-        .map(|(p, m)| {
-            p.value = m.into();
-            p
-        })
-        .collect::<Vec<&mut PartialVariable>>();
-
-    let body = json!({
-        "variables": partials,
-    });
-
-    let client = reqwest::Client::new();
-    let auth_token = get_token(&key.fingerprint, &uuid).await?;
-
-    let url = get_api_url().join("/variables/update-many")?;
-
-    let res = client
-        .post(url)
-        .header(header::AUTHORIZATION, format!("Bearer {}", auth_token))
-        .json(&body)
-        .send()
-        .await?
-        .json::<Vec<String>>()
-        .await?;
-
-    println!("Updated {} variables", res.len());
-    println!("IDs: {:?}", res);
+    info!("Updated {} variables", res.len());
+    info!("IDs: {:?}", res);
 
     SDK::add_user_to_project(&key.fingerprint, &user_id, &project_id).await?;
+    if !new_member.is_trusted() {
+        known_keys::update(|k| {
+            k.pin_one(&project_id, &new_member);
+            Ok(())
+        })?;
+    }
 
     Ok(())
 }
@@ -1,6 +1,6 @@
 use super::*;
 use crate::types::PartialUser;
-use crate::utils::config::get_config;
+use crate::utils::{config::get_config, output, settings::OutputFormat};
 use crate::{sdk::SDK, utils::choice::Choice};
 
 /// Get all environment variables for a project
@@ -14,7 +14,7 @@ pub struct Args {
     #[clap(short, long)]
     project_id: Option<String>,
 
-    /// Output in JSON format
+    /// Same as `--output json`
     #[clap(long)]
     json: bool,
 
@@ -23,8 +23,8 @@ pub struct Args {
     all: bool,
 }
 
-// TODO: Pretty print project info (in a table?)
 pub async fn command(args: Args) -> Result<()> {
+    output::json_if(args.json);
     let config = get_config()?;
     let key = config.get_key_or_default(args.key)?;
     let project_id =
@@ -32,39 +32,32 @@ pub async fn command(args: Args) -> Result<()> {
     let project_info =
         SDK::get_project_info(&project_id, &key.fingerprint).await?;
 
-    if args.json && args.all {
-        println!("{}", serde_json::to_string(&project_info.users)?);
-        return Ok(());
-    }
-
-    if args.json {
-        println!(
-            "{}",
-            serde_json::to_string(
-                &project_info
-                    .users
-                    .iter()
-                    .map(|u| u.clone().into())
-                    .collect::<Vec<PartialUser>>()
-            )?
-        );
-        return Ok(());
+    if args.all {
+        return output::print(&project_info.users, |_| {
+            for user in project_info.users.iter() {
+                println!(
+                    "{} - {} - {} - {}",
+                    user.username, user.id, user.created_at, user.public_key
+                );
+            }
+            Ok(())
+        });
     }
 
-    if args.all {
+    let users = project_info
+        .users
+        .iter()
+        .map(|u| u.clone().into())
+        .collect::<Vec<PartialUser>>();
+    output::print(&users, |format| {
         for user in project_info.users.iter() {
-            println!(
-                "{} - {} - {} - {}",
-                user.username, user.id, user.created_at, user.public_key
-            );
+            match format {
+                OutputFormat::Plain => {
+                    println!("{}\t{}", user.id, user.username)
+                }
+                _ => println!("{}", user),
+            }
         }
-        println!("{:?}", &project_info.users);
-        return Ok(());
-    }
-
-    for user in project_info.users.iter() {
-        println!("{}", user);
-    }
-
-    Ok(())
+        Ok(())
+    })
 }
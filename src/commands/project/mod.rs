@@ -7,6 +7,7 @@ pub(super) use colored::Colorize;
 pub mod add_user;
 pub mod list_users;
 pub mod remove_user;
+pub mod trust;
 
 use crate::commands_enum;
 use clap::Subcommand;
@@ -17,13 +18,15 @@ pub struct Args {
     #[clap(subcommand)]
     command: Commands,
 
+    /// Same as `--output json`
     #[clap(global = true, long)]
     json: bool,
 }
 
-commands_enum!(add_user, remove_user, list_users);
+commands_enum!(add_user, remove_user, list_users, trust);
 
 pub async fn command(args: Args) -> Result<()> {
+    crate::utils::output::json_if(args.json);
     Commands::exec(args).await?;
     Ok(())
 }
@@ -1,19 +1,14 @@
-use std::collections::HashSet;
-
-use anyhow::{Context, Result};
+use crate::info;
+use anyhow::Context;
 use clap::Parser;
-use pgp::{Deserializable, SignedPublicKey};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use reqwest::header;
-use serde_json::json;
 
+use crate::interact_or;
 use crate::{
-    sdk::{get_api_url, SDK},
+    sdk::SDK,
     types::User,
     utils::{
-        auth::get_token, choice::Choice, config::get_config,
-        partial_variable::PartialVariable, prompt::prompt_multi_options,
-        rpgp::encrypt_multi,
+        choice::Choice, config::get_config, known_keys::trusted_public_keys,
+        prompt::prompt_multi_options,
     },
 };
 
@@ -37,8 +32,8 @@ pub async fn command(args: Args) -> anyhow::Result<()> {
     let config = get_config()?;
     let key = config.get_key_or_default(args.key)?;
 
-    let uuid = key
-        .uuid
+    key.uuid
+        .as_ref()
         .context("Key does not have a UUID, try `envx upload`")?;
 
     let project_id =
@@ -49,6 +44,7 @@ pub async fn command(args: Args) -> anyhow::Result<()> {
     let users_to_remove = match args.user_id {
         Some(u) => vec![u],
         None => {
+            interact_or!("No user given, pass it with --user-id");
             let users = prompt_multi_options(
                 "Users to Remove",
                 project_info.users.clone(),
@@ -57,75 +53,33 @@ pub async fn command(args: Args) -> anyhow::Result<()> {
         }
     };
 
-    let (kvpairs, mut partials) =
-        SDK::get_variables(&project_id, &key.fingerprint).await?;
-
     let users_without_users_to_remove = project_info
         .users
         .into_iter()
         .filter(|u| !users_to_remove.contains(&u.id))
         .collect::<Vec<User>>();
 
-    let recipients = users_without_users_to_remove
-        .iter()
-        .map(|e| e.public_key.clone())
-        .collect::<Vec<String>>();
-
-    let recipients = recipients
-        .into_iter()
-        .collect::<HashSet<String>>()
-        .into_iter()
-        .collect::<Vec<String>>();
-
-    let pubkeys = recipients
-        .iter()
-        .map(|k| Ok(SignedPublicKey::from_string(k)?.0))
-        .collect::<Result<Vec<SignedPublicKey>>>()?;
-
-    let messages = kvpairs
-        .par_iter()
-        .map(|k| encrypt_multi(&k.to_json()?, &pubkeys))
-        .collect::<Result<Vec<String>>>()?;
-
-    let partials = partials
-        .iter_mut()
-        .zip(messages.iter())
-        .map(|(p, m)| {
-            p.value = m.into();
-            p
-        })
-        .collect::<Vec<&mut PartialVariable>>();
-
-    let body = json!({
-        "variables": partials,
-    });
-
-    let client = reqwest::Client::new();
-    let auth_token = get_token(&key.fingerprint, &uuid).await?;
-
-    let url = get_api_url().join("/variables/update-many")?;
+    let pubkeys = trusted_public_keys(
+        &project_id,
+        &users_without_users_to_remove,
+        &config,
+    )?;
 
-    let res = client
-        .post(url)
-        .header(header::AUTHORIZATION, format!("Bearer {}", auth_token))
-        .json(&body)
-        .send()
-        .await?
-        .json::<Vec<String>>()
-        .await?;
+    let res =
+        SDK::rekey_variables(&project_id, &key.fingerprint, &pubkeys).await?;
 
-    println!("Updated {} variables", res.len());
-    println!("IDs: {:?}", res);
+    info!("Updated {} variables", res.len());
+    info!("IDs: {:?}", res);
 
     SDK::remove_users_from_project(
         &key.fingerprint,
         users_to_remove.clone(),
-        &project_info.project_id,
+        &project_info.id,
     )
     .await?;
 
-    println!("Successfully removed users from project");
-    println!("Users removed: {:?}", users_to_remove);
+    info!("Successfully removed users from project");
+    info!("Users removed: {:?}", users_to_remove);
 
     Ok(())
 }
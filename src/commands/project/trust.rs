@@ -0,0 +1,73 @@
+use super::*;
+use crate::info;
+use crate::interact_or;
+use crate::{
+    sdk::SDK,
+    utils::{
+        choice::Choice, config::get_config, known_keys, prompt::prompt_confirm,
+    },
+};
+
+/// Review and pin the public keys of all project members
+#[derive(Parser)]
+pub struct Args {
+    /// Partial fingerprint of key to use
+    #[clap(short, long)]
+    key: Option<String>,
+
+    /// Project ID
+    #[clap(short, long)]
+    project_id: Option<String>,
+
+    /// Trust all keys without prompting for confirmation
+    #[clap(short, long)]
+    yes: bool,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let config = get_config()?;
+    let key = config.get_key_or_default(args.key)?;
+    let project_id =
+        Choice::try_project(args.project_id, &key.fingerprint).await?;
+    let project_info =
+        SDK::get_project_info(&project_id, &key.fingerprint).await?;
+
+    let members = known_keys::check(&project_id, &project_info.users, &config)?;
+
+    info!("Members of project {}:", project_id);
+    for member in members.iter() {
+        info!("  {}", known_keys::describe(member));
+    }
+
+    if members.iter().all(|m| m.is_trusted()) {
+        info!("{}", "All member keys are already trusted".green());
+        known_keys::update(|k| {
+            k.pin(&project_id, &members);
+            Ok(())
+        })?;
+        return Ok(());
+    }
+
+    info!(
+        "\nVerify the fingerprints marked {} or {} with their owners out of band.",
+        "NEW".yellow(),
+        "CHANGED".red().bold()
+    );
+
+    if !args.yes {
+        interact_or!("Pass --yes to trust these keys without confirmation");
+    }
+    if !args.yes && !prompt_confirm("Trust these keys?")? {
+        info!("Aborting...");
+        return Ok(());
+    }
+
+    known_keys::update(|k| {
+        k.pin(&project_id, &members);
+        Ok(())
+    })?;
+
+    info!("{}", "Pinned member keys".green());
+
+    Ok(())
+}
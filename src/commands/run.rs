@@ -1,5 +1,6 @@
 use super::*;
 use crate::utils::choice::Choice;
+use crate::utils::project_file::ProjectFile;
 use anyhow::bail;
 use std::collections::BTreeMap;
 
@@ -36,10 +37,21 @@ pub async fn command(args: Args) -> Result<()> {
     let mut all_variables = BTreeMap::<String, String>::new();
     all_variables.insert("IN_ENVCLI_SHELL".to_owned(), "true".to_owned());
 
-    let variables =
+    let mut variables =
         crate::sdk::SDK::get_variables_pruned(&project_id, &key.fingerprint)
             .await?;
 
+    if let Some(project_file) = ProjectFile::find()? {
+        project_file.enforce(
+            "run",
+            &variables
+                .iter()
+                .map(|v| (v.key.as_str(), v.value.as_str()))
+                .collect(),
+        )?;
+        variables = project_file.filter("run", variables, |v| &v.key)?;
+    }
+
     for variable in variables {
         all_variables.insert(variable.key, variable.value);
     }
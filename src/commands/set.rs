@@ -1,6 +1,7 @@
 use anyhow::bail;
 
 use super::*;
+use crate::info;
 use crate::{
     sdk::SDK,
     utils::{choice::Choice, config::get_config, kvpair::KVPair},
@@ -58,7 +59,7 @@ pub async fn command(args: Args) -> Result<()> {
             (ok, err)
         });
 
-    errors.iter().for_each(|e| println!("Skipping {}", e));
+    errors.iter().for_each(|e| info!("Skipping {}", e));
 
     if kvpairs.is_empty() {
         return Err(anyhow::anyhow!("No valid KV pairs provided"));
@@ -66,8 +67,8 @@ pub async fn command(args: Args) -> Result<()> {
 
     let ids = SDK::set_many(kvpairs, &key.fingerprint, &project_id).await?;
 
-    println!("Uploaded {} variables", ids.len());
-    println!("IDs: {:?}", ids);
+    info!("Uploaded {} variables", ids.len());
+    info!("IDs: {:?}", ids);
 
     Ok(())
 }
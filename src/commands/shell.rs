@@ -1,6 +1,8 @@
 use crate::utils::choice::Choice;
+use crate::utils::project_file::ProjectFile;
 
 use super::*;
+use crate::info;
 use std::collections::BTreeMap;
 use std::vec;
 
@@ -34,9 +36,6 @@ pub struct Args {
     /// Key to use for signing
     #[clap(short, long)]
     key: Option<String>,
-
-    #[clap(short, long)]
-    silent: bool,
 }
 
 pub async fn command(args: Args) -> Result<()> {
@@ -57,10 +56,21 @@ pub async fn command(args: Args) -> Result<()> {
     let mut all_variables = BTreeMap::<String, String>::new();
     all_variables.insert("IN_ENVCLI_SHELL".to_owned(), "true".to_owned());
 
-    let variables =
+    let mut variables =
         crate::sdk::SDK::get_variables_pruned(&project_id, &key.fingerprint)
             .await?;
 
+    if let Some(project_file) = ProjectFile::find()? {
+        project_file.enforce(
+            "shell",
+            &variables
+                .iter()
+                .map(|v| (v.key.as_str(), v.value.as_str()))
+                .collect(),
+        )?;
+        variables = project_file.filter("shell", variables, |v| &v.key)?;
+    }
+
     for variable in variables {
         all_variables.insert(variable.key, variable.value);
     }
@@ -84,9 +94,7 @@ pub async fn command(args: Args) -> Result<()> {
         _ => vec![],
     };
 
-    if !args.silent {
-        println!("Entering subshell with envcli variables available. Type 'exit' to exit.\n");
-    }
+    info!("Entering subshell with envcli variables available. Type 'exit' to exit.\n");
 
     // a bit janky :/
     ctrlc::set_handler(move || {
@@ -103,7 +111,7 @@ pub async fn command(args: Args) -> Result<()> {
         .await
         .context("Failed to wait for command")?;
 
-    println!("Exited subshell, envcli variables no longer available.");
+    info!("Exited subshell, envcli variables no longer available.");
     Ok(())
 }
 
@@ -1,8 +1,11 @@
-use crate::utils::{config::get_config, keyring::try_get_password};
+use crate::utils::{
+    agent, config::get_config, keyring::try_get_password, output, rpgp,
+};
+use serde::Serialize;
 
 use super::*;
 use anyhow::Ok;
-use pgp::{composed, crypto, Deserializable, SignedSecretKey};
+use pgp::{composed, Deserializable, SignedSecretKey};
 
 /// Sign a message with a key
 #[derive(Parser)]
@@ -15,6 +18,12 @@ pub struct Args {
     message: String,
 }
 
+#[derive(Serialize)]
+struct Signed {
+    message: String,
+    signature: String,
+}
+
 pub async fn command(args: Args) -> Result<()> {
     let config = get_config().context("Failed to get config")?;
 
@@ -24,20 +33,27 @@ pub async fn command(args: Args) -> Result<()> {
         .find(|k| k.fingerprint.contains(&args.key))
         .ok_or_else(|| anyhow!("Key not found"))?;
 
-    let key = SignedSecretKey::from_string(&key.secret_key()?)?.0;
-
-    let message =
-        composed::message::Message::new_literal("none", &args.message);
-
-    println!("{}", message.to_armored_string(None)?);
-
-    let passphrase = try_get_password(&args.key, &config)?;
-    let pw = || passphrase;
-
-    let signature =
-        message.sign(&key, pw, crypto::hash::HashAlgorithm::SHA3_512)?;
-
-    println!("{}", signature.to_armored_string(None)?);
-
-    Ok(())
+    let armored =
+        composed::message::Message::new_literal("none", &args.message)
+            .to_armored_string(None)?;
+
+    let signature = match agent::sign(&key.fingerprint, &args.message) {
+        Some(signature) => signature?,
+        None => {
+            let passphrase = try_get_password(&key.fingerprint, &config)?;
+            let key = SignedSecretKey::from_string(&key.secret_key()?)?.0;
+            rpgp::sign_message(&args.message, &key, passphrase)?
+                .to_armored_string(None)?
+        }
+    };
+
+    let signed = Signed {
+        message: armored,
+        signature,
+    };
+    output::print(&signed, |_| {
+        println!("{}", signed.message);
+        println!("{}", signed.signature);
+        Ok(())
+    })
 }
@@ -1,22 +1,24 @@
 use super::*;
-use crate::utils::config::get_config;
+use crate::info;
+use crate::utils::config::update_config;
 
 /// Unset the current project
 #[derive(Parser)]
 pub struct Args {}
 
 pub async fn command(_args: Args) -> Result<()> {
-    let mut config = get_config()?;
-
-    let unset = config.unset_project()?;
-    config.write()?;
+    let mut unset = vec![];
+    update_config(|config| {
+        unset = config.unset_project()?;
+        Ok(())
+    })?;
 
     // There should only ever be one project unset
     // but the unset command unsets all projects that match the current directory
 
-    println!("{}", "Unset project(s):".green());
+    info!("{}", "Unset project(s):".green());
     for project in unset {
-        println!("  {}", project);
+        info!("  {}", project);
     }
 
     // println!("{} {}", "Unset project:".green(), unset[0]);
@@ -0,0 +1,84 @@
+use super::*;
+use crate::info;
+use crate::interact_or;
+use crate::utils::{
+    agent::{self, expect_ok, Request},
+    config::get_config,
+    duration::{format_duration, parse_duration},
+    env::{self, ENVX_PASSPHRASE},
+    keyring::set_password_for,
+    prompt::prompt_password,
+    rpgp::get_key,
+};
+use anyhow::bail;
+use chrono::{DateTime, Local};
+use pgp::types::SecretKeyTrait;
+use std::time::SystemTime;
+
+/// Unlock a key so envx doesn't ask for its passphrase for a while
+#[derive(Parser)]
+pub struct Args {
+    /// Partial fingerprint of the key to unlock, defaults to the primary key
+    #[clap(short, long)]
+    key: Option<String>,
+
+    /// How long to stay unlocked, e.g. `8h` (default: the `session_ttl`
+    /// setting)
+    #[clap(long)]
+    ttl: Option<String>,
+}
+
+pub async fn command(args: Args) -> Result<()> {
+    let config = get_config()?;
+    let key = config.get_key_or_default(args.key)?;
+    let ttl = match &args.ttl {
+        Some(ttl) => parse_duration(ttl)?,
+        None => config.get_settings()?.session_ttl()?,
+    };
+
+    let passphrase = match env::var(ENVX_PASSPHRASE) {
+        Some(passphrase) => passphrase,
+        None => {
+            interact_or!("No passphrase given, set {}", ENVX_PASSPHRASE);
+            prompt_password(&format!("Passphrase for {}", key))?
+        }
+    };
+
+    let (seckey, fingerprint) = get_key(key.fingerprint.clone())?;
+    if seckey.unlock(|| passphrase.clone(), |_| Ok(())).is_err() {
+        bail!("Wrong passphrase for {}", fingerprint);
+    }
+
+    let agent_running = agent::is_running();
+    if agent_running {
+        expect_ok(agent::request(&Request::Unlock {
+            fingerprint: fingerprint.clone(),
+            passphrase: passphrase.clone(),
+        })?)?;
+    }
+
+    if let Err(e) = set_password_for(&fingerprint, &passphrase, ttl) {
+        if !agent_running {
+            bail!(
+                "Failed to store the passphrase in the keyring: {}\nStart `envx agent` to keep keys unlocked without a keyring",
+                e
+            );
+        }
+        info!("Unlocked {} in the agent", fingerprint);
+        return Ok(());
+    }
+
+    let until = DateTime::<Local>::from(SystemTime::now() + ttl);
+    info!(
+        "{}",
+        format!(
+            "Unlocked {} for {} (until {})",
+            fingerprint,
+            format_duration(ttl),
+            until.format("%Y-%m-%d %H:%M")
+        )
+        .green()
+    );
+
+    Ok(())
+}
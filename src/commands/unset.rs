@@ -1,4 +1,5 @@
 use super::*;
+use crate::interact_or;
 use crate::utils::prompt;
 use crate::{sdk::SDK, utils::config::get_config};
 use anyhow::Context;
@@ -22,6 +23,7 @@ pub async fn command(args: Args) -> Result<()> {
     let variable = match args.variable {
         Some(v) => v,
         None => {
+            interact_or!("No variable given, pass it with --variable");
             let (_, all_variables) =
                 SDK::get_all_variables(&key.fingerprint).await?;
             prompt::prompt_options("Select variables to delete", all_variables)?
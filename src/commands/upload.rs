@@ -1,8 +1,14 @@
 use super::*;
+use crate::interact_or;
 use crate::{
     sdk::SDK,
-    utils::{config::get_config, prompt::prompt_text},
+    utils::{
+        config::{get_config, update_config},
+        output,
+        prompt::prompt_text,
+    },
 };
+use serde::Serialize;
 
 /// If your key is not in the database, use this command to upload it
 #[derive(Parser)]
@@ -16,26 +22,30 @@ pub struct Args {
     username: Option<String>,
 }
 
+#[derive(Serialize)]
+struct Uploaded<'a> {
+    uuid: &'a str,
+}
+
 pub async fn command(args: Args) -> Result<()> {
-    let mut config = get_config()?;
+    let config = get_config()?;
 
     let key = config.get_key(&args.key)?;
 
     let username = match args.username {
         Some(u) => u,
-        None => prompt_text("Username: ")?,
+        None => {
+            interact_or!("No username given, pass it with --username");
+            prompt_text("Username: ")?
+        }
     };
 
     let id = SDK::new_user(&username, &key.public_key()?).await?;
-    println!("UUID: {}", &id);
-
-    for k in config.keys.iter_mut() {
-        if k.fingerprint == key.fingerprint {
-            k.uuid = Some(id.clone());
-        }
-    }
 
-    config.write()?;
+    update_config(|config| config.set_uuid(&key.fingerprint, &id))?;
 
-    Ok(())
+    output::print(&Uploaded { uuid: &id }, |_| {
+        println!("UUID: {}", &id);
+        Ok(())
+    })
 }
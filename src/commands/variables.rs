@@ -2,7 +2,9 @@ use super::*;
 use crate::{
     sdk::SDK,
     utils::{
-        btreemap::ToBTreeMap, choice::Choice, config::get_config, table::Table,
+        btreemap::ToBTreeMap, choice::Choice, config::get_config, output,
+        partial_variable::ToKVPair, project_file::ProjectFile,
+        settings::OutputFormat, signing, table::Table,
     },
 };
 /// Get all environment variables for the current configured directory
@@ -14,57 +16,64 @@ pub struct Args {
     #[clap(short, long)]
     project_id: Option<String>,
 
-    /// Output as JSON - JSON has the highest precedence and will override other output formats
+    /// Same as `--output json`
     #[clap(long)]
     json: bool,
 
-    /// Output as a list of key=value pairs
+    /// Output as a list of key=value pairs, same as `--output plain`
     #[clap(long)]
     kv: bool,
+
+    /// Show the values in the table instead of masking them, secret ones
+    /// included
+    #[clap(long)]
+    reveal: bool,
 }
 
 pub async fn command(args: Args) -> Result<()> {
-    let mode = Mode::from_args(&args);
+    output::json_if(args.json);
+    if args.kv {
+        output::set_format(OutputFormat::Plain);
+    }
 
     let config = get_config()?;
     let key = config.get_key_or_default(args.key)?;
     let project_id =
         Choice::try_project(args.project_id, &key.fingerprint).await?;
-    let mut kvpairs =
-        SDK::get_variables_pruned(&project_id, &key.fingerprint).await?;
-    kvpairs.sort_by(|a, b| a.key.cmp(&b.key));
-
-    match mode {
-        Mode::KV => {
-            kvpairs.iter().for_each(|kv| println!("{}", kv));
-        }
-        Mode::Json => {
-            let btreemap = kvpairs.to_btreemap()?;
-            println!("{}", serde_json::to_string_pretty(&btreemap)?);
-        }
-        Mode::Table => {
-            let btreemap = kvpairs.to_btreemap()?;
-            Table::new("Variables".into(), btreemap).print()?;
-        }
+    let mut variables =
+        SDK::get_parsed_variables_pruned(&project_id, &key.fingerprint).await?;
+    let project_file = ProjectFile::find()?;
+    if let Some(project_file) = &project_file {
+        variables =
+            project_file.filter("variables", variables, |v| &v.value.key)?;
     }
+    let is_secret =
+        |name: &str| project_file.as_ref().is_some_and(|f| f.is_secret(name));
 
-    Ok(())
-}
-
-enum Mode {
-    KV,
-    Json,
-    Table,
-}
+    // Only the table is masked, the other formats are for scripts
+    let kvpairs = variables.to_kvpair();
 
-impl Mode {
-    fn from_args(args: &Args) -> Self {
-        if args.json {
-            Self::Json
-        } else if args.kv {
-            Self::KV
-        } else {
-            Self::Table
+    output::print(&kvpairs.to_btreemap()?, |format| {
+        if format == OutputFormat::Plain {
+            kvpairs.iter().for_each(|kv| println!("{}", kv));
+            return Ok(());
         }
-    }
+
+        let redact = output::redact(args.reveal);
+        let btreemap = variables
+            .iter()
+            .map(|v| {
+                let author =
+                    format!("by {}", signing::describe(&v.signer)).dimmed();
+                let secret = is_secret(&v.value.key) && !args.reveal;
+                let value = if redact || secret {
+                    output::mask(&v.value.value)
+                } else {
+                    v.value.value.clone()
+                };
+                (v.value.key.clone(), format!("{}\n{}", value, author))
+            })
+            .collect();
+        Table::new("Variables".into(), btreemap).print()
+    })
 }
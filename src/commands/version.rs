@@ -1,17 +1,35 @@
 use super::*;
+use crate::utils::{output, settings::OutputFormat};
+use serde::Serialize;
 
 /// Fancy, pretty-printed version information.
 #[derive(Parser)]
 pub struct Args {}
 
+#[derive(Serialize)]
+struct Version {
+    name: &'static str,
+    version: &'static str,
+}
+
 pub async fn command(_args: Args) -> Result<()> {
-    println!(
-        "{} {} {} {}",
-        "env-cli".cyan(),
-        env!("CARGO_PKG_VERSION").magenta(),
-        "by".blue(),
-        "alexng353".yellow()
-    );
+    let version = Version {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+    };
 
-    Ok(())
+    output::print(&version, |format| {
+        if format == OutputFormat::Plain {
+            println!("{}", version.version);
+            return Ok(());
+        }
+        println!(
+            "{} {} {} {}",
+            "env-cli".cyan(),
+            version.version.magenta(),
+            "by".blue(),
+            "alexng353".yellow()
+        );
+        Ok(())
+    })
 }
@@ -0,0 +1,198 @@
+//! Where envx keeps its config, and which server context is in use
+//!
+//! The `envx` CLI and [`ClientBuilder::from_config`] both go through these,
+//! so a service finds the same config.json, known_keys.json and context as
+//! the CLI run by the same user. The library only ever reads: creating,
+//! migrating and writing the config is left to the CLI.
+//!
+//! [`ClientBuilder::from_config`]: crate::ClientBuilder::from_config
+
+use crate::connection::{ConnectionSettings, RetryPolicy};
+use crate::duration::parse_duration;
+use crate::env::{self, ENVX_API_URL, ENVX_CONFIG_DIR, ENVX_CONTEXT};
+use crate::env::{ENVX_HOME, ENVX_USER_ID};
+use crate::error::{Error, Result};
+use crate::known_keys::KnownKeys;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the context stored in the top level fields of the config
+pub const DEFAULT_CONTEXT: &str = "default";
+
+const APP_DIR: &str = "envx";
+
+/// The single directory given with `ENVX_HOME` or `ENVX_CONFIG_DIR`
+pub fn home_from_env() -> Option<PathBuf> {
+    env::var(ENVX_HOME)
+        .or_else(|| env::var(ENVX_CONFIG_DIR))
+        .map(PathBuf::from)
+}
+
+/// An absolute path from an XDG variable, ignoring relative ones as the
+/// spec requires
+fn xdg_dir(var: &str) -> Option<PathBuf> {
+    env::var(var).map(PathBuf::from).filter(|p| p.is_absolute())
+}
+
+fn user_home() -> Result<PathBuf> {
+    home::home_dir()
+        .ok_or_else(|| Error::Config("Failed to get home directory".into()))
+}
+
+/// The directory holding config.json and known_keys.json, `home` if one is
+/// given, `$XDG_CONFIG_HOME/envx` otherwise
+pub fn config_dir(home: Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(dir) = home {
+        return Ok(dir);
+    }
+    let base = match xdg_dir("XDG_CONFIG_HOME") {
+        Some(dir) => dir,
+        None => user_home()?.join(".config"),
+    };
+    Ok(base.join(APP_DIR))
+}
+
+/// The directory holding the key vault, `home` if one is given,
+/// `$XDG_DATA_HOME/envx` otherwise
+pub fn data_dir(home: Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(dir) = home {
+        return Ok(dir);
+    }
+    let base = match xdg_dir("XDG_DATA_HOME") {
+        Some(dir) => dir,
+        None => user_home()?.join(".local").join("share"),
+    };
+    Ok(base.join(APP_DIR))
+}
+
+/// The context to use: `explicit` if given, then `ENVX_CONTEXT`, then the
+/// `current_context` of the config, then the default context
+pub fn select_context(
+    explicit: Option<String>,
+    current: Option<String>,
+) -> String {
+    explicit
+        .or_else(|| env::var(ENVX_CONTEXT))
+        .or(current)
+        .unwrap_or_else(|| DEFAULT_CONTEXT.into())
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    keys: Vec<Key>,
+    sdk_url: Option<String>,
+    #[serde(default)]
+    connection: ConnectionSettings,
+    #[serde(default)]
+    settings: Option<RetrySettings>,
+    #[serde(default)]
+    contexts: BTreeMap<String, ServerContext>,
+    current_context: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Key {
+    fingerprint: String,
+    uuid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServerContext {
+    sdk_url: Option<String>,
+    #[serde(default)]
+    connection: ConnectionSettings,
+}
+
+/// The part of the CLI's settings the client cares about
+#[derive(Debug, Deserialize, Default)]
+struct RetrySettings {
+    retry_attempts: Option<u32>,
+    retry_backoff: Option<String>,
+}
+
+/// What the client takes from the config
+#[derive(Debug, Default)]
+pub(crate) struct Settings {
+    pub url: Option<String>,
+    /// Given with `ENVX_USER_ID`
+    pub user_id: Option<String>,
+    /// Server user id of each key, by lowercase fingerprint
+    pub user_ids: BTreeMap<String, String>,
+    /// Fingerprints of the keys in the config
+    pub own_keys: Vec<String>,
+    pub connection: ConnectionSettings,
+    pub retry: RetryPolicy,
+    pub known_keys: KnownKeys,
+}
+
+impl Settings {
+    /// The server user id of the key with `fingerprint`
+    pub fn user_id_of(&self, fingerprint: &str) -> Option<String> {
+        self.user_id
+            .clone()
+            .or_else(|| self.user_ids.get(&fingerprint.to_lowercase()).cloned())
+    }
+}
+
+fn read(path: &Path) -> Result<ConfigFile> {
+    if !path.exists() {
+        return Ok(ConfigFile::default());
+    }
+    let contents = std::fs::read(path).map_err(|e| {
+        Error::Config(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    serde_json::from_slice(&contents).map_err(|e| {
+        Error::Config(format!("Failed to parse {}: {}", path.display(), e))
+    })
+}
+
+/// The server, user, connection settings and pinned keys the CLI would use
+///
+/// `dir` and `context` default to the ones the CLI would pick.
+pub(crate) fn load(
+    dir: Option<PathBuf>,
+    context: Option<String>,
+) -> Result<Settings> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => config_dir(home_from_env())?,
+    };
+    let mut config = read(&dir.join("config.json"))?;
+
+    let context = select_context(context, config.current_context.clone());
+    if context != DEFAULT_CONTEXT {
+        let named = config.contexts.remove(&context).ok_or_else(|| {
+            Error::Config(format!("Unknown context '{}'", context))
+        })?;
+        config.sdk_url = named.sdk_url;
+        config.connection = named.connection;
+    }
+
+    let settings = config.settings.unwrap_or_default();
+    let default = RetryPolicy::default();
+    let backoff = match settings.retry_backoff {
+        Some(backoff) => parse_duration(&backoff).map_err(|e| {
+            Error::Config(format!("Invalid retry_backoff: {}", e))
+        })?,
+        None => default.backoff,
+    };
+
+    Ok(Settings {
+        url: env::var(ENVX_API_URL).or(config.sdk_url),
+        user_id: env::var(ENVX_USER_ID),
+        own_keys: config.keys.iter().map(|k| k.fingerprint.clone()).collect(),
+        user_ids: config
+            .keys
+            .into_iter()
+            .filter_map(|k| Some((k.fingerprint.to_lowercase(), k.uuid?)))
+            .collect(),
+        connection: config.connection,
+        retry: RetryPolicy::new(
+            settings.retry_attempts.unwrap_or(default.attempts),
+            backoff,
+        ),
+        known_keys: KnownKeys::read(&dir.join("known_keys.json"))?,
+    })
+}
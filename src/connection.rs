@@ -0,0 +1,240 @@
+//! How to reach the server of a context: TLS, proxy and retry settings
+
+use crate::error::{Error, Result};
+use reqwest::{
+    Certificate, ClientBuilder, Identity, Method, NoProxy, Proxy,
+    RequestBuilder, Response, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Upper limit of the retry attempts, larger values are capped
+pub const MAX_RETRY_ATTEMPTS: u32 = 10;
+
+/// Longest delay between two retries
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How to connect to the server of a context
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConnectionSettings {
+    /// Extra root CAs (PEM bundle) to trust
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+    /// Client certificate (PEM) for mutual TLS
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+    /// PKCS#8 private key (PEM) of the client certificate
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+    /// HTTP(S) or SOCKS5 proxy, e.g. `socks5://localhost:1080`
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Comma separated hosts that bypass the proxy, defaults to `NO_PROXY`
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    /// Don't verify the server's certificate, for local development only
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+impl ConnectionSettings {
+    /// Apply the settings to a client builder
+    pub fn apply(&self, mut builder: ClientBuilder) -> Result<ClientBuilder> {
+        if let Some(path) = &self.ca_bundle {
+            let certificates =
+                split_pem_certificates(&read(path, "CA bundle")?);
+            if certificates.is_empty() {
+                return Err(Error::Config(format!(
+                    "No certificates found in CA bundle {}",
+                    path.display()
+                )));
+            }
+            for certificate in certificates {
+                let certificate =
+                    Certificate::from_pem(&certificate).map_err(|e| {
+                        Error::Config(format!(
+                            "Invalid CA bundle {}: {}",
+                            path.display(),
+                            e
+                        ))
+                    })?;
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                let identity = Identity::from_pkcs8_pem(
+                    &read(cert, "client certificate")?,
+                    &read(key, "client key")?,
+                )
+                .map_err(|e| {
+                    Error::Config(format!(
+                        "Invalid client certificate or key, the key must be PKCS#8: {}",
+                        e
+                    ))
+                })?;
+                builder = builder.identity(identity);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(Error::Config(
+                    "Both a client certificate and key are required".into(),
+                ))
+            }
+        }
+
+        if let Some(proxy) = &self.proxy {
+            let no_proxy = match &self.no_proxy {
+                Some(hosts) => NoProxy::from_string(hosts),
+                None => NoProxy::from_env(),
+            };
+            let proxy = Proxy::all(proxy)
+                .map_err(|e| {
+                    Error::Config(format!(
+                        "Invalid proxy URL '{}': {}",
+                        proxy, e
+                    ))
+                })?
+                .no_proxy(no_proxy);
+            builder = builder.proxy(proxy);
+        }
+
+        if self.insecure_skip_verify {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// How often and how patiently to retry failed requests
+///
+/// Requests that never reached the server are always retried, others only
+/// if they are idempotent and failed with a timeout or a temporary error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, at most [`MAX_RETRY_ATTEMPTS`]
+    pub attempts: u32,
+    /// Delay before the first retry, doubled after every retry up to
+    /// [`MAX_BACKOFF`]
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 2,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy within [`MAX_RETRY_ATTEMPTS`] and [`MAX_BACKOFF`]
+    pub fn new(attempts: u32, backoff: Duration) -> Self {
+        Self {
+            attempts: attempts.min(MAX_RETRY_ATTEMPTS),
+            backoff: backoff.min(MAX_BACKOFF),
+        }
+    }
+
+    /// Send a request, retrying it as the policy allows
+    pub async fn send(
+        &self,
+        request: RequestBuilder,
+    ) -> reqwest::Result<Response> {
+        let attempts = self.attempts.min(MAX_RETRY_ATTEMPTS);
+        let mut backoff = self.backoff.min(MAX_BACKOFF);
+
+        let idempotent = request
+            .try_clone()
+            .and_then(|r| r.build().ok())
+            .is_some_and(|r| {
+                [Method::GET, Method::HEAD, Method::PUT, Method::DELETE]
+                    .contains(r.method())
+            });
+
+        let mut attempt = 0;
+        loop {
+            // bodies that are streams can't be cloned, send them once
+            let Some(attempt_request) = request.try_clone() else {
+                return request.send().await;
+            };
+            let result = attempt_request.send().await;
+
+            let retry = match &result {
+                Err(e) => e.is_connect() || (idempotent && e.is_timeout()),
+                Ok(res) => {
+                    idempotent
+                        && matches!(
+                            res.status(),
+                            StatusCode::TOO_MANY_REQUESTS
+                                | StatusCode::BAD_GATEWAY
+                                | StatusCode::SERVICE_UNAVAILABLE
+                                | StatusCode::GATEWAY_TIMEOUT
+                        )
+                }
+            };
+            if !retry || attempt >= attempts {
+                return result;
+            }
+
+            attempt += 1;
+            tokio::time::sleep(backoff).await;
+            backoff = backoff.saturating_mul(2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+fn read(path: &Path, what: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|e| {
+        Error::Config(format!(
+            "Failed to read {} {}: {}",
+            what,
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Split a PEM bundle into its certificates
+///
+/// `Certificate::from_pem` only reads the first certificate of a bundle
+fn split_pem_certificates(pem: &[u8]) -> Vec<Vec<u8>> {
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let pem = String::from_utf8_lossy(pem);
+    pem.split_inclusive(END)
+        .filter(|block| block.contains(END))
+        .map(|block| block.trim().as_bytes().to_vec())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_retry_policy() {
+        let policy = RetryPolicy::new(100, Duration::from_secs(3600));
+        assert_eq!(policy.attempts, MAX_RETRY_ATTEMPTS);
+        assert_eq!(policy.backoff, MAX_BACKOFF);
+
+        let policy = RetryPolicy::new(3, Duration::from_secs(2));
+        assert_eq!(policy.attempts, 3);
+        assert_eq!(policy.backoff, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn splits_pem_bundles() {
+        let cert =
+            "-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----";
+        let bundle = format!("{}\n\n{}\n", cert, cert);
+        let certificates = split_pem_certificates(bundle.as_bytes());
+        assert_eq!(certificates.len(), 2);
+        assert!(certificates.iter().all(|c| c == cert.as_bytes()));
+    }
+}
@@ -1 +1,3 @@
 pub const MINIMUM_PASSWORD_LENGTH: usize = 8;
+/// Warn about keys that expire within this many days
+pub const KEY_EXPIRY_WARNING_DAYS: i64 = 30;
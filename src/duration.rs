@@ -0,0 +1,120 @@
+//! Human readable durations, as used for TTLs, expiries and retry delays
+//! in the envx config
+
+use crate::error::{Error, Result};
+use std::time::Duration;
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const YEAR: u64 = 365 * DAY;
+
+/// Longest duration accepted, far enough for any expiry or TTL while
+/// staying clear of overflows when added to the current time
+const MAX: u64 = 100 * YEAR;
+
+/// Parse a human readable duration such as `90s`, `8h`, `30d`, `6mo` or `2y`
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim().to_lowercase();
+    let split = input.find(|c: char| !c.is_ascii_digit()).ok_or_else(|| {
+        Error::Config(
+            "Missing unit, expected one of s, m, h, d, w, mo, y".into(),
+        )
+    })?;
+    let (amount, unit) = input.split_at(split);
+
+    let amount = amount
+        .parse::<u64>()
+        .map_err(|_| Error::Config(format!("Invalid duration: {}", input)))?;
+
+    let unit = match unit {
+        "s" => 1,
+        "m" => MINUTE,
+        "h" => HOUR,
+        "d" => DAY,
+        "w" => 7 * DAY,
+        "mo" => 30 * DAY,
+        "y" => YEAR,
+        _ => {
+            return Err(Error::Config(format!(
+            "Invalid duration unit '{}', expected one of s, m, h, d, w, mo, y",
+            unit
+        )))
+        }
+    };
+
+    match amount.checked_mul(unit) {
+        Some(secs) if secs <= MAX => Ok(Duration::from_secs(secs)),
+        _ => Err(Error::Config(format!(
+            "Duration {} is too long, the maximum is 100y",
+            input
+        ))),
+    }
+}
+
+/// Format a duration using the largest fitting unit, e.g. `3d 4h`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes) =
+        (secs / DAY, (secs % DAY) / HOUR, (secs % HOUR) / MINUTE);
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(
+            parse_duration("8h").unwrap(),
+            Duration::from_secs(8 * HOUR)
+        );
+        assert_eq!(
+            parse_duration(" 2W ").unwrap(),
+            Duration::from_secs(14 * DAY)
+        );
+        assert_eq!(
+            parse_duration("6mo").unwrap(),
+            Duration::from_secs(180 * DAY)
+        );
+        assert_eq!(parse_duration("100y").unwrap(), Duration::from_secs(MAX));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("-1d").is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert!(parse_duration("101y").is_err());
+        assert!(parse_duration("99999999999y").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+    }
+
+    #[test]
+    fn formats_largest_units() {
+        assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(90)), "1m");
+        assert_eq!(format_duration(Duration::from_secs(HOUR + 120)), "1h 2m");
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * DAY + 4 * HOUR)),
+            "3d 4h"
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Environment variables that override the config, for headless and CI use
+//!
+//! ENVX_HOME           directory holding config.json and the key vault
+//! ENVX_CONFIG_DIR     same as ENVX_HOME
+//! ENVX_CONTEXT        server context to use
+//! ENVX_API_URL        server URL, overrides the URL of the context
+//! ENVX_KEY            partial fingerprint of the key to use
+//! ENVX_PRIVATE_KEY    armored secret key, used without writing it to disk
+//! ENVX_USER_ID        server user id of ENVX_PRIVATE_KEY
+//! ENVX_PASSPHRASE     passphrase of the key, skips the keyring and prompts
+//! ENVX_NEW_PASSPHRASE new passphrase for `envx key passwd`
+//! ENVX_PROJECT        project id, used when no project is given or linked
+//! ENVX_ENV            environment from .envx.toml to use
+//! ENVX_INSECURE_KEYS  set to load keys other users can read
+//! ENVX_AGENT_SOCK     socket of `envx agent`, overrides the default location
+//! ENVX_KEYRING_PASSPHRASE
+//!                     master passphrase of the encrypted keyring file
+//!
+//! Values set this way are never written to the config. Plugins get most of
+//! these set for them, and ENVX_TOKEN, see `utils::plugin` in the CLI.
+
+pub const ENVX_HOME: &str = "ENVX_HOME";
+pub const ENVX_CONFIG_DIR: &str = "ENVX_CONFIG_DIR";
+pub const ENVX_CONTEXT: &str = "ENVX_CONTEXT";
+pub const ENVX_API_URL: &str = "ENVX_API_URL";
+pub const ENVX_KEY: &str = "ENVX_KEY";
+pub const ENVX_PRIVATE_KEY: &str = "ENVX_PRIVATE_KEY";
+pub const ENVX_USER_ID: &str = "ENVX_USER_ID";
+pub const ENVX_PASSPHRASE: &str = "ENVX_PASSPHRASE";
+pub const ENVX_NEW_PASSPHRASE: &str = "ENVX_NEW_PASSPHRASE";
+pub const ENVX_PROJECT: &str = "ENVX_PROJECT";
+pub const ENVX_ENV: &str = "ENVX_ENV";
+pub const ENVX_INSECURE_KEYS: &str = "ENVX_INSECURE_KEYS";
+pub const ENVX_AGENT_SOCK: &str = "ENVX_AGENT_SOCK";
+pub const ENVX_KEYRING_PASSPHRASE: &str = "ENVX_KEYRING_PASSPHRASE";
+pub const ENVX_TOKEN: &str = "ENVX_TOKEN";
+
+/// Read an environment variable, treating empty values as unset
+pub fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
@@ -0,0 +1,37 @@
+use crate::known_keys::MemberKey;
+use thiserror::Error;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Something the client needs is missing or invalid
+    #[error("{0}")]
+    Config(String),
+
+    /// The request didn't reach the server or the response couldn't be read
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The server refused the request
+    #[error("The server returned {status}: {message}")]
+    Api { status: u16, message: String },
+
+    /// The key couldn't be read, unlocked or used
+    #[error("Key error: {0}")]
+    Key(String),
+
+    /// A variable couldn't be decrypted or isn't what envx writes
+    #[error("Failed to decrypt variable {id}: {message}")]
+    Decrypt { id: String, message: String },
+
+    /// Keys of project members are new or changed since they were pinned,
+    /// so nothing is encrypted to them
+    #[error(
+        "Refusing to encrypt to untrusted member keys of project {project_id}"
+    )]
+    Untrusted {
+        project_id: String,
+        members: Vec<MemberKey>,
+    },
+}
@@ -0,0 +1,107 @@
+use crate::env::{self, ENVX_PASSPHRASE, ENVX_PRIVATE_KEY};
+use crate::error::{Error, Result};
+use pgp::composed::message::Message;
+use pgp::types::KeyTrait;
+use pgp::{crypto, Deserializable, SignedSecretKey};
+
+/// A key the client decrypts variables and signs requests with
+///
+/// Implement this to keep keys in a secret manager, an HSM or an agent.
+/// [`SecretKey`] holds one in memory.
+pub trait KeyProvider: Send + Sync {
+    /// Fingerprint of the key, hex
+    fn fingerprint(&self) -> String;
+
+    /// Decrypt a message encrypted to the key, returning the inner message
+    /// with its signature, if any
+    fn decrypt(&self, message: &Message) -> Result<Message>;
+
+    /// Sign `text` into an armored signed message, used to authenticate
+    /// with the server
+    fn sign(&self, text: &str) -> Result<String>;
+
+    /// Decrypt every message, e.g. in a single round trip to an agent
+    fn decrypt_many(&self, messages: &[Message]) -> Result<Vec<Message>> {
+        messages.iter().map(|m| self.decrypt(m)).collect()
+    }
+
+    /// Sign every text into a signed message, used for the variables the
+    /// client writes so other members can tell who wrote them
+    fn sign_many(&self, texts: &[String]) -> Result<Vec<Message>> {
+        texts
+            .iter()
+            .map(|text| {
+                let (message, _) = Message::from_string(&self.sign(text)?)
+                    .map_err(|e| Error::Key(e.to_string()))?;
+                Ok(message)
+            })
+            .collect()
+    }
+}
+
+/// An unlocked secret key held in memory
+pub struct SecretKey {
+    key: SignedSecretKey,
+    passphrase: String,
+}
+
+impl SecretKey {
+    pub fn new(key: SignedSecretKey, passphrase: impl Into<String>) -> Self {
+        Self {
+            key,
+            passphrase: passphrase.into(),
+        }
+    }
+
+    /// Parse an armored secret key
+    pub fn from_armored(
+        armored: &str,
+        passphrase: impl Into<String>,
+    ) -> Result<Self> {
+        let (key, _) = SignedSecretKey::from_string(armored)
+            .map_err(|e| Error::Key(format!("Invalid secret key: {}", e)))?;
+        Ok(Self::new(key, passphrase))
+    }
+
+    /// The key in `ENVX_PRIVATE_KEY` with the passphrase in
+    /// `ENVX_PASSPHRASE`, the variables `envx` itself reads in CI
+    pub fn from_env() -> Result<Self> {
+        let armored = env::var(ENVX_PRIVATE_KEY).ok_or_else(|| {
+            Error::Key(format!("{} is not set", ENVX_PRIVATE_KEY))
+        })?;
+        let passphrase = env::var(ENVX_PASSPHRASE).unwrap_or_default();
+        Self::from_armored(&armored, passphrase)
+    }
+}
+
+impl KeyProvider for SecretKey {
+    fn fingerprint(&self) -> String {
+        hex::encode(self.key.fingerprint())
+    }
+
+    fn decrypt(&self, message: &Message) -> Result<Message> {
+        let (mut decryptor, _) = message
+            .decrypt(|| self.passphrase.clone(), &[&self.key])
+            .map_err(|e| Error::Key(e.to_string()))?;
+        match decryptor.next() {
+            Some(message) => message.map_err(|e| Error::Key(e.to_string())),
+            None => Err(Error::Key("The message is empty".into())),
+        }
+    }
+
+    fn sign(&self, text: &str) -> Result<String> {
+        Message::new_literal("none", text)
+            .sign(
+                &self.key,
+                || self.passphrase.clone(),
+                crypto::hash::HashAlgorithm::SHA3_512,
+            )
+            .and_then(|signed| signed.to_armored_string(None))
+            .map_err(|e| {
+                Error::Key(format!(
+                    "Failed to sign, most likely a wrong passphrase: {}",
+                    e
+                ))
+            })
+    }
+}
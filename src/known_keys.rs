@@ -0,0 +1,194 @@
+//! Pinned fingerprints of project members' keys
+//!
+//! Works like ssh's known_hosts: the fingerprint of every project member's
+//! public key is pinned the first time it is trusted, and a new or
+//! different key handed out by the server later is not trusted until it is
+//! pinned again. The `envx` CLI keeps the pins in known_keys.json next to
+//! config.json and pins keys with `envx project trust`.
+
+use crate::error::{Error, Result};
+use crate::models::User;
+use pgp::{types::KeyTrait, Deserializable, SignedPublicKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct KnownKeys {
+    /// project id -> (user id -> pinned fingerprint)
+    pub projects: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyStatus {
+    /// The fingerprint matches the pinned one
+    Pinned,
+    /// The key is one of our own keys
+    Own,
+    /// No fingerprint pinned for this user yet
+    New,
+    /// The server returned a different key than the pinned one
+    Changed { pinned: String },
+}
+
+/// A project member with the fingerprint of the key the server returned
+#[derive(Debug, Clone)]
+pub struct MemberKey {
+    pub user: User,
+    pub fingerprint: String,
+    pub public_key: SignedPublicKey,
+    pub status: KeyStatus,
+}
+
+impl MemberKey {
+    pub fn is_trusted(&self) -> bool {
+        matches!(self.status, KeyStatus::Pinned | KeyStatus::Own)
+    }
+}
+
+impl Display for KeyStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyStatus::Pinned => write!(f, "pinned"),
+            KeyStatus::Own => write!(f, "own key"),
+            KeyStatus::New => write!(f, "NEW"),
+            KeyStatus::Changed { pinned } => {
+                write!(f, "CHANGED (was {})", pinned)
+            }
+        }
+    }
+}
+
+impl Display for MemberKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} - {}\n    {} [{}]",
+            self.user.username,
+            self.user.id,
+            format_fingerprint(&self.fingerprint),
+            self.status
+        )
+    }
+}
+
+/// Split a fingerprint into groups of four for reading aloud
+pub fn format_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .collect::<Vec<char>>()
+        .chunks(4)
+        .map(|c| c.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl KnownKeys {
+    /// Read the pins from `path`, none if it doesn't exist
+    pub fn read(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path).map_err(|e| {
+            Error::Config(format!(
+                "Failed to read known keys file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        serde_json::from_str(&contents).map_err(|e| {
+            Error::Config(format!(
+                "Failed to parse known keys file {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Compare the keys returned by the server against the pinned ones
+    ///
+    /// Keys with one of the `own_keys` fingerprints are trusted unless a
+    /// different key is pinned for their user.
+    pub fn check(
+        &self,
+        project_id: &str,
+        users: &[User],
+        own_keys: &[String],
+    ) -> Result<Vec<MemberKey>> {
+        let pinned = self.projects.get(project_id);
+
+        users
+            .iter()
+            .map(|user| {
+                let (public_key, _) = SignedPublicKey::from_string(
+                    &user.public_key,
+                )
+                .map_err(|e| {
+                    Error::Key(format!(
+                        "Failed to parse public key of {}: {}",
+                        user, e
+                    ))
+                })?;
+                let fingerprint = hex::encode_upper(public_key.fingerprint());
+
+                let own = own_keys
+                    .iter()
+                    .any(|k| k.eq_ignore_ascii_case(&fingerprint));
+
+                let status = match pinned.and_then(|p| p.get(&user.id)) {
+                    Some(p) if p.eq_ignore_ascii_case(&fingerprint) => {
+                        KeyStatus::Pinned
+                    }
+                    Some(p) => KeyStatus::Changed { pinned: p.clone() },
+                    None if own => KeyStatus::Own,
+                    None => KeyStatus::New,
+                };
+
+                Ok(MemberKey {
+                    user: user.clone(),
+                    fingerprint,
+                    public_key,
+                    status,
+                })
+            })
+            .collect()
+    }
+
+    /// Pin the given members, replacing the previous pins for the project
+    pub fn pin(&mut self, project_id: &str, members: &[MemberKey]) {
+        let pins = members
+            .iter()
+            .map(|m| (m.user.id.clone(), m.fingerprint.clone()))
+            .collect();
+        self.projects.insert(project_id.to_string(), pins);
+    }
+
+    /// Pin a single member without touching the other pins of the project
+    pub fn pin_one(&mut self, project_id: &str, member: &MemberKey) {
+        self.projects
+            .entry(project_id.to_string())
+            .or_default()
+            .insert(member.user.id.clone(), member.fingerprint.clone());
+    }
+}
+
+/// The public keys of all members, [`Error::Untrusted`] if any of them is
+/// new or has changed since it was pinned
+pub fn trusted_public_keys(
+    project_id: &str,
+    members: Vec<MemberKey>,
+) -> Result<Vec<SignedPublicKey>> {
+    let (trusted, untrusted): (Vec<_>, Vec<_>) =
+        members.into_iter().partition(|m| m.is_trusted());
+
+    if !untrusted.is_empty() {
+        return Err(Error::Untrusted {
+            project_id: project_id.to_string(),
+            members: untrusted,
+        });
+    }
+
+    Ok(trusted.into_iter().map(|m| m.public_key).collect())
+}
@@ -0,0 +1,45 @@
+//! Read and write envx variables from Rust services
+//!
+//! The [`Client`] is what the `envx` CLI itself talks to the server with,
+//! but it never prints or prompts: everything it needs is given to its
+//! builder or read from the envx config and environment, and every failure
+//! is returned as an [`Error`]. Decryption and signing go through a
+//! [`KeyProvider`], so keys can live wherever the service keeps secrets.
+//!
+//! Who wrote a variable is checked against the member keys pinned with
+//! `envx project trust`, never against the keys the server hands out
+//! alone, see [`Signer`] and [`known_keys`].
+//!
+//! ```no_run
+//! use envx::{Client, SecretKey};
+//!
+//! # async fn run() -> envx::Result<()> {
+//! // ENVX_PRIVATE_KEY and ENVX_PASSPHRASE, as used by `envx` in CI
+//! let key = SecretKey::from_env()?;
+//! let client = Client::builder().from_config()?.key(key).build()?;
+//!
+//! for variable in client.variables("my-project-id").await? {
+//!     std::env::set_var(&variable.name, &variable.value);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod client;
+pub mod config;
+pub mod connection;
+pub mod duration;
+pub mod env;
+pub mod error;
+pub mod key_provider;
+pub mod known_keys;
+pub mod models;
+pub mod signing;
+
+pub use client::{AuthToken, Client, ClientBuilder, DEFAULT_URL};
+pub use connection::{ConnectionSettings, RetryPolicy};
+pub use error::{Error, Result};
+pub use key_provider::{KeyProvider, SecretKey};
+pub use known_keys::{KnownKeys, MemberKey};
+pub use models::{PartialUser, Project, User, Variable};
+pub use signing::Signer;
@@ -21,15 +21,49 @@ macro_rules! commands_enum {
         }
       }
     );
+    // Unknown subcommands are passed to `$fallback` with their arguments
+    ($($module:ident),* ; external => $fallback:path) => (
+      paste::paste! {
+        #[derive(Subcommand)]
+        enum Commands {
+            $(
+              [<$module:camel>]($module::Args),
+            )*
+            #[clap(external_subcommand)]
+            External(Vec<String>),
+        }
+
+        impl Commands {
+            async fn exec(cli: Args) -> Result<()> {
+              match cli.command {
+                $(
+                  Commands::[<$module:camel>](args) => $module::command(args).await?,
+                )*
+                Commands::External(args) => $fallback(args).await?,
+              }
+              Ok(())
+            }
+        }
+      }
+    );
+}
+
+/// Print a status message like `println!`, skipped with `--silent` and
+/// sent to stderr with `--output json|yaml`
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::utils::output::info(&format!($($arg)*))
+    };
 }
 
-/// Ensure running in a terminal or bail with the provided message
+/// Ensure prompts can be shown or bail with the provided message, which
+/// should name the flag or environment variable to use instead
 #[macro_export]
 macro_rules! interact_or {
-    ($message:expr) => {
-        if !std::io::stdout().is_terminal() {
-            use anyhow::bail;
-            bail!($message);
+    ($($message:tt)+) => {
+        if !$crate::utils::prompt::is_interactive() {
+            anyhow::bail!($($message)+);
         }
     };
 }
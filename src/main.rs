@@ -19,23 +19,79 @@ pub struct Args {
     #[clap(subcommand)]
     command: Commands,
 
-    #[clap(long)]
+    /// Only print results and errors, no status messages
+    #[clap(long, global = true)]
     silent: bool,
+
+    /// Output format, defaults to the output_format setting
+    #[clap(long, global = true, value_enum)]
+    output: Option<utils::settings::OutputFormat>,
+
+    /// Server context to use, overrides ENVX_CONTEXT
+    #[clap(long, global = true)]
+    context: Option<String>,
+
+    /// Environment from .envx.toml to use, overrides ENVX_ENV
+    #[clap(long, global = true)]
+    env: Option<String>,
+
+    /// Keep the config and keys in this directory, overrides ENVX_HOME
+    #[clap(long, global = true)]
+    config_dir: Option<std::path::PathBuf>,
+
+    /// Fail instead of prompting, the default when stdin isn't a terminal
+    #[clap(long, global = true)]
+    non_interactive: bool,
 }
 
 // Generates the commands based on the modules in the commands directory
 // Specify the modules you want to include in the commands_enum! macro
 commands_enum!(
-    auth, debug, decrypt, encrypt, export, gen, import, link, list_keys, run,
-    set, shell, sign, unlink, unset, upload, variables, version,
+    auth, check, completions, copy, debug, decrypt, doctor, encrypt, export,
+    gen, history, import, link, list_keys, lock, run, set, shell, sign,
+    unlink, unlock, unset, upload, variables, version,
     // commands with subcommands
-    config, delete, get, keyring, new, project
+    agent, config, context, delete, get, key, keyring, new, plugins, project;
+    // anything else runs a plugin
+    external => utils::plugin::run
 );
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Called by the completion scripts, kept out of `--help`
+    let raw_args = std::env::args().collect::<Vec<_>>();
+    if raw_args.get(1).map(String::as_str) == Some(completions::COMPLETE) {
+        return completions::complete(&raw_args[2..]);
+    }
+
     let cli = Args::parse();
 
+    if let Some(config_dir) = &cli.config_dir {
+        utils::dirs::set_home_override(config_dir.clone());
+    }
+    if let Some(context) = &cli.context {
+        utils::config::set_context_override(context.clone());
+    }
+    if matches!(cli.command, Commands::Context(_) | Commands::Doctor(_)) {
+        utils::config::fall_back_to_default_context();
+    }
+    if matches!(&cli.command, Commands::Doctor(args) if args.read_only()) {
+        utils::config::set_read_only();
+    }
+    if let Some(env) = &cli.env {
+        utils::project_file::set_environment_override(env.clone());
+    }
+    if cli.non_interactive {
+        utils::prompt::set_non_interactive();
+    }
+    if cli.silent {
+        utils::output::set_silent();
+    }
+    if let Some(output) = cli.output {
+        utils::output::set_format(output);
+    }
+    utils::settings::apply_color();
+
     match Commands::exec(cli).await {
         Ok(_) => {}
         Err(e) => {
@@ -47,7 +103,7 @@ async fn main() -> Result<()> {
                 return Ok(());
             }
 
-            eprintln!("{:?}", e);
+            utils::output::print_error(&e);
             std::process::exit(1);
         }
     }
@@ -0,0 +1,64 @@
+use crate::signing::Signer;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// A user of the server, identified by their key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct User {
+    pub id: String,
+    pub username: String,
+    pub created_at: String, // DateTime
+    /// Armored public key
+    pub public_key: String,
+}
+
+impl Display for User {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} - {}", self.username, self.id)
+    }
+}
+
+/// The public parts of a user, e.g. the author of a variable
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PartialUser {
+    pub id: String,
+    pub username: String,
+}
+
+impl From<User> for PartialUser {
+    fn from(user: User) -> Self {
+        PartialUser {
+            id: user.id,
+            username: user.username,
+        }
+    }
+}
+
+impl Display for PartialUser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} - {}", self.username, self.id)
+    }
+}
+
+/// A project and the users its variables are encrypted to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Project {
+    #[serde(rename = "project_id")]
+    pub id: String,
+    pub users: Vec<User>,
+}
+
+/// A decrypted value of a variable
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Variable {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub value: String,
+    pub created_at: String,
+    /// Who signed the variable. Only [`Signer::Verified`] if the signature
+    /// checks out against a member key that is pinned or one of our own,
+    /// the keys the server hands out aren't trusted on their own.
+    #[serde(default)]
+    pub signer: Signer,
+}
@@ -1,74 +1,130 @@
+// The envx server API, as used by the commands
+//
+// Requests, decryption and signature checks go through `envx::Client`, this
+// adds what only the CLI does: picking the key and server from the config,
+// asking for passphrases, pointing at `envx project trust` and recording
+// completion candidates.
+
 use super::*;
 use crate::{
-    types::ProjectInfo,
+    types::Project,
     utils::{
-        auth::get_token,
-        config::get_config,
+        auth::sign_challenge,
+        completion,
+        config::{get_config, Config, DEFAULT_SDK_URL},
+        dirs,
+        env::{self, ENVX_API_URL},
+        http::{http_client, warn_if_insecure, SendWithRetry},
+        known_keys::{own_keys, untrusted},
         kvpair::KVPair,
-        partial_variable::{
-            ParsedPartialVariable, PartialVariable, ToKVPair, ToParsed,
-        },
-        rpgp::{decrypt_full_many, encrypt_multi},
+        partial_variable::{ParsedPartialVariable, ToKVPair},
+        rpgp::{decrypt_many_messages, sign_many},
+        signing::enforce_signatures,
     },
 };
 use anyhow::bail;
-use pgp::{Deserializable, SignedPublicKey};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use reqwest::header;
-use serde::{Deserialize, Serialize};
+use envx::{Client, KeyProvider};
+use pgp::composed::message::Message;
+use pgp::SignedPublicKey;
 use serde_json::json;
 use url::Url;
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct SetEnvParams {
-    pub message: String,
-    pub allowed_keys: Vec<String>,
-    pub project_id: Option<String>,
+/// The URL of the server for the current context
+pub fn get_api_url() -> Result<Url> {
+    if std::env::var("DEV_MODE").is_ok() {
+        return Ok(Url::parse("http://localhost:3000")?);
+    }
+
+    if let Some(url) = env::var(ENVX_API_URL) {
+        return Url::parse(&url)
+            .with_context(|| format!("Invalid {} '{}'", ENVX_API_URL, url));
+    }
+
+    let config = get_config()?;
+    let url = config.sdk_url.as_deref().unwrap_or(DEFAULT_SDK_URL);
+    Url::parse(url).with_context(|| {
+        format!(
+            "Invalid server URL '{}' for context '{}'",
+            url,
+            config.context_name()
+        )
+    })
+}
+
+/// A key of the config, used through `envx agent` if it holds the key and
+/// with the passphrase from the keyring or a prompt otherwise
+struct ConfigKey {
+    fingerprint: String,
+    config: Config,
+}
+
+fn key_error(error: anyhow::Error) -> envx::Error {
+    envx::Error::Key(format!("{:#}", error))
 }
 
-pub fn get_api_url() -> Url {
-    fn try_get_url() -> Result<Url> {
-        let dev_mode = std::env::var("DEV_MODE").is_ok();
-        if dev_mode {
-            return Ok(Url::parse("http://localhost:3000")?);
-        }
-        let url = get_config()?
-            .sdk_url
-            .unwrap_or("https://api.env-cli.com".into());
-        let url = Url::parse(&url)?;
-        Ok(url)
+impl KeyProvider for ConfigKey {
+    fn fingerprint(&self) -> String {
+        self.fingerprint.clone()
+    }
+
+    fn decrypt(&self, message: &Message) -> envx::Result<Message> {
+        self.decrypt_many(std::slice::from_ref(message))?
+            .pop()
+            .ok_or_else(|| envx::Error::Key("Failed to find message".into()))
+    }
+
+    fn decrypt_many(&self, messages: &[Message]) -> envx::Result<Vec<Message>> {
+        decrypt_many_messages(messages, &self.config).map_err(key_error)
+    }
+
+    fn sign(&self, text: &str) -> envx::Result<String> {
+        sign_challenge(&self.fingerprint, text).map_err(key_error)
     }
-    match try_get_url() {
-        Ok(u) => u,
-        Err(_) => Url::parse("http://localhost:3000")
-            .context("Failed to parse URL, this should literally never happen")
-            .unwrap(),
+
+    fn sign_many(&self, texts: &[String]) -> envx::Result<Vec<Message>> {
+        sign_many(texts, &self.fingerprint, &self.config).map_err(key_error)
     }
 }
 
 #[allow(clippy::upper_case_acronyms)]
 pub(crate) struct SDK {}
 impl SDK {
-    async fn auth_header(partial_fingerprint: &str) -> Result<String> {
+    /// A client for the server of the current context, authenticating with
+    /// the key of `partial_fingerprint`
+    fn client(partial_fingerprint: &str) -> Result<Client> {
         let config = get_config()?;
         let key = config.get_key(partial_fingerprint)?;
         let Some(uuid) = key.uuid else {
             bail!("No UUID for key {}\nTry envx upload", partial_fingerprint)
         };
-        let auth_token = get_token(&key.fingerprint, &uuid).await?;
-        Ok(format!("Bearer {}", auth_token))
+        warn_if_insecure(&config.connection);
+
+        // connection, retry settings and pinned keys come from the config
+        let client = Client::builder()
+            .config_dir(dirs::config_dir()?)
+            .context(config.context_name())
+            .from_config()?
+            .url(get_api_url()?)
+            .user_id(uuid)
+            .own_keys(own_keys(&config))
+            .key(ConfigKey {
+                fingerprint: key.fingerprint,
+                config,
+            })
+            .build()?;
+        Ok(client)
     }
 
     pub async fn new_user(username: &str, public_key: &str) -> Result<String> {
-        let client = reqwest::Client::new();
+        let client = http_client()?;
 
         let body = json!({
             "username": username,
             "public_key": public_key
         });
 
-        let url = get_api_url().join("/user/new")?;
-        let res = client.post(url).json(&body).send().await;
+        let url = get_api_url()?.join("/user/new")?;
+        let res = client.post(url).json(&body).send_with_retry().await;
 
         let res = match res {
             Ok(r) => r.text().await?,
@@ -81,25 +137,11 @@ impl SDK {
     pub async fn get_project_info(
         project_id: &str,
         partial_fingerprint: &str,
-    ) -> Result<ProjectInfo> {
-        let client = reqwest::Client::new();
-
-        let url = get_api_url().join("project/")?.join(project_id)?;
-
-        let project_info = client
-            .get(url)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .send()
-            .await
-            .context("Failed to get project info")?
-            .json::<ProjectInfo>()
+    ) -> Result<Project> {
+        Self::client(partial_fingerprint)?
+            .project(project_id)
             .await
-            .context("Failed to parse project info")?;
-
-        Ok(project_info)
+            .context("Failed to get project info")
     }
 
     pub async fn set_many(
@@ -107,167 +149,46 @@ impl SDK {
         partial_fingerprint: &str,
         project_id: &str,
     ) -> Result<Vec<String>> {
-        let client = reqwest::Client::new();
-
-        let project_info =
-            Self::get_project_info(project_id, partial_fingerprint).await?;
-
-        let recipients = project_info
-            .users
-            .iter()
-            .map(|u| u.public_key.as_str())
-            .collect::<Vec<&str>>();
-
-        let pubkeys = recipients
-            .iter()
-            .map(|k| Ok(SignedPublicKey::from_string(k)?.0))
-            .collect::<Result<Vec<SignedPublicKey>>>()?;
-
-        let messages = kvpairs
-            .par_iter()
-            .map(|k| encrypt_multi(&k.to_json()?, &pubkeys))
-            .collect::<Result<Vec<String>>>()?;
-
-        let body = json!({
-            "project_id": project_id,
-            "variables": messages,
-        });
-
-        #[derive(Serialize, Deserialize, Debug)]
-        pub struct SetManyVariableReturnType {
-            pub id: String,
-        }
-
-        let url = get_api_url().join("/variables/set-many")?;
-
-        let res = client
-            .post(url)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .json(&body)
-            .send()
-            .await?;
-
-        let res = res
-            .json::<Vec<SetManyVariableReturnType>>()
-            .await?
-            .iter()
-            .map(|r| &r.id)
-            .cloned()
-            .collect::<Vec<String>>();
+        let variables = kvpairs
+            .into_iter()
+            .map(|k| (k.key, k.value))
+            .collect::<Vec<(String, String)>>();
 
-        Ok(res)
+        Self::client(partial_fingerprint)?
+            .set_variables(project_id, &variables)
+            .await
+            .map_err(untrusted)
     }
 
     pub async fn get_all_variables(
         partial_fingerprint: &str,
     ) -> Result<(Vec<KVPair>, Vec<ParsedPartialVariable>)> {
-        // GET /user/:id/variables
-        let config = get_config()?;
-        let key = config.get_key(partial_fingerprint)?;
-
-        let client = reqwest::Client::new();
-
-        let mut url = get_api_url();
-        url.set_path(&format!(
-            "/user/{}/variables",
-            key.uuid.context("No UUID for key, try `envx upload`")?
-        ));
-
-        let encrypted = client
-            .get(url)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .send()
+        let partials = Self::client(partial_fingerprint)?
+            .user_variables()
             .await
             .context("Failed to get variables")?
-            .json::<Vec<PartialVariable>>()
-            .await
-            .context("Failed to parse API response into PartialVariables")?;
-
-        let decrypted = decrypt_full_many(
-            encrypted
-                .iter()
-                .map(|e| e.value.clone())
-                .collect::<Vec<String>>(),
-            &get_config()?,
-        )?;
-
-        let parsed = decrypted
-            .iter()
-            .map(|d| KVPair::from_json(d))
-            .collect::<Result<Vec<KVPair>>>()?;
-
-        let partials = decrypted
             .into_iter()
-            .zip(encrypted.into_iter())
-            .map(move |(d, e)| {
-                Ok(ParsedPartialVariable {
-                    id: e.id,
-                    value: KVPair::from_json(&d)?,
-                    project_id: e.project_id,
-                    created_at: e.created_at,
-                })
-            })
-            .collect::<Result<Vec<ParsedPartialVariable>>>()?;
+            .map(ParsedPartialVariable::from)
+            .collect::<Vec<ParsedPartialVariable>>();
 
-        Ok((parsed, partials))
+        Ok((partials.to_kvpair(), partials))
     }
 
-    /// You're probably looking for `get_variables_pruned` instead
+    /// Every version of every variable, you're probably looking for
+    /// `get_variables_pruned` instead
     pub async fn get_variables(
         project_id: &str,
         partial_fingerprint: &str,
-    ) -> Result<(Vec<KVPair>, Vec<PartialVariable>)> {
-        // url : /project/:id/variables
-        let client = reqwest::Client::new();
-
-        let url = get_api_url()
-            .join(&format!("/project/{}/variables", project_id))?;
-
-        let encrypted = client
-            .get(url)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .send()
-            .await
-            .context("Failed to get variables")?
-            .json::<Vec<PartialVariable>>()
+    ) -> Result<Vec<ParsedPartialVariable>> {
+        let variables = Self::client(partial_fingerprint)?
+            .history(project_id)
             .await
-            .context("Failed to parse API response into PartialVariables")?;
-
-        let decrypted = decrypt_full_many(
-            encrypted
-                .iter()
-                .map(|e| e.value.clone())
-                .collect::<Vec<String>>(),
-            &get_config()?,
-        )?;
-
-        // splice decrypted and encrypted into a Vector of PartialKey
-        let partials = decrypted
-            .iter()
-            .zip(encrypted.into_iter())
-            .map(|(d, e)| PartialVariable {
-                id: e.id,
-                value: d.clone(),
-                project_id: e.project_id,
-                created_at: e.created_at,
-            })
-            .collect::<Vec<PartialVariable>>();
-
-        let parsed = decrypted
-            .iter()
-            .map(|d| KVPair::from_json(d))
-            .collect::<Result<Vec<KVPair>>>()?;
+            .context("Failed to get variables")?;
 
-        Ok((parsed, partials))
+        Ok(variables
+            .into_iter()
+            .map(ParsedPartialVariable::from)
+            .collect())
     }
 
     /// Return variables as a list of kv pairs
@@ -277,42 +198,67 @@ impl SDK {
         project_id: &str,
         partial_fingerprint: &str,
     ) -> Result<Vec<KVPair>> {
-        let (kvpairs, partial) =
-            Self::get_variables(project_id, partial_fingerprint)
-                .await
-                .context("Failed to get variables")?;
-        let mut pruned = partial.zip_to_parsed(kvpairs).to_kvpair();
-        pruned.sort_by(|a, b| a.key.cmp(&b.key));
+        let pruned =
+            Self::get_parsed_variables_pruned(project_id, partial_fingerprint)
+                .await?
+                .to_kvpair();
+        Ok(pruned)
+    }
+
+    /// Like `get_variables_pruned`, but keeps the record metadata (author,
+    /// creation date, ...) alongside each kv pair
+    pub async fn get_parsed_variables_pruned(
+        project_id: &str,
+        partial_fingerprint: &str,
+    ) -> Result<Vec<ParsedPartialVariable>> {
+        let pruned = Self::client(partial_fingerprint)?
+            .variables(project_id)
+            .await
+            .context("Failed to get variables")?
+            .into_iter()
+            .map(ParsedPartialVariable::from)
+            .collect::<Vec<ParsedPartialVariable>>();
+
+        // Only the versions in use matter, older ones may predate signing
+        enforce_signatures(
+            project_id,
+            pruned.iter().map(|v| (v.value.key.as_str(), &v.signer)),
+            get_config()?
+                .get_settings()?
+                .require_signatures
+                .iter()
+                .any(|p| p == project_id),
+        )?;
+        completion::record_variables(
+            project_id,
+            pruned.iter().map(|v| (v.id.as_str(), v.value.key.as_str())),
+        );
         Ok(pruned)
     }
 
+    /// Re-encrypt all variables of a project to the given recipients
+    ///
+    /// The original (signed) messages are re-encrypted as they are, so the
+    /// authors of the variables are kept. Returns the updated variable IDs
+    pub async fn rekey_variables(
+        project_id: &str,
+        partial_fingerprint: &str,
+        pubkeys: &[SignedPublicKey],
+    ) -> Result<Vec<String>> {
+        Self::client(partial_fingerprint)?
+            .rekey_variables(project_id, pubkeys)
+            .await
+            .context("Failed to re-encrypt variables")
+    }
+
+    /// The armored public key of a user
     pub async fn get_user(
         partial_fingerprint: &str,
         user_to_get: &str,
-    ) -> Result<(String, String)> {
-        // url: /user/:id
-        let client = reqwest::Client::new();
-
-        #[derive(Serialize, Deserialize, Debug)]
-        pub struct StrippedUser {
-            pub id: String,
-            pub public_key: String,
-        }
-
-        let url = get_api_url().join("user/")?.join(user_to_get)?;
-
-        let user = client
-            .get(url)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .send()
-            .await?
-            .json::<StrippedUser>()
-            .await?;
-
-        Ok((user.id, user.public_key))
+    ) -> Result<String> {
+        Ok(Self::client(partial_fingerprint)?
+            .public_key(user_to_get)
+            .await?)
     }
 
     pub async fn add_user_to_project(
@@ -320,33 +266,10 @@ impl SDK {
         user_to_add: &str,
         project_id: &str,
     ) -> Result<()> {
-        // url: /project/:id/add-user
-        let client = reqwest::Client::new();
-
-        let body = json!({
-            "user_id": user_to_add
-        });
-
-        let url =
-            get_api_url().join(&format!("/project/{}/add-user", project_id))?;
-
-        let res = client
-            .post(url.join(&format!("/project/{}/add-user", project_id))?)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .json(&body)
-            .send()
-            .await?;
-
-        let status = res.status();
-
-        if status.is_success() {
-            Ok(())
-        } else {
-            bail!("Failed to add user to project: {}", res.text().await?)
-        }
+        Self::client(partial_fingerprint)?
+            .add_user(project_id, user_to_add)
+            .await
+            .context("Failed to add user to project")
     }
 
     pub async fn remove_users_from_project(
@@ -354,148 +277,60 @@ impl SDK {
         users_to_remove: Vec<String>,
         project_id: &str,
     ) -> Result<()> {
-        // url: /project/:id/remove-user
-        let client = reqwest::Client::new();
-
-        let body = json!({
-            "users": users_to_remove
-        });
-
-        let url = get_api_url()
-            .join(&format!("/project/{}/remove-user", project_id))?;
-
-        let res = client
-            .post(url)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .json(&body)
-            .send()
-            .await?;
-
-        let status = res.status();
-
-        if status.is_success() {
-            Ok(())
-        } else {
-            bail!("Failed to remove user from project: {}", res.text().await?)
-        }
+        Self::client(partial_fingerprint)?
+            .remove_users(project_id, &users_to_remove)
+            .await
+            .context("Failed to remove user from project")
     }
 
     pub async fn delete_project(
         partial_fingerprint: &str,
         project_id: &str,
     ) -> Result<()> {
-        // url: /project/:id
-        let client = reqwest::Client::new();
-
-        let url = get_api_url().join(&format!("/project/{}", project_id))?;
-
-        let res = client
-            .delete(url)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .send()
-            .await?;
-
-        let status = res.status();
-
-        if status.is_success() {
-            Ok(())
-        } else {
-            bail!("Failed to delete project: {}", res.text().await?)
-        }
+        Self::client(partial_fingerprint)?
+            .delete_project(project_id)
+            .await
+            .context("Failed to delete project")
     }
 
     pub async fn delete_variable(
         variable_id: &str,
         partial_fingerprint: &str,
     ) -> Result<()> {
-        // url: DELETE /variables/:id
-        let client = reqwest::Client::new();
-
-        let url = get_api_url().join("variables/")?.join(variable_id)?;
-
-        client
-            .delete(url)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .send()
-            .await?;
-
-        Ok(())
+        Self::client(partial_fingerprint)?
+            .delete_variable(variable_id)
+            .await
+            .context("Failed to delete variable")
     }
 
     pub async fn list_projects(
         partial_fingerprint: &str,
     ) -> Result<Vec<String>> {
-        // GET /projects
-        let client = reqwest::Client::new();
-
-        let url = get_api_url().join("projects")?;
-
-        let res = client
-            .get(url)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .send()
+        let res = Self::client(partial_fingerprint)?
+            .projects()
             .await
             .context("Failed to get projects")?;
-
-        let res = res
-            .json::<Vec<String>>()
-            .await
-            .context("Failed to parse API response into Vec<String>")?;
+        completion::record_projects(&res);
 
         Ok(res)
     }
 
     pub async fn new_project(partial_fingerprint: &str) -> Result<String> {
-        // POST /projects/new
-        let client = reqwest::Client::new();
-
-        let res = client
-            .post(get_api_url().join("projects/new")?)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .send()
-            .await?
-            .text()
-            .await?;
-
-        Ok(res)
+        Ok(Self::client(partial_fingerprint)?.new_project().await?)
     }
-    pub async fn delete_key(partial_fingerprint: &str) -> Result<()> {
-        // DELETE /user/:id
-        let client = reqwest::Client::new();
-
-        let config = get_config()?;
-        let key = config.get_key(partial_fingerprint)?;
-
-        let uuid = key.uuid.context("No UUID for key, try `envx upload`")?;
 
-        let url = get_api_url().join("user/")?.join(&uuid)?;
-
-        client
-            .delete(url)
-            .header(
-                header::AUTHORIZATION,
-                Self::auth_header(partial_fingerprint).await?,
-            )
-            .send()
-            .await?
-            .text()
-            .await?;
+    /// Publish a revoked public key so the server stops accepting it
+    pub async fn revoke_key(
+        partial_fingerprint: &str,
+        revoked_public_key: &str,
+    ) -> Result<()> {
+        Self::client(partial_fingerprint)?
+            .revoke_key(revoked_public_key)
+            .await
+            .context("Failed to publish revocation")
+    }
 
-        Ok(())
+    pub async fn delete_key(partial_fingerprint: &str) -> Result<()> {
+        Ok(Self::client(partial_fingerprint)?.delete_user().await?)
     }
 }
@@ -0,0 +1,261 @@
+//! Who wrote a variable, according to its signature
+//!
+//! Every record envx writes is signed by its author before it is
+//! encrypted, together with the id of the project it was written for.
+//! Signatures are checked against the keys of the project members, and
+//! only count as verified for keys that are pinned or our own, see
+//! [`known_keys`](crate::known_keys).
+
+use crate::known_keys::MemberKey;
+use crate::models::PartialUser;
+use pgp::composed::message::Message;
+use pgp::types::KeyTrait;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// What envx signs and encrypts as a version of a variable
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Record {
+    pub key: String,
+    pub value: String,
+    /// The project the record was written for, so it can't be replayed
+    /// into another project of its author
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+}
+
+/// Who wrote a variable record, according to its signature
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "status", content = "user", rename_all = "snake_case")]
+pub enum Signer {
+    /// Signed by a project member whose key is pinned
+    Verified(PartialUser),
+    /// Signed by a project member whose key has not been trusted yet
+    Untrusted(PartialUser),
+    /// Signed by a key that doesn't belong to any project member
+    Unknown(String),
+    /// The signature doesn't match the record, or the record was signed
+    /// for another project
+    Invalid,
+    /// The record has no signature
+    Unsigned,
+    /// The signature has not been checked
+    #[default]
+    Unchecked,
+}
+
+impl Signer {
+    pub fn is_verified(&self) -> bool {
+        matches!(self, Signer::Verified(_))
+    }
+}
+
+impl Display for Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Signer::Verified(user) => write!(f, "{}", user.username),
+            Signer::Untrusted(user) => {
+                write!(f, "{} (untrusted key)", user.username)
+            }
+            Signer::Unknown(key_id) => write!(f, "unknown key {}", key_id),
+            Signer::Invalid => write!(f, "INVALID SIGNATURE"),
+            Signer::Unsigned => write!(f, "unsigned"),
+            Signer::Unchecked => write!(f, "not verified"),
+        }
+    }
+}
+
+/// Verify the signature of a decrypted message against the project members
+pub fn verify_signer(msg: &Message, members: &[MemberKey]) -> Signer {
+    let Message::Signed { signature, .. } = msg else {
+        return Signer::Unsigned;
+    };
+
+    let member = match signature.issuer() {
+        Some(issuer) => {
+            members.iter().find(|m| &m.public_key.key_id() == issuer)
+        }
+        None => members.iter().find(|m| msg.verify(&m.public_key).is_ok()),
+    };
+
+    let Some(member) = member else {
+        return Signer::Unknown(
+            signature
+                .issuer()
+                .map(|i| format!("{:X}", i))
+                .unwrap_or_default(),
+        );
+    };
+
+    if msg.verify(&member.public_key).is_err() {
+        return Signer::Invalid;
+    }
+
+    let user = PartialUser::from(member.user.clone());
+    if member.is_trusted() {
+        Signer::Verified(user)
+    } else {
+        Signer::Untrusted(user)
+    }
+}
+
+/// Verify the signature of a decrypted record of `project_id`
+///
+/// Signed records written for another project, or for no project in
+/// particular, are [`Signer::Invalid`].
+pub fn verify_record(
+    msg: &Message,
+    project_id: &str,
+    members: &[MemberKey],
+) -> Signer {
+    let signer = verify_signer(msg, members);
+    if !matches!(signer, Signer::Verified(_) | Signer::Untrusted(_)) {
+        return signer;
+    }
+
+    let record =
+        msg.get_content().ok().flatten().and_then(|content| {
+            serde_json::from_slice::<Record>(&content).ok()
+        });
+    match record.and_then(|r| r.project_id) {
+        Some(signed_for) if signed_for == project_id => signer,
+        _ => Signer::Invalid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::known_keys::KnownKeys;
+    use crate::models::User;
+    use pgp::composed::{KeyType, SecretKeyParamsBuilder};
+    use pgp::crypto::hash::HashAlgorithm;
+    use pgp::types::SecretKeyTrait;
+    use pgp::{SignedPublicKey, SignedSecretKey};
+
+    const PROJECT: &str = "project";
+
+    fn member(name: &str) -> (SignedSecretKey, User) {
+        let key = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_sign(true)
+            .primary_user_id(name.into())
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap()
+            .sign(String::new)
+            .unwrap();
+        let public_key = SignedPublicKey::new(
+            key.primary_key.public_key(),
+            key.details.clone(),
+            vec![],
+        );
+        let user = User {
+            id: name.into(),
+            username: name.into(),
+            created_at: "".into(),
+            public_key: public_key.to_armored_string(None).unwrap(),
+        };
+        (key, user)
+    }
+
+    fn signed_by(key: &SignedSecretKey) -> Message {
+        Message::new_literal("none", "{}")
+            .sign(key, String::new, HashAlgorithm::SHA2_256)
+            .unwrap()
+    }
+
+    fn record_signed_by(
+        key: &SignedSecretKey,
+        project_id: Option<&str>,
+    ) -> Message {
+        let record = serde_json::to_string(&Record {
+            key: "KEY".into(),
+            value: "value".into(),
+            project_id: project_id.map(String::from),
+        })
+        .unwrap();
+        Message::new_literal("none", &record)
+            .sign(key, String::new, HashAlgorithm::SHA2_256)
+            .unwrap()
+    }
+
+    fn signer(known_keys: &KnownKeys, users: &[User], msg: &Message) -> Signer {
+        let members = known_keys.check(PROJECT, users, &[]).unwrap();
+        verify_signer(msg, &members)
+    }
+
+    #[test]
+    fn trusts_only_pinned_keys() {
+        let (alice_key, alice) = member("alice");
+        let msg = signed_by(&alice_key);
+        let mut known_keys = KnownKeys::default();
+
+        let users = [alice.clone()];
+        assert!(matches!(
+            signer(&known_keys, &users, &msg),
+            Signer::Untrusted(_)
+        ));
+
+        let members = known_keys.check(PROJECT, &users, &[]).unwrap();
+        known_keys.pin(PROJECT, &members);
+        assert!(signer(&known_keys, &users, &msg).is_verified());
+
+        // the server swaps in another key for the pinned user
+        let (mallory_key, mallory) = member("mallory");
+        let forged = User {
+            public_key: mallory.public_key,
+            ..alice
+        };
+        assert!(matches!(
+            signer(&known_keys, &[forged], &signed_by(&mallory_key)),
+            Signer::Untrusted(_)
+        ));
+    }
+
+    #[test]
+    fn trusts_own_keys() {
+        let (key, user) = member("me");
+        let members = KnownKeys::default()
+            .check(PROJECT, &[user], &[hex::encode(key.fingerprint())])
+            .unwrap();
+        assert!(verify_signer(&signed_by(&key), &members).is_verified());
+    }
+
+    #[test]
+    fn binds_records_to_their_project() {
+        let (key, user) = member("me");
+        let members = KnownKeys::default()
+            .check(PROJECT, &[user], &[hex::encode(key.fingerprint())])
+            .unwrap();
+        let verify = |project_id: Option<&str>| {
+            verify_record(
+                &record_signed_by(&key, project_id),
+                PROJECT,
+                &members,
+            )
+        };
+
+        assert!(verify(Some(PROJECT)).is_verified());
+        assert!(matches!(verify(Some("other")), Signer::Invalid));
+        assert!(matches!(verify(None), Signer::Invalid));
+    }
+
+    #[test]
+    fn flags_records_not_signed_by_members() {
+        let (_, alice) = member("alice");
+        let (stranger_key, _) = member("stranger");
+        let known_keys = KnownKeys::default();
+        let users = [alice];
+
+        assert!(matches!(
+            signer(&known_keys, &users, &signed_by(&stranger_key)),
+            Signer::Unknown(_)
+        ));
+        assert!(matches!(
+            signer(&known_keys, &users, &Message::new_literal("none", "{}")),
+            Signer::Unsigned
+        ));
+    }
+}
@@ -0,0 +1,502 @@
+// `envx agent`, which keeps unlocked keys in memory
+//
+// The agent decrypts and signs on behalf of other envx processes so they
+// never need the passphrase. It listens on a 0600 Unix socket in the runtime
+// dir (or ENVX_AGENT_SOCK) and answers one JSON request per connection.
+// Keys that aren't used for `agent_idle_timeout` are locked again.
+
+use super::env::{self, ENVX_AGENT_SOCK};
+use anyhow::{bail, Context, Result};
+use pgp::composed::message::Message;
+use pgp::Deserializable;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    Unlock {
+        fingerprint: String,
+        passphrase: String,
+    },
+    /// Lock one key, or all of them
+    Lock {
+        fingerprint: Option<String>,
+    },
+    Status,
+    Decrypt {
+        fingerprint: String,
+        messages: Vec<String>,
+    },
+    Sign {
+        fingerprint: String,
+        text: String,
+    },
+    /// Sign every text, like `Sign` but in one round trip
+    SignMany {
+        fingerprint: String,
+        texts: Vec<String>,
+    },
+    Stop,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Response {
+    Ok,
+    Error {
+        message: String,
+    },
+    /// The key isn't unlocked in the agent
+    Locked,
+    Status(AgentStatus),
+    /// Armored decrypted messages
+    Decrypted {
+        messages: Vec<String>,
+    },
+    Signature {
+        armored: String,
+    },
+    /// Armored signed messages, in the order of the texts
+    Signed {
+        messages: Vec<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgentStatus {
+    pub pid: u32,
+    /// Seconds a key may stay unused before it is locked
+    pub idle_timeout: u64,
+    pub keys: Vec<KeyStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyStatus {
+    pub fingerprint: String,
+    /// Seconds until the key is locked unless it is used
+    pub locks_in: u64,
+}
+
+/// Where the agent listens
+pub fn socket_path() -> Result<PathBuf> {
+    if let Some(path) = env::var(ENVX_AGENT_SOCK) {
+        return Ok(PathBuf::from(path));
+    }
+    Ok(super::dirs::runtime_dir().join("agent.sock"))
+}
+
+/// Send a request to the agent, failing if it isn't running
+pub fn request(request: &Request) -> Result<Response> {
+    #[cfg(unix)]
+    {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::fs::MetadataExt;
+        use std::os::unix::net::UnixStream;
+
+        let path = socket_path()?;
+        // passphrases are sent over the socket, make sure it is ours
+        if let Ok(metadata) = std::fs::metadata(&path) {
+            if metadata.uid() != super::dirs::user_id() {
+                bail!(
+                    "{} is owned by another user, refusing to use it",
+                    path.display()
+                );
+            }
+        }
+        let mut stream = UnixStream::connect(&path).with_context(|| {
+            format!(
+                "The agent is not running ({}), start it with `envx agent start`",
+                path.display()
+            )
+        })?;
+
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        stream
+            .write_all(line.as_bytes())
+            .context("Failed to send request to the agent")?;
+
+        let mut response = String::new();
+        BufReader::new(stream)
+            .read_line(&mut response)
+            .context("Failed to read response from the agent")?;
+        serde_json::from_str(&response)
+            .context("Invalid response from the agent")
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = request;
+        bail!("envx agent is only supported on Unix")
+    }
+}
+
+/// Whether an agent is listening on the socket
+pub fn is_running() -> bool {
+    socket_path().is_ok_and(|path| path.exists())
+        && matches!(request(&Request::Status), Ok(Response::Status(_)))
+}
+
+/// Decrypt with a key held by the agent
+///
+/// `None` if the agent isn't running or doesn't hold the key, in which case
+/// the caller decrypts itself.
+pub fn decrypt(
+    fingerprint: &str,
+    messages: &[String],
+) -> Option<Result<Vec<Message>>> {
+    if !socket_path().is_ok_and(|path| path.exists()) {
+        return None;
+    }
+
+    let response = request(&Request::Decrypt {
+        fingerprint: fingerprint.to_string(),
+        messages: messages.to_vec(),
+    });
+    match response {
+        Ok(Response::Decrypted { messages }) => Some(
+            messages
+                .iter()
+                .map(|m| {
+                    Message::from_string(m)
+                        .map(|(msg, _)| msg)
+                        .context("Invalid message from the agent")
+                })
+                .collect(),
+        ),
+        Ok(Response::Error { message }) => {
+            Some(Err(anyhow::anyhow!("Agent: {}", message)))
+        }
+        _ => None,
+    }
+}
+
+/// Sign `text` with a key held by the agent, returning the armored message
+///
+/// `None` if the agent isn't running or doesn't hold the key.
+pub fn sign(fingerprint: &str, text: &str) -> Option<Result<String>> {
+    if !socket_path().is_ok_and(|path| path.exists()) {
+        return None;
+    }
+
+    let response = request(&Request::Sign {
+        fingerprint: fingerprint.to_string(),
+        text: text.to_string(),
+    });
+    match response {
+        Ok(Response::Signature { armored }) => Some(Ok(armored)),
+        Ok(Response::Error { message }) => {
+            Some(Err(anyhow::anyhow!("Agent: {}", message)))
+        }
+        _ => None,
+    }
+}
+
+/// Sign every text with a key held by the agent
+///
+/// `None` if the agent isn't running or doesn't hold the key, in which case
+/// the caller signs itself.
+pub fn sign_many(
+    fingerprint: &str,
+    texts: &[String],
+) -> Option<Result<Vec<Message>>> {
+    if !socket_path().is_ok_and(|path| path.exists()) {
+        return None;
+    }
+
+    let response = request(&Request::SignMany {
+        fingerprint: fingerprint.to_string(),
+        texts: texts.to_vec(),
+    });
+    match response {
+        Ok(Response::Signed { messages }) => Some(
+            messages
+                .iter()
+                .map(|m| {
+                    Message::from_string(m)
+                        .map(|(msg, _)| msg)
+                        .context("Invalid message from the agent")
+                })
+                .collect(),
+        ),
+        Ok(Response::Error { message }) => {
+            Some(Err(anyhow::anyhow!("Agent: {}", message)))
+        }
+        _ => None,
+    }
+}
+
+/// Turn an unexpected response into an error
+pub fn expect_ok(response: Response) -> Result<()> {
+    match response {
+        Response::Ok => Ok(()),
+        Response::Error { message } => bail!("{}", message),
+        Response::Locked => bail!("The key is not unlocked in the agent"),
+        other => bail!("Unexpected response from the agent: {:?}", other),
+    }
+}
+
+#[cfg(unix)]
+pub use server::serve;
+
+#[cfg(unix)]
+mod server {
+    use super::*;
+    use crate::info;
+    use crate::utils::{dirs, rpgp};
+    use pgp::{types::SecretKeyTrait, SignedSecretKey};
+    use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::sync::Notify;
+
+    struct UnlockedKey {
+        key: SignedSecretKey,
+        passphrase: String,
+        last_used: Instant,
+    }
+
+    struct State {
+        keys: HashMap<String, UnlockedKey>,
+        idle_timeout: Duration,
+    }
+
+    type Shared = Arc<Mutex<State>>;
+
+    /// Run the agent until it is stopped
+    pub async fn serve(idle_timeout: Duration) -> Result<()> {
+        lock_memory();
+
+        let path = socket_path()?;
+        if path.exists() {
+            if UnixStream::connect(&path).await.is_ok() {
+                bail!("An agent is already running on {}", path.display());
+            }
+            fs::remove_file(&path).context("Failed to remove stale socket")?;
+        }
+        if env::var(ENVX_AGENT_SOCK).is_none() {
+            dirs::private_runtime_dir()?;
+        }
+
+        let listener = UnixListener::bind(&path).with_context(|| {
+            format!("Failed to listen on {}", path.display())
+        })?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+            .context("Failed to set permissions of the socket")?;
+
+        info!("Agent listening on {}", path.display());
+
+        let state = Arc::new(Mutex::new(State {
+            keys: HashMap::new(),
+            idle_timeout,
+        }));
+        let stop = Arc::new(Notify::new());
+        let mut ticker = tokio::time::interval(Duration::from_secs(10));
+
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let Ok((stream, _)) = accepted else { continue };
+                    let state = state.clone();
+                    let stop = stop.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle(stream, state, stop).await {
+                            eprintln!("Agent: {:#}", e);
+                        }
+                    });
+                }
+                _ = ticker.tick() => lock_idle_keys(&state),
+                _ = stop.notified() => break,
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+
+        let _ = fs::remove_file(&path);
+        Ok(())
+    }
+
+    /// Keep the keys out of swap and core dumps, as far as the system allows
+    fn lock_memory() {
+        // SAFETY: mlockall only changes how memory of this process is paged
+        let locked =
+            unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) };
+        if locked != 0 {
+            eprintln!(
+                "WARNING: Failed to lock the agent's memory, keys may be swapped to disk"
+            );
+        }
+
+        #[cfg(target_os = "linux")]
+        // SAFETY: PR_SET_DUMPABLE takes a plain integer argument
+        unsafe {
+            libc::prctl(libc::PR_SET_DUMPABLE, 0);
+        }
+    }
+
+    fn lock_idle_keys(state: &Shared) {
+        let mut state = state.lock().unwrap();
+        let timeout = state.idle_timeout;
+        state.keys.retain(|_, k| k.last_used.elapsed() < timeout);
+    }
+
+    async fn handle(
+        stream: UnixStream,
+        state: Shared,
+        stop: Arc<Notify>,
+    ) -> Result<()> {
+        // the socket is 0600, but refuse other users all the same
+        let peer = stream.peer_cred().context("Failed to get peer")?;
+        if peer.uid() != dirs::user_id() {
+            bail!("Refused connection from uid {}", peer.uid());
+        }
+
+        let (reader, mut writer) = stream.into_split();
+        let mut line = String::new();
+        BufReader::new(reader).read_line(&mut line).await?;
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Stop) => {
+                stop.notify_one();
+                Response::Ok
+            }
+            Ok(request) => {
+                let state = state.clone();
+                tokio::task::spawn_blocking(move || respond(request, &state))
+                    .await?
+            }
+            Err(e) => Response::Error {
+                message: format!("Invalid request: {}", e),
+            },
+        };
+
+        let mut response = serde_json::to_string(&response)?;
+        response.push('\n');
+        writer.write_all(response.as_bytes()).await?;
+        Ok(())
+    }
+
+    fn respond(request: Request, state: &Shared) -> Response {
+        match run(request, state) {
+            Ok(response) => response,
+            Err(e) => Response::Error {
+                message: format!("{:#}", e),
+            },
+        }
+    }
+
+    fn run(request: Request, state: &Shared) -> Result<Response> {
+        match request {
+            Request::Unlock {
+                fingerprint,
+                passphrase,
+            } => {
+                let (key, _) = rpgp::get_key(fingerprint.clone())?;
+                key.unlock(|| passphrase.clone(), |_| Ok(()))
+                    .map_err(|_| anyhow::anyhow!("Wrong passphrase"))?;
+                state.lock().unwrap().keys.insert(
+                    fingerprint,
+                    UnlockedKey {
+                        key,
+                        passphrase,
+                        last_used: Instant::now(),
+                    },
+                );
+                Ok(Response::Ok)
+            }
+            Request::Lock { fingerprint } => {
+                let mut state = state.lock().unwrap();
+                match fingerprint {
+                    Some(fingerprint) => {
+                        state.keys.remove(&fingerprint);
+                    }
+                    None => state.keys.clear(),
+                }
+                Ok(Response::Ok)
+            }
+            Request::Status => {
+                let state = state.lock().unwrap();
+                let mut keys = state
+                    .keys
+                    .iter()
+                    .map(|(fingerprint, k)| KeyStatus {
+                        fingerprint: fingerprint.clone(),
+                        locks_in: state
+                            .idle_timeout
+                            .saturating_sub(k.last_used.elapsed())
+                            .as_secs(),
+                    })
+                    .collect::<Vec<KeyStatus>>();
+                keys.sort_by(|a, b| a.fingerprint.cmp(&b.fingerprint));
+                Ok(Response::Status(AgentStatus {
+                    pid: std::process::id(),
+                    idle_timeout: state.idle_timeout.as_secs(),
+                    keys,
+                }))
+            }
+            Request::Decrypt {
+                fingerprint,
+                messages,
+            } => {
+                let Some((key, passphrase)) = take_key(state, &fingerprint)
+                else {
+                    return Ok(Response::Locked);
+                };
+                let messages = messages
+                    .par_iter()
+                    .map(|m| {
+                        rpgp::decrypt_message(m, &key, passphrase.clone())?
+                            .to_armored_string(None)
+                            .context("Failed to armor decrypted message")
+                    })
+                    .collect::<Result<Vec<String>>>()?;
+                Ok(Response::Decrypted { messages })
+            }
+            Request::Sign { fingerprint, text } => {
+                let Some((key, passphrase)) = take_key(state, &fingerprint)
+                else {
+                    return Ok(Response::Locked);
+                };
+                let armored = rpgp::sign_message(&text, &key, passphrase)?
+                    .to_armored_string(None)
+                    .context("Failed to armor signature")?;
+                Ok(Response::Signature { armored })
+            }
+            Request::SignMany { fingerprint, texts } => {
+                let Some((key, passphrase)) = take_key(state, &fingerprint)
+                else {
+                    return Ok(Response::Locked);
+                };
+                let messages = texts
+                    .par_iter()
+                    .map(|text| {
+                        rpgp::sign_message(text, &key, passphrase.clone())?
+                            .to_armored_string(None)
+                            .context("Failed to armor signed message")
+                    })
+                    .collect::<Result<Vec<String>>>()?;
+                Ok(Response::Signed { messages })
+            }
+            Request::Stop => Ok(Response::Ok),
+        }
+    }
+
+    /// A copy of an unlocked key, marking it as used
+    fn take_key(
+        state: &Shared,
+        fingerprint: &str,
+    ) -> Option<(SignedSecretKey, String)> {
+        let mut state = state.lock().unwrap();
+        let key = state
+            .keys
+            .iter_mut()
+            .find(|(fp, _)| fp.eq_ignore_ascii_case(fingerprint))
+            .map(|(_, k)| k)?;
+        key.last_used = Instant::now();
+        Some((key.key.clone(), key.passphrase.clone()))
+    }
+}
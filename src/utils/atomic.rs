@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Write a file atomically
+///
+/// The contents are written to a temporary file in the same directory,
+/// synced to disk and then renamed over the destination, so the original
+/// file is left untouched if anything fails along the way.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    write_atomic_with(path, contents, None)
+}
+
+/// Write a file atomically, readable and writable by the owner only (0600)
+///
+/// Use this for anything secret, the file never exists with looser
+/// permissions, not even briefly.
+pub fn write_atomic_private(path: &Path, contents: &[u8]) -> Result<()> {
+    write_atomic_with(path, contents, Some(0o600))
+}
+
+fn write_atomic_with(
+    path: &Path,
+    contents: &[u8],
+    mode: Option<u32>,
+) -> Result<()> {
+    let parent = path.parent().context("Failed to get parent directory")?;
+    let file_name = path
+        .file_name()
+        .context("Failed to get file name")?
+        .to_string_lossy();
+    let tmp_path =
+        parent.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = (|| -> Result<()> {
+        let mut file =
+            create(&tmp_path, mode).context("Failed to create temp file")?;
+        file.write_all(contents)
+            .context("Failed to write temp file")?;
+        file.sync_all().context("Failed to sync temp file")?;
+        fs::rename(&tmp_path, path).context("Failed to replace file")?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+#[cfg(unix)]
+fn create(path: &Path, mode: Option<u32>) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    if let Some(mode) = mode {
+        options.mode(mode);
+    }
+    options.open(path)
+}
+
+#[cfg(not(unix))]
+fn create(path: &Path, _mode: Option<u32>) -> std::io::Result<File> {
+    File::create(path)
+}
+
+/// Create a directory and its parents, accessible by the owner only (0700)
+pub fn create_private_dir(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        // `mode` doesn't apply to directories that already exist
+        fs::set_permissions(path, fs::Permissions::from_mode(0o700))
+            .with_context(|| {
+                format!("Failed to set permissions of {}", path.display())
+            })?;
+    }
+    #[cfg(not(unix))]
+    fs::create_dir_all(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+
+    Ok(())
+}
+
+/// An exclusive advisory lock on a file, released when dropped
+///
+/// The lock is taken on `<file>.lock` next to the file, because files
+/// written with `write_atomic` are replaced on every write.
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Block until no other envx process holds the lock on `path`
+    pub fn acquire(path: &Path) -> Result<Self> {
+        let mut file_name = path
+            .file_name()
+            .context("Failed to get file name")?
+            .to_os_string();
+        file_name.push(".lock");
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_file_name(file_name))
+            .with_context(|| {
+                format!("Failed to open lock file for {}", path.display())
+            })?;
+        file.lock_exclusive()
+            .with_context(|| format!("Failed to lock {}", path.display()))?;
+
+        Ok(Self { _file: file })
+    }
+}
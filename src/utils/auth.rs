@@ -1,16 +1,24 @@
 use crate::utils::config::get_config;
 use anyhow::{anyhow, Context};
 use chrono::Utc;
+pub use envx::AuthToken;
 use pgp::composed::message::Message;
 use pgp::{crypto, Deserializable, SignedSecretKey};
-use serde::{Deserialize, Serialize};
 
+use super::agent;
 use super::keyring::try_get_password;
 
 pub async fn get_token(
     fingerprint: &str,
     token: &str,
 ) -> anyhow::Result<AuthToken> {
+    let signature = sign_challenge(fingerprint, &Utc::now().to_string())?;
+    Ok(AuthToken::new(token.into(), signature))
+}
+
+/// Sign an authentication challenge with the key of `fingerprint`, through
+/// `envx agent` if it holds the key
+pub fn sign_challenge(fingerprint: &str, text: &str) -> anyhow::Result<String> {
     let config = get_config().context("Failed to get config")?;
     let key = config
         .keys
@@ -18,11 +26,15 @@ pub async fn get_token(
         .find(|k| k.fingerprint.contains(fingerprint))
         .ok_or_else(|| anyhow!("Key not found"))?;
 
+    if let Some(signature) = agent::sign(&key.fingerprint, text) {
+        return signature;
+    }
+
     let key = key.secret_key().context("Failed to get secret key")?;
     let (key, _) = SignedSecretKey::from_string(&key)
         .context("Failed to parse secret key")?;
 
-    let msg = Message::new_literal("none", &Utc::now().to_string());
+    let msg = Message::new_literal("none", text);
 
     let passphrase = try_get_password(fingerprint, &config)?;
     let pw = || passphrase;
@@ -35,53 +47,32 @@ pub async fn get_token(
             eprintln!("Failed to sign API authentication challenge: {}", e);
             if let pgp::errors::Error::Incomplete(_) = e {
                 eprintln!("This is most likely due to a missing or incorrect passphrase.");
-                println!(
+                eprintln!(
                     "You can view the saved passphrase with 'envx keyring view <fingerprint>'"
                 );
-                println!("Or you can check against the saved passphrase with 'envx keyring check -k <fingerprint> -p <passphrase>'");
-                println!("Both of these commands are interactive")
+                eprintln!("Or you can check against the saved passphrase with 'envx keyring check -k <fingerprint> -p <passphrase>'");
+                eprintln!("Both of these commands are interactive")
             }
 
             return Err(anyhow!("Failed to sign API authentication challenge"));
         }
     };
 
-    let signature = match signature.to_armored_string(None) {
-        Ok(s) => s,
+    match signature.to_armored_string(None) {
+        Ok(s) => Ok(s),
         Err(e) => {
-            println!("Failed to convert signature to armored string: {}", e);
-            return Err(anyhow!(
-                "Failed to convert signature to armored string"
-            ));
+            eprintln!("Failed to convert signature to armored string: {}", e);
+            Err(anyhow!("Failed to convert signature to armored string"))
         }
-    };
-    let auth_token = AuthToken::new(token.into(), signature);
-
-    Ok(auth_token)
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct AuthToken {
-    pub token: String,
-    pub signature: String,
-}
-
-impl AuthToken {
-    pub fn new(token: String, signature: String) -> Self {
-        Self { token, signature }
-    }
-}
-
-impl From<AuthToken> for String {
-    fn from(auth_token: AuthToken) -> String {
-        serde_json::to_string(&auth_token).unwrap()
     }
 }
 
-impl std::fmt::Display for AuthToken {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let string_token = serde_json::to_string(&self).unwrap();
-
-        write!(f, "{}", string_token)
-    }
+/// A token signed by `envx agent`, `None` if it doesn't hold the key
+pub fn agent_token(
+    fingerprint: &str,
+    token: &str,
+) -> Option<anyhow::Result<AuthToken>> {
+    let text = Utc::now().to_string();
+    agent::sign(fingerprint, &text)
+        .map(|signature| Ok(AuthToken::new(token.into(), signature?)))
 }
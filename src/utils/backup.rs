@@ -0,0 +1,482 @@
+// Offline (paper) backups of secret keys
+//
+// A backup is a bincode encoded `Backup`, printed either as numbered lines
+// of hex with a checksum per line (paperkey style), or as one-line chunks
+// that each fit in a QR code. Chunks carry the hash of the whole backup so
+// chunks of different backups are never put together.
+
+use anyhow::{anyhow, bail, Context, Result};
+use crypto_hash::{digest, Algorithm};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sharks::{Share, Sharks};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const BACKUP_VERSION: u8 = 1;
+const BYTES_PER_LINE: usize = 24;
+const BEGIN: &str = "-----BEGIN ENVX KEY BACKUP-----";
+const END: &str = "-----END ENVX KEY BACKUP-----";
+const CHUNK_PREFIX: &str = "ENVX1";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Backup {
+    pub version: u8,
+    pub fingerprint: String,
+    pub primary_user_id: String,
+    pub hashed_note: String,
+    pub uuid: Option<String>,
+    pub share: Option<ShareInfo>,
+    /// The binary secret key, or one share of it
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShareInfo {
+    /// Index of the share, starting at 1
+    pub index: u8,
+    pub shares: u8,
+    pub threshold: u8,
+}
+
+/// Chunks of one backup by number, with the total number of chunks
+type Chunks = BTreeMap<usize, (usize, Vec<u8>)>;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum BackupFormat {
+    /// Numbered lines of hex with a checksum per line, for printing
+    Paper,
+    /// One line per chunk, each small enough for a QR code
+    Chunks,
+}
+
+impl Backup {
+    pub fn new(
+        fingerprint: String,
+        primary_user_id: String,
+        hashed_note: String,
+        uuid: Option<String>,
+        data: Vec<u8>,
+    ) -> Self {
+        Self {
+            version: BACKUP_VERSION,
+            fingerprint,
+            primary_user_id,
+            hashed_note,
+            uuid,
+            share: None,
+            data,
+        }
+    }
+
+    /// Split the backup into `shares` shares, any `threshold` of which are
+    /// enough to restore the key
+    pub fn split(&self, shares: u8, threshold: u8) -> Result<Vec<Backup>> {
+        if threshold < 2 || threshold > shares {
+            bail!("The threshold must be between 2 and the number of shares");
+        }
+
+        let shares = Sharks(threshold)
+            .dealer_rng(&self.data, &mut OsRng)
+            .take(shares as usize)
+            .enumerate()
+            .map(|(i, share)| Backup {
+                share: Some(ShareInfo {
+                    index: i as u8 + 1,
+                    shares,
+                    threshold,
+                }),
+                data: Vec::from(&share),
+                ..self.clone()
+            })
+            .collect();
+
+        Ok(shares)
+    }
+
+    /// Combine shares back into the full backup
+    ///
+    /// Refuses backups of different keys, full backups mixed with shares,
+    /// and shares of different splits
+    pub fn combine(backups: &[Backup]) -> Result<Backup> {
+        let first = backups.first().context("No backups provided")?;
+        if backups.iter().any(|b| b.fingerprint != first.fingerprint) {
+            bail!("The backups belong to different keys");
+        }
+
+        let Some(info) = &first.share else {
+            if backups.iter().any(|b| b.share.is_some()) {
+                bail!(
+                    "Got both a full backup and shares, use one or the other"
+                );
+            }
+            return Ok(first.clone());
+        };
+
+        // the same share may be given twice, e.g. as paper and as chunks
+        let mut shares: BTreeMap<u8, &[u8]> = BTreeMap::new();
+        for backup in backups {
+            let Some(share) = &backup.share else {
+                bail!(
+                    "Got both a full backup and shares, use one or the other"
+                );
+            };
+            if share.threshold != info.threshold || share.shares != info.shares
+            {
+                bail!(
+                    "The shares come from different splits ({} of {} and {} of {})",
+                    info.threshold,
+                    info.shares,
+                    share.threshold,
+                    share.shares
+                );
+            }
+            match shares.insert(share.index, &backup.data) {
+                Some(data) if data != backup.data.as_slice() => bail!(
+                    "Got two different shares numbered {}, the shares come from different splits",
+                    share.index
+                ),
+                _ => {}
+            }
+        }
+
+        if shares.len() < info.threshold as usize {
+            bail!(
+                "{} shares are needed to restore this key, got {}",
+                info.threshold,
+                shares.len()
+            );
+        }
+
+        let parsed = shares
+            .values()
+            .map(|data| Share::try_from(*data).map_err(|e| anyhow!(e)))
+            .collect::<Result<Vec<Share>>>()?;
+
+        let data = Sharks(info.threshold)
+            .recover(&parsed)
+            .map_err(|e| anyhow!("Failed to combine shares: {}", e))?;
+
+        Ok(Backup {
+            share: None,
+            data,
+            ..first.clone()
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self).context("Failed to serialize backup")
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let backup = bincode::deserialize::<Backup>(bytes)
+            .context("Failed to deserialize backup")?;
+        if backup.version != BACKUP_VERSION {
+            bail!("Unsupported backup version {}", backup.version);
+        }
+        Ok(backup)
+    }
+
+    fn description(&self) -> String {
+        match &self.share {
+            Some(s) => format!(
+                "share {} of {} (any {} restore the key)",
+                s.index, s.shares, s.threshold
+            ),
+            None => "full key".into(),
+        }
+    }
+
+    /// Encode the backup as printable text
+    pub fn encode(
+        &self,
+        format: BackupFormat,
+        chunk_size: usize,
+    ) -> Result<String> {
+        let bytes = self.to_bytes()?;
+        match format {
+            BackupFormat::Paper => Ok(self.encode_paper(&bytes)),
+            BackupFormat::Chunks => self.encode_chunks(&bytes, chunk_size),
+        }
+    }
+
+    fn encode_paper(&self, bytes: &[u8]) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{}", BEGIN);
+        let _ = writeln!(out, "Fingerprint: {}", self.fingerprint);
+        let _ = writeln!(out, "User: {}", self.primary_user_id);
+        let _ = writeln!(out, "Contents: {}", self.description());
+        let _ = writeln!(out, "Length: {}", bytes.len());
+        let _ = writeln!(out);
+
+        for (i, line) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+            let number = i + 1;
+            let groups = line
+                .chunks(4)
+                .map(hex::encode_upper)
+                .collect::<Vec<String>>()
+                .join(" ");
+            let _ = writeln!(
+                out,
+                "{:03}: {} {}",
+                number,
+                groups,
+                line_checksum(number, line)
+            );
+        }
+
+        let _ = writeln!(out, "{}", END);
+        out
+    }
+
+    fn encode_chunks(&self, bytes: &[u8], chunk_size: usize) -> Result<String> {
+        if chunk_size == 0 {
+            bail!("Chunk size must be greater than 0");
+        }
+
+        let share = self.share.as_ref().map_or(0, |s| s.index);
+        let id = backup_id(bytes);
+        let chunks = bytes.chunks(chunk_size).collect::<Vec<&[u8]>>();
+
+        Ok(chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let number = i + 1;
+                format!(
+                    "{}:{}:{}:{}/{}:{}:{}",
+                    CHUNK_PREFIX,
+                    id,
+                    share,
+                    number,
+                    chunks.len(),
+                    hex::encode_upper(chunk),
+                    line_checksum(number, chunk)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n"))
+    }
+
+    /// Decode all backups (or shares) found in the text
+    ///
+    /// Both formats are accepted and may be mixed
+    pub fn decode_all(text: &str) -> Result<Vec<Backup>> {
+        let mut backups = vec![];
+
+        // paper backups, between BEGIN and END markers
+        let mut paper: Option<Vec<u8>> = None;
+        // chunk backups, grouped by backup id and share number
+        let mut chunks: BTreeMap<(String, u8), Chunks> = BTreeMap::new();
+
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let context =
+                || format!("Invalid backup on line {}", line_number + 1);
+
+            if line == BEGIN {
+                paper = Some(vec![]);
+            } else if line == END {
+                let bytes = paper.take().with_context(context)?;
+                backups.push(Self::from_bytes(&bytes).with_context(context)?);
+            } else if let Some(rest) = line.strip_prefix(CHUNK_PREFIX) {
+                let (id, share, number, total, data) =
+                    parse_chunk(rest).with_context(context)?;
+                let parts = chunks.entry((id, share)).or_default();
+                if parts.values().any(|(t, _)| *t != total)
+                    || parts.get(&number).is_some_and(|(_, d)| *d != data)
+                {
+                    bail!(
+                        "Chunk {}/{} doesn't match the other chunks of share {}, are chunks of different backups mixed?",
+                        number,
+                        total,
+                        share
+                    );
+                }
+                parts.insert(number, (total, data));
+            } else if let Some(bytes) = paper.as_mut() {
+                let Some((number, rest)) = line.split_once(':') else {
+                    continue;
+                };
+                let Ok(number) = number.parse::<usize>() else {
+                    continue;
+                };
+                bytes.extend(
+                    parse_paper_line(number, rest).with_context(context)?,
+                );
+            }
+        }
+
+        if paper.is_some() {
+            bail!("Backup is missing its end marker");
+        }
+
+        for ((id, share), parts) in chunks {
+            let total = parts.values().next().map_or(0, |(t, _)| *t);
+            if (1..=total).any(|n| !parts.contains_key(&n)) {
+                bail!(
+                    "Backup share {} is incomplete, got {} of {} chunks",
+                    share,
+                    parts.len(),
+                    total
+                );
+            }
+            let bytes = parts
+                .into_values()
+                .flat_map(|(_, data)| data)
+                .collect::<Vec<u8>>();
+            if backup_id(&bytes) != id {
+                bail!("Backup share {} doesn't match its checksum", share);
+            }
+            backups.push(Self::from_bytes(&bytes)?);
+        }
+
+        Ok(backups)
+    }
+}
+
+/// Short hash of an encoded backup, identifying it in its chunks
+fn backup_id(bytes: &[u8]) -> String {
+    hex::encode_upper(&digest(Algorithm::SHA256, bytes)[..4])
+}
+
+fn line_checksum(number: usize, data: &[u8]) -> String {
+    let mut input = number.to_be_bytes().to_vec();
+    input.extend_from_slice(data);
+    hex::encode_upper(&digest(Algorithm::SHA256, &input)[..3])
+}
+
+fn parse_paper_line(number: usize, rest: &str) -> Result<Vec<u8>> {
+    let mut groups = rest.split_whitespace().collect::<Vec<&str>>();
+    let checksum = groups.pop().context("Missing checksum")?;
+    let bytes = hex::decode(groups.concat()).context("Invalid hex")?;
+
+    if !line_checksum(number, &bytes).eq_ignore_ascii_case(checksum) {
+        bail!("Checksum mismatch on backup line {:03}", number);
+    }
+
+    Ok(bytes)
+}
+
+fn parse_chunk(rest: &str) -> Result<(String, u8, usize, usize, Vec<u8>)> {
+    let parts = rest.trim_start_matches(':').split(':').collect::<Vec<_>>();
+    let [id, share, position, data, checksum] = parts[..] else {
+        bail!("Malformed chunk");
+    };
+
+    let share = share.parse::<u8>().context("Invalid share number")?;
+    let (number, total) =
+        position.split_once('/').context("Malformed chunk")?;
+    let number = number.parse::<usize>().context("Invalid chunk number")?;
+    let total = total.parse::<usize>().context("Invalid chunk count")?;
+    let data = hex::decode(data).context("Invalid hex")?;
+
+    if !line_checksum(number, &data).eq_ignore_ascii_case(checksum) {
+        bail!("Checksum mismatch on chunk {}/{}", number, total);
+    }
+
+    Ok((id.to_uppercase(), share, number, total, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backup(fingerprint: &str) -> Backup {
+        Backup::new(
+            fingerprint.into(),
+            "test <test@example.com>".into(),
+            "".into(),
+            Some("uuid".into()),
+            (0..=255).collect(),
+        )
+    }
+
+    #[test]
+    fn paper_round_trip() {
+        let original = backup("AAAA");
+        let text = original.encode(BackupFormat::Paper, 0).unwrap();
+        let decoded = Backup::decode_all(&text).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].data, original.data);
+        assert_eq!(decoded[0].fingerprint, original.fingerprint);
+    }
+
+    #[test]
+    fn chunks_round_trip_in_any_order() {
+        let original = backup("AAAA");
+        let text = original.encode(BackupFormat::Chunks, 50).unwrap();
+        let reversed = text.lines().rev().collect::<Vec<_>>().join("\n");
+        let decoded = Backup::decode_all(&reversed).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].data, original.data);
+    }
+
+    #[test]
+    fn rejects_corrupted_lines() {
+        let text = backup("AAAA").encode(BackupFormat::Paper, 0).unwrap();
+        let corrupted = text.replacen("001: 0", "001: 1", 1);
+        assert!(Backup::decode_all(&corrupted).is_err());
+    }
+
+    #[test]
+    fn rejects_incomplete_chunks() {
+        let text = backup("AAAA").encode(BackupFormat::Chunks, 50).unwrap();
+        let missing = text.lines().skip(1).collect::<Vec<_>>().join("\n");
+        assert!(Backup::decode_all(&missing).is_err());
+    }
+
+    #[test]
+    fn rejects_chunks_of_different_backups() {
+        let a = backup("AAAA").encode(BackupFormat::Chunks, 50).unwrap();
+        let b = backup("BBBB").encode(BackupFormat::Chunks, 50).unwrap();
+        let mut mixed = a.lines().take(2).collect::<Vec<_>>();
+        mixed.extend(b.lines().skip(2));
+        assert!(Backup::decode_all(&mixed.join("\n")).is_err());
+    }
+
+    #[test]
+    fn combines_any_threshold_of_shares() {
+        let original = backup("AAAA");
+        let shares = original.split(5, 3).unwrap();
+
+        let restored = Backup::combine(&shares[1..4]).unwrap();
+        assert_eq!(restored.data, original.data);
+        assert!(restored.share.is_none());
+
+        let text = [&shares[0], &shares[2], &shares[4]]
+            .iter()
+            .map(|s| s.encode(BackupFormat::Chunks, 40).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let decoded = Backup::decode_all(&text).unwrap();
+        assert_eq!(Backup::combine(&decoded).unwrap().data, original.data);
+    }
+
+    #[test]
+    fn rejects_too_few_shares() {
+        let shares = backup("AAAA").split(5, 3).unwrap();
+        assert!(Backup::combine(&shares[..2]).is_err());
+        // the same share twice only counts once
+        let twice = [shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert!(Backup::combine(&twice).is_err());
+    }
+
+    #[test]
+    fn rejects_mixed_inputs() {
+        let full = backup("AAAA");
+        let shares = full.split(3, 2).unwrap();
+        let other_split = full.split(4, 2).unwrap();
+        let other_key = backup("BBBB").split(3, 2).unwrap();
+
+        assert!(Backup::combine(&[full.clone(), shares[0].clone()]).is_err());
+        assert!(Backup::combine(&[shares[0].clone(), full.clone()]).is_err());
+        assert!(
+            Backup::combine(&[shares[0].clone(), other_split[1].clone()])
+                .is_err()
+        );
+        assert!(Backup::combine(&[shares[0].clone(), other_key[1].clone()])
+            .is_err());
+        assert!(Backup::combine(&[full, backup("BBBB")]).is_err());
+    }
+}
@@ -7,12 +7,6 @@ pub trait ToBTreeMap {
     fn to_btreemap(&self) -> Result<BTreeMap<String, String>>;
 }
 
-pub trait FromBTreeMap {
-    fn from_btreemap(map: &BTreeMap<String, String>) -> Result<Self>
-    where
-        Self: Sized;
-}
-
 impl ToBTreeMap for Config {
     fn to_btreemap(&self) -> Result<BTreeMap<String, String>> {
         // Convert Config to JSON value
@@ -43,18 +37,6 @@ impl ToBTreeMap for Settings {
     }
 }
 
-impl FromBTreeMap for Settings {
-    fn from_btreemap(map: &BTreeMap<String, String>) -> Result<Self> {
-        // Convert BTreeMap to JSON value
-        let v: Value = serde_json::to_value(map)
-            .context("Failed to convert BTreeMap to JSON value")?;
-
-        // Convert JSON value into Settings
-        serde_json::from_value(v)
-            .context("Failed to convert JSON value to Settings")
-    }
-}
-
 impl ToBTreeMap for Vec<Key> {
     fn to_btreemap(&self) -> Result<BTreeMap<String, String>> {
         let mut map = BTreeMap::new();
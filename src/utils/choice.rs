@@ -4,8 +4,11 @@ use crate::sdk::SDK;
 
 use super::{
     config::{get_config, Config},
+    env::{self, ENVX_PROJECT},
     key::Key,
+    project_file::{selected_environment, ProjectFile},
 };
+use crate::interact_or;
 
 pub struct Choice {}
 impl Choice {
@@ -16,6 +19,10 @@ impl Choice {
     }
 
     pub async fn choose_project(partial_fingerprint: &str) -> Result<String> {
+        interact_or!(
+            "No project given, pass its id, set {} or link one with `envx link`",
+            ENVX_PROJECT
+        );
         let (key, config) = Self::get_key(partial_fingerprint)?;
         let all_projects = SDK::list_projects(&key.fingerprint).await?;
 
@@ -51,21 +58,47 @@ impl Choice {
         Ok(selected)
     }
 
+    /// Resolve the project to use
+    ///
+    /// In order: the given project, `ENVX_PROJECT`, an environment selected
+    /// with `--env` in `.envx.toml`, the project linked in the config, the
+    /// project in `.envx.toml`, and finally a prompt
     pub async fn try_project(
         project_id: Option<String>,
         partial_fingerprint: &str,
     ) -> Result<String> {
         match project_id {
             Some(p) => Ok(p),
-            None => {
-                let config = get_config().context("Failed to get config")?;
-                let project = config.get_project();
+            None => match Self::configured_project()? {
+                Some(project_id) => Ok(project_id),
+                None => Self::choose_project(partial_fingerprint).await,
+            },
+        }
+    }
 
-                match project {
-                    Ok(p) => Ok(p.project_id.clone()),
-                    Err(_) => Self::choose_project(partial_fingerprint).await,
+    /// The project `try_project` uses when none is given, without asking
+    pub fn configured_project() -> Result<Option<String>> {
+        if let Some(project_id) = env::var(ENVX_PROJECT) {
+            return Ok(Some(project_id));
+        }
+
+        let project_file = ProjectFile::find()?;
+        if let Some(file) = &project_file {
+            if selected_environment().is_some() {
+                if let Some(project_id) = file.project_id()? {
+                    return Ok(Some(project_id));
                 }
             }
         }
+
+        let config = get_config().context("Failed to get config")?;
+        if let Ok(project) = config.get_project() {
+            return Ok(Some(project.project_id.clone()));
+        }
+
+        match &project_file {
+            Some(file) => file.project_id(),
+            None => Ok(None),
+        }
     }
 }
@@ -0,0 +1,149 @@
+// Copying to the clipboard
+//
+// Under Wayland and X11 the text is handed to wl-copy, xclip or xsel, which
+// keep serving it after envx exits. Everywhere else, and over SSH, the
+// terminal is asked to set the clipboard with an OSC 52 escape sequence,
+// which most terminals support but some leave disabled.
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Wayland,
+    Xclip,
+    Xsel,
+    Osc52,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Wayland => write!(f, "wl-copy"),
+            Backend::Xclip => write!(f, "xclip"),
+            Backend::Xsel => write!(f, "xsel"),
+            Backend::Osc52 => write!(f, "the terminal"),
+        }
+    }
+}
+
+/// The first backend usable in this session
+pub fn backend() -> Backend {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() && on_path("wl-copy") {
+        return Backend::Wayland;
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        if on_path("xclip") {
+            return Backend::Xclip;
+        }
+        if on_path("xsel") {
+            return Backend::Xsel;
+        }
+    }
+    Backend::Osc52
+}
+
+pub fn copy(backend: Backend, text: &str) -> Result<()> {
+    match backend {
+        Backend::Wayland => pipe_to("wl-copy", &[], text),
+        Backend::Xclip => pipe_to("xclip", &["-selection", "clipboard"], text),
+        Backend::Xsel => pipe_to("xsel", &["--clipboard", "--input"], text),
+        Backend::Osc52 => osc52(text),
+    }
+}
+
+/// What the clipboard holds, `None` where it can't be read back
+pub fn paste(backend: Backend) -> Option<String> {
+    let (program, args): (&str, &[&str]) = match backend {
+        Backend::Wayland => ("wl-paste", &["--no-newline"]),
+        Backend::Xclip => ("xclip", &["-selection", "clipboard", "-o"]),
+        Backend::Xsel => ("xsel", &["--clipboard", "--output"]),
+        Backend::Osc52 => return None,
+    };
+    let output = Command::new(program)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub fn clear(backend: Backend) -> Result<()> {
+    match backend {
+        Backend::Wayland => {
+            run(Command::new("wl-copy").arg("--clear").stdin(Stdio::null()))
+        }
+        Backend::Xsel => {
+            run(Command::new("xsel").args(["--clipboard", "--clear"]))
+        }
+        // xclip has no way to clear, so the clipboard is emptied instead
+        Backend::Xclip | Backend::Osc52 => copy(backend, ""),
+    }
+}
+
+fn pipe_to(program: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to start {}", program))?;
+    child
+        .stdin
+        .take()
+        .context("Failed to open stdin")?
+        .write_all(text.as_bytes())
+        .with_context(|| format!("Failed to write to {}", program))?;
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{} failed with {}", program, status);
+    }
+    Ok(())
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to clear the clipboard")?;
+    if !status.success() {
+        bail!("Failed to clear the clipboard: {}", status);
+    }
+    Ok(())
+}
+
+/// Ask the terminal to set the clipboard. The sequence goes to the
+/// terminal itself so it works with stdout redirected.
+fn osc52(text: &str) -> Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let sequence = format!("\x1b]52;c;{}\x07", encoded);
+
+    #[cfg(unix)]
+    if let Ok(mut tty) =
+        std::fs::OpenOptions::new().write(true).open("/dev/tty")
+    {
+        return tty
+            .write_all(sequence.as_bytes())
+            .context("Failed to write to the terminal");
+    }
+
+    let mut stderr = std::io::stderr();
+    stderr
+        .write_all(sequence.as_bytes())
+        .and_then(|_| stderr.flush())
+        .context("Failed to write to the terminal")
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|path| {
+        std::env::split_paths(&path).any(|dir| dir.join(program).is_file())
+    })
+}
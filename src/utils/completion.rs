@@ -0,0 +1,70 @@
+// Names offered by shell completion that only the server knows
+//
+// Project ids and variable names are remembered whenever they are fetched,
+// so completing never waits on the network or asks for a passphrase. Only
+// names and ids are kept, never values. The cache is best effort: failing
+// to read or write it never fails a command.
+
+use super::atomic::{create_private_dir, write_atomic_private};
+use super::dirs;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+
+const FILE: &str = "completion.json";
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Cache {
+    /// Projects the server listed for any of our keys
+    #[serde(default)]
+    pub projects: BTreeSet<String>,
+    /// project id -> (variable id -> variable name)
+    #[serde(default)]
+    pub variables: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Cache {
+    /// The cache, empty if it doesn't exist or can't be read
+    pub fn load() -> Self {
+        path()
+            .and_then(|path| Ok(fs::read(path)?))
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    fn write(&self) -> Result<()> {
+        let dir = dirs::data_dir()?;
+        create_private_dir(&dir)?;
+        write_atomic_private(&dir.join(FILE), &serde_json::to_vec(self)?)
+    }
+}
+
+fn path() -> Result<PathBuf> {
+    Ok(dirs::data_dir()?.join(FILE))
+}
+
+/// Remember the projects the server listed
+pub fn record_projects(ids: &[String]) {
+    let mut cache = Cache::load();
+    cache.projects.extend(ids.iter().cloned());
+    let _ = cache.write();
+}
+
+/// Remember the variables of a project as `(id, name)`, replacing what
+/// was known about it
+pub fn record_variables<'a>(
+    project_id: &str,
+    variables: impl Iterator<Item = (&'a str, &'a str)>,
+) {
+    let mut cache = Cache::load();
+    cache.variables.insert(
+        project_id.to_string(),
+        variables
+            .map(|(id, name)| (id.to_string(), name.to_string()))
+            .collect(),
+    );
+    let _ = cache.write();
+}
@@ -1,19 +1,60 @@
-// configuration path = ~/.config/envcli/config.json
+// configuration path = ~/.config/envx/config.json, see `utils::dirs`
 
+use super::atomic::{create_private_dir, write_atomic_private, FileLock};
+use super::dirs;
+use super::env::{self, ENVX_KEY, ENVX_USER_ID};
+use super::http::ConnectionSettings;
 use super::key::Key;
-use super::rpgp::get_vault_location;
+use super::migrations::{self, CONFIG_VERSION};
 use super::settings::Settings;
+use super::vault;
 use anyhow::anyhow;
 use anyhow::{Context, Result};
 use colored::Colorize;
-use home::home_dir;
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::io::{BufWriter, Write};
+use std::collections::BTreeMap;
+use std::fs;
+use std::mem;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Once, OnceLock};
+
+pub use envx::config::DEFAULT_CONTEXT;
+pub const DEFAULT_SDK_URL: &str = envx::DEFAULT_URL;
+
+/// Context selected with `--context`, takes precedence over `ENVX_CONTEXT`
+static CONTEXT_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// Use this context for the rest of the process
+pub fn set_context_override(name: String) {
+    let _ = CONTEXT_OVERRIDE.set(name);
+}
+
+/// Set by `envx doctor` without `--fix`, which must not change anything
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Never write the config for the rest of the process: a missing config
+/// isn't created, legacy files aren't moved and migrations stay in memory
+pub fn set_read_only() {
+    READ_ONLY.store(true, Ordering::Relaxed);
+}
+
+/// Set for commands that must work with a broken context selection
+static DEFAULT_CONTEXT_FALLBACK: AtomicBool = AtomicBool::new(false);
+static UNKNOWN_CONTEXT_WARNING: Once = Once::new();
+
+/// Use the default context, with a warning, instead of failing when the
+/// selected context doesn't exist, so `envx context use` and `envx doctor`
+/// can still repair a stale `current_context` or `ENVX_CONTEXT`
+pub fn fall_back_to_default_context() {
+    DEFAULT_CONTEXT_FALLBACK.store(true, Ordering::Relaxed);
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
+    /// Version of the config layout, see `utils::migrations`
+    #[serde(default)]
+    pub version: u32,
     /// TODO: rethink Salting hashes
     pub salt: String,
     /// The fingerprint of the primary signing key
@@ -30,6 +71,42 @@ pub struct Config {
     pub projects: Vec<Project>,
     /// Password for the primary key
     pub primary_key_password: Option<String>,
+    /// TLS and proxy settings for the SDK
+    #[serde(default)]
+    pub connection: ConnectionSettings,
+    /// Named server contexts, the top level fields are the default context
+    #[serde(default)]
+    pub contexts: BTreeMap<String, ServerContext>,
+    /// The context used unless `--context` or `ENVX_CONTEXT` is given
+    #[serde(default)]
+    pub current_context: Option<String>,
+    /// The named context whose fields are swapped into the top level fields
+    #[serde(skip)]
+    active_context: Option<String>,
+    /// Values set from environment variables, removed again before writing
+    #[serde(skip)]
+    env_overrides: EnvOverrides,
+}
+
+#[derive(Debug, Clone, Default)]
+struct EnvOverrides {
+    /// (configured, overridden) primary key
+    primary_key: Option<(String, String)>,
+    /// Fingerprint of the key added from `ENVX_PRIVATE_KEY`
+    ephemeral_key: Option<String>,
+}
+
+/// A server profile with its own URL, primary key, project links and
+/// connection settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ServerContext {
+    pub sdk_url: Option<String>,
+    #[serde(default)]
+    pub primary_key: String,
+    #[serde(default)]
+    pub projects: Vec<Project>,
+    #[serde(default)]
+    pub connection: ConnectionSettings,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -43,54 +120,173 @@ impl Default for Config {
         let salt = rand::random::<[u8; 32]>();
         let salt = hex::encode(salt);
         Self {
+            version: CONFIG_VERSION,
             salt,
             primary_key: "".into(),
             keys: vec![],
             online: true,
-            sdk_url: Some("https://api.env-cli.com".into()),
+            sdk_url: Some(DEFAULT_SDK_URL.into()),
             settings: None,
             projects: vec![],
             primary_key_password: None,
+            connection: ConnectionSettings::default(),
+            contexts: BTreeMap::new(),
+            current_context: None,
+            active_context: None,
+            env_overrides: EnvOverrides::default(),
         }
     }
 }
 
 impl Config {
-    /// Vulnerable to fs race conditions
-    /// should rewrite using file locks
-    pub fn write(&self) -> Result<()> {
+    /// Write the config while the caller holds the `ConfigLock`
+    fn write_locked(&self) -> Result<()> {
+        if READ_ONLY.load(Ordering::Relaxed) {
+            return Err(anyhow!("The config can't be changed by this command"));
+        }
         let path = get_config_path().context("Failed to get config path")?;
-        let file =
-            File::create(path).context("Failed to create config file")?;
-        let mut writer = BufWriter::new(file);
-        let contents = serde_json::to_string_pretty(self)
+        let mut config = Config {
+            version: CONFIG_VERSION,
+            ..self.clone()
+        };
+        config.remove_env_overrides();
+        config.leave_context()?;
+        let contents = serde_json::to_string_pretty(&config)
             .context("Failed to serialize config to JSON string")?;
 
-        writer
-            .write_all(contents.as_bytes())
-            .context("Failed to write config to file")?;
+        write_atomic_private(&path, contents.as_bytes())
+            .context("Failed to write config to file")
+    }
+
+    /// Apply `ENVX_PRIVATE_KEY` and `ENVX_KEY`
+    fn apply_env_overrides(&mut self) -> Result<()> {
+        let mut primary_key = None;
+
+        if let Some((seckey, fingerprint)) = env::private_key()? {
+            if !self
+                .keys
+                .iter()
+                .any(|k| k.fingerprint.eq_ignore_ascii_case(&fingerprint))
+            {
+                let primary_user_id = seckey
+                    .details
+                    .users
+                    .first()
+                    .map(|u| u.id.id().to_string())
+                    .unwrap_or_default();
+
+                self.keys.push(Key {
+                    fingerprint: fingerprint.clone(),
+                    note: "from ENVX_PRIVATE_KEY".into(),
+                    primary_user_id,
+                    hashed_note: "".into(),
+                    pubkey_only: None,
+                    uuid: env::var(ENVX_USER_ID),
+                    revoked: None,
+                });
+                self.env_overrides.ephemeral_key = Some(fingerprint.clone());
+            }
+            primary_key = Some(fingerprint);
+        }
+
+        if let Some(key) = env::var(ENVX_KEY) {
+            let key = self
+                .get_key_or_default(Some(key))
+                .with_context(|| format!("Invalid {}", ENVX_KEY))?;
+            primary_key = Some(key.fingerprint);
+        }
+
+        if let Some(primary_key) = primary_key {
+            let configured = mem::replace(&mut self.primary_key, primary_key);
+            self.env_overrides.primary_key =
+                Some((configured, self.primary_key.clone()));
+        }
 
         Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn primary_key(&self) -> Result<String> {
-        let primary_key = &self.primary_key;
-        let primary_key_location = get_vault_location()?
-            .join(primary_key.clone())
-            .join("public.key");
+    /// Undo `apply_env_overrides`, keeping changes made since
+    fn remove_env_overrides(&mut self) {
+        let overrides = mem::take(&mut self.env_overrides);
+
+        if let Some(fingerprint) = overrides.ephemeral_key {
+            self.keys.retain(|k| k.fingerprint != fingerprint);
+        }
+        if let Some((configured, overridden)) = overrides.primary_key {
+            if self.primary_key == overridden {
+                self.primary_key = configured;
+            }
+        }
+    }
+
+    /// The name of the context in use
+    pub fn context_name(&self) -> &str {
+        self.active_context.as_deref().unwrap_or(DEFAULT_CONTEXT)
+    }
+
+    /// All contexts by name, including the default one
+    pub fn all_contexts(&self) -> Result<BTreeMap<String, ServerContext>> {
+        let mut config = self.clone();
+        config.leave_context()?;
 
-        let primary_public_key = fs::read_to_string(primary_key_location)
-            .context("Failed to read primary public key")?;
+        let mut contexts = config.contexts;
+        contexts.insert(
+            DEFAULT_CONTEXT.into(),
+            ServerContext {
+                sdk_url: config.sdk_url,
+                primary_key: config.primary_key,
+                projects: config.projects,
+                connection: config.connection,
+            },
+        );
+        Ok(contexts)
+    }
+
+    /// Swap the fields of a named context with the top level fields
+    fn swap_context(&mut self, name: &str) -> Result<()> {
+        let context = self.contexts.get_mut(name).with_context(|| {
+            format!(
+                "Unknown context '{}', see `envx context ls` or switch back with `envx context use default`",
+                name
+            )
+        })?;
+
+        mem::swap(&mut self.sdk_url, &mut context.sdk_url);
+        mem::swap(&mut self.primary_key, &mut context.primary_key);
+        mem::swap(&mut self.projects, &mut context.projects);
+        mem::swap(&mut self.connection, &mut context.connection);
+        Ok(())
+    }
+
+    /// Make a context the one used by the rest of envx
+    fn enter_context(&mut self, name: &str) -> Result<()> {
+        self.leave_context()?;
+        if name != DEFAULT_CONTEXT {
+            self.swap_context(name)?;
+            self.active_context = Some(name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Put the fields of the active context back where they belong
+    fn leave_context(&mut self) -> Result<()> {
+        if let Some(name) = self.active_context.take() {
+            self.swap_context(&name)?;
+        }
+        Ok(())
+    }
 
-        Ok(primary_public_key)
+    #[allow(dead_code)]
+    pub fn primary_key(&self) -> Result<String> {
+        vault::read(&self.primary_key, vault::PUBLIC_KEY)
+            .context("Failed to read primary public key")
     }
 
     /// Set the primary key
     ///
     /// - Returns an error if the key doesn't exist
     ///
-    /// Does not write to disk. Call `config.write()` to write to disk
+    /// Does not write to disk, call it from `update_config`
     pub fn set_primary_key(&mut self, fingerprint: &str) -> Result<()> {
         let key = self.get_key(fingerprint)?;
         self.primary_key = key.fingerprint.clone();
@@ -227,27 +423,109 @@ impl Config {
     }
 }
 
-/// Get the configuration path ~/.config/envcli/config.json
+/// An exclusive advisory lock on the config, released when dropped
+pub struct ConfigLock {
+    _lock: FileLock,
+}
+
+impl ConfigLock {
+    /// Block until no other envx process holds the lock
+    pub fn acquire() -> Result<Self> {
+        let path = get_config_path().context("Failed to get config path")?;
+        let lock =
+            FileLock::acquire(&path).context("Failed to lock config file")?;
+
+        Ok(Self { _lock: lock })
+    }
+}
+
+/// Get the configuration path ~/.config/envx/config.json
 pub fn get_config_path() -> Result<PathBuf> {
-    let mut path = home_dir().context("Failed to get home directory")?;
-    path.push(".config/envcli/config.json");
+    if READ_ONLY.load(Ordering::Relaxed) {
+        return Ok(dirs::config_dir()?.join("config.json"));
+    }
+    dirs::migrate_legacy_dir()?;
+    let path = dirs::config_dir()?.join("config.json");
     // if it doesn't exist, create it
     if !path.exists() {
         let default = serde_json::to_string_pretty(&Config::default())?;
         let parent_path =
             path.parent().context("Failed to get parent directory")?;
-        fs::create_dir_all(parent_path)?;
-        let mut file = File::create(&path)?;
-        file.write_all(default.as_ref())?;
+        create_private_dir(parent_path)?;
+        write_atomic_private(&path, default.as_bytes())?;
     }
     Ok(path)
 }
 
-/// Read the configuration file and parse it into a Config struct
-pub fn get_config() -> Result<Config> {
+/// Read and parse the config file, migrating it in memory if needed
+///
+/// Returns the config and whether it was migrated
+fn read_config() -> Result<(Config, bool)> {
     let path = get_config_path().context("Failed to get config path")?;
     let contents =
         fs::read_to_string(path).context("Failed to read config file")?;
-    serde_json::from_str::<Config>(&contents)
-        .context("Failed to parse config file")
+    let mut raw = serde_json::from_str::<serde_json::Value>(&contents)
+        .context("Failed to parse config file")?;
+
+    let migrated = migrations::migrate(&mut raw)?;
+    let mut config = serde_json::from_value::<Config>(raw)
+        .context("Failed to parse config file")?;
+
+    let mut context = envx::config::select_context(
+        CONTEXT_OVERRIDE.get().cloned(),
+        config.current_context.clone(),
+    );
+    if context != DEFAULT_CONTEXT
+        && !config.contexts.contains_key(&context)
+        && DEFAULT_CONTEXT_FALLBACK.load(Ordering::Relaxed)
+    {
+        UNKNOWN_CONTEXT_WARNING.call_once(|| {
+            eprintln!(
+                "{} unknown context '{}', using the default context",
+                "Warning:".yellow(),
+                context
+            );
+        });
+        context = DEFAULT_CONTEXT.into();
+    }
+    config.enter_context(&context)?;
+    config.apply_env_overrides()?;
+
+    Ok((config, migrated))
+}
+
+/// The salt of the config, without migrating the config on disk
+///
+/// Safe to call while the config lock is held.
+pub fn salt() -> Result<String> {
+    Ok(read_config()?.0.salt)
+}
+
+/// Read the configuration file and parse it into a Config struct
+///
+/// Configs written by older versions of envx are upgraded on disk
+pub fn get_config() -> Result<Config> {
+    let (config, migrated) = read_config()?;
+    if !migrated || READ_ONLY.load(Ordering::Relaxed) {
+        return Ok(config);
+    }
+
+    // another process may have migrated it in the meantime
+    update_config(|_| Ok(()))
+}
+
+/// Read, modify and write the config while holding the config lock
+///
+/// The only way to change the config, so envx processes running at the
+/// same time never drop each other's changes. Read values needed before
+/// the change with `get_config` and make the change itself in `f`.
+pub fn update_config<F>(f: F) -> Result<Config>
+where
+    F: FnOnce(&mut Config) -> Result<()>,
+{
+    let _lock = ConfigLock::acquire()?;
+    let (mut config, _) = read_config()?;
+    f(&mut config)?;
+    config.write_locked()?;
+    Ok(config)
 }
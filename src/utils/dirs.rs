@@ -0,0 +1,231 @@
+// Where envx keeps its files
+//
+// By default the XDG base directories are used:
+//
+//   config.json, known_keys.json  $XDG_CONFIG_HOME/envx  (~/.config/envx)
+//   keys/                         $XDG_DATA_HOME/envx    (~/.local/share/envx)
+//   sessions                      $XDG_RUNTIME_DIR/envx  (or the temp dir)
+//
+// `--config-dir` or `ENVX_HOME` (`ENVX_CONFIG_DIR` also works) put the
+// config and the keys in a single directory instead.
+//
+// Files in the legacy location (~/.config/envcli) are moved over the
+// first time envx runs.
+
+use super::atomic::{create_private_dir, FileLock};
+use super::env;
+use super::vault;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+const APP_DIR: &str = "envx";
+const LEGACY_APP_DIR: &str = "envcli";
+
+/// Directory given with `--config-dir`
+static HOME_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keep all files in this directory for the rest of the process
+pub fn set_home_override(path: PathBuf) {
+    let _ = HOME_OVERRIDE.set(path);
+}
+
+/// The single directory given with `--config-dir` or `ENVX_HOME`
+pub fn home_override() -> Option<PathBuf> {
+    HOME_OVERRIDE
+        .get()
+        .cloned()
+        .or_else(envx::config::home_from_env)
+}
+
+fn home() -> Result<PathBuf> {
+    home::home_dir().context("Failed to get home directory")
+}
+
+/// An absolute path from an XDG variable, ignoring relative ones as the
+/// spec requires
+fn xdg_dir(var: &str) -> Option<PathBuf> {
+    env::var(var).map(PathBuf::from).filter(|p| p.is_absolute())
+}
+
+/// The directory holding config.json
+pub fn config_dir() -> Result<PathBuf> {
+    Ok(envx::config::config_dir(home_override())?)
+}
+
+/// The directory holding the key vault
+pub fn data_dir() -> Result<PathBuf> {
+    Ok(envx::config::data_dir(home_override())?)
+}
+
+/// The directory for short lived files such as session markers and the
+/// agent socket
+///
+/// Falls back to a directory of the current user in the temp dir.
+pub fn runtime_dir() -> PathBuf {
+    match xdg_dir("XDG_RUNTIME_DIR") {
+        Some(dir) => dir.join(APP_DIR),
+        None => std::env::temp_dir().join(format!("{}-{}", APP_DIR, user_id())),
+    }
+}
+
+/// Create the runtime dir, making sure nobody else owns or can read it
+pub fn private_runtime_dir() -> Result<PathBuf> {
+    let dir = runtime_dir();
+    create_private_dir(&dir)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let owner = fs::metadata(&dir)?.uid();
+        if owner != user_id() {
+            anyhow::bail!(
+                "{} is owned by another user, refusing to use it",
+                dir.display()
+            );
+        }
+    }
+
+    Ok(dir)
+}
+
+/// The id of the current user, 0 where there is no such thing
+pub fn user_id() -> u32 {
+    #[cfg(unix)]
+    {
+        // SAFETY: getuid has no preconditions and can't fail
+        unsafe { libc::getuid() }
+    }
+    #[cfg(not(unix))]
+    0
+}
+
+/// Whether files can be created in `dir`, or in the directory it would be
+/// created in if it doesn't exist yet, without writing anything
+pub fn is_writable(dir: &Path) -> bool {
+    let Some(dir) = dir.ancestors().find(|d| d.exists()) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes())
+        else {
+            return false;
+        };
+        // SAFETY: path is a valid NUL-terminated string
+        unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+    }
+    #[cfg(not(unix))]
+    fs::metadata(dir).is_ok_and(|m| !m.permissions().readonly())
+}
+
+/// ~/.config/envcli, where envx kept everything before
+fn legacy_dir() -> Result<PathBuf> {
+    Ok(home()?.join(".config").join(LEGACY_APP_DIR))
+}
+
+/// Move the config and keys from the legacy location, once
+///
+/// Does nothing when a config already exists in the new location or the
+/// location was overridden.
+pub fn migrate_legacy_dir() -> Result<()> {
+    if home_override().is_some() {
+        return Ok(());
+    }
+
+    let legacy = legacy_dir()?;
+    let config_dir = config_dir()?;
+    if !legacy.join("config.json").is_file()
+        || config_dir.join("config.json").exists()
+    {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&config_dir)
+        .context("Failed to create config directory")?;
+    // the config lock, so concurrent first runs move the files only once
+    let _lock = FileLock::acquire(&config_dir.join("config.json"))
+        .context("Failed to lock config file")?;
+    if !legacy.join("config.json").is_file()
+        || config_dir.join("config.json").exists()
+    {
+        return Ok(());
+    }
+
+    let data_dir = data_dir()?;
+    eprintln!(
+        "Moving envx files from {} to {} and {}",
+        legacy.display(),
+        config_dir.display(),
+        data_dir.display()
+    );
+
+    fs::create_dir_all(&data_dir).context("Failed to create data directory")?;
+
+    if legacy.join("keys").is_dir() {
+        move_path(&legacy.join("keys"), &data_dir.join("keys"))?;
+        // the keys were written before the vault had checksums, the ones
+        // not recorded here are recorded when they are first read
+        if let Some(salt) = legacy_salt(&legacy) {
+            vault::record_missing_checksums(&salt)?;
+        }
+    }
+    if legacy.join("known_keys.json").is_file() {
+        move_path(
+            &legacy.join("known_keys.json"),
+            &config_dir.join("known_keys.json"),
+        )?;
+    }
+    // moved last, so an interrupted migration is retried
+    move_path(&legacy.join("config.json"), &config_dir.join("config.json"))?;
+
+    // only succeeds if nothing else is left in it
+    let _ = fs::remove_dir(&legacy);
+
+    Ok(())
+}
+
+/// The salt of the config in the legacy location
+fn legacy_salt(legacy: &Path) -> Option<String> {
+    let contents = fs::read_to_string(legacy.join("config.json")).ok()?;
+    let config: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    config.get("salt")?.as_str().map(String::from)
+}
+
+/// Rename a file or directory, copying it when renaming across file systems
+/// isn't possible
+///
+/// A missing source counts as already moved.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if !from.exists() || fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    if from.is_file() {
+        fs::copy(from, to)
+            .with_context(|| format!("Failed to copy {}", from.display()))?;
+        fs::remove_file(from)?;
+        return Ok(());
+    }
+
+    for entry in WalkDir::new(from) {
+        let entry = entry.context("Failed to read legacy directory")?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::copy(entry.path(), &target).with_context(|| {
+                format!("Failed to copy {}", entry.path().display())
+            })?;
+        }
+    }
+
+    fs::remove_dir_all(from)?;
+
+    Ok(())
+}
@@ -0,0 +1,23 @@
+// Environment variables that override the config, for headless and CI use
+//
+// The variables themselves are listed in `envx::env`, which the library
+// reads as well. Plugins get most of them set for them, and ENVX_TOKEN,
+// see `utils::plugin`.
+
+use super::vecu8::ToHex;
+use anyhow::{Context, Result};
+pub use envx::env::*;
+use pgp::{types::KeyTrait, Deserializable, SignedSecretKey};
+
+/// The secret key given in `ENVX_PRIVATE_KEY` and its fingerprint
+pub fn private_key() -> Result<Option<(SignedSecretKey, String)>> {
+    let Some(armored) = var(ENVX_PRIVATE_KEY) else {
+        return Ok(None);
+    };
+
+    let (seckey, _) = SignedSecretKey::from_string(&armored)
+        .with_context(|| format!("Failed to parse {}", ENVX_PRIVATE_KEY))?;
+    let fingerprint = seckey.fingerprint().to_hex();
+
+    Ok(Some((seckey, fingerprint)))
+}
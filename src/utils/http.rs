@@ -0,0 +1,56 @@
+// Shared HTTP client for talking to envx servers
+//
+// The connection and retry settings themselves live in `envx::connection`,
+// so the library connects the same way.
+
+use super::config::get_config;
+use anyhow::{Context, Result};
+use colored::Colorize;
+pub use envx::ConnectionSettings;
+use reqwest::{Client, RequestBuilder, Response};
+use std::sync::Once;
+
+static INSECURE_WARNING: Once = Once::new();
+
+/// Warn once per process if the context doesn't verify the server's
+/// certificate
+pub fn warn_if_insecure(connection: &ConnectionSettings) {
+    if connection.insecure_skip_verify {
+        INSECURE_WARNING.call_once(|| {
+            eprintln!(
+                "{}",
+                "WARNING: TLS certificate verification is disabled for this context"
+                    .yellow()
+            );
+        });
+    }
+}
+
+/// An HTTP client configured for the server of the current context
+pub fn http_client() -> Result<Client> {
+    let config = get_config()?;
+    warn_if_insecure(&config.connection);
+    config
+        .connection
+        .apply(Client::builder())?
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+/// Send a request, retrying as configured with `retry_attempts` and
+/// `retry_backoff`
+///
+/// Requests that never reached the server are always retried, others only
+/// if they are idempotent and failed with a timeout or a temporary error.
+pub(crate) trait SendWithRetry {
+    async fn send_with_retry(self) -> reqwest::Result<Response>;
+}
+
+impl SendWithRetry for RequestBuilder {
+    async fn send_with_retry(self) -> reqwest::Result<Response> {
+        let settings = get_config()
+            .and_then(|c| c.get_settings())
+            .unwrap_or_default();
+        settings.retry_policy().send(self).await
+    }
+}
@@ -1,7 +1,11 @@
-use super::rpgp::get_vault_location;
+use super::env;
+use super::rpgp::public_key_from_secret;
+use super::vault;
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use pgp::{Deserializable, SignedPublicKey, SignedSecretKey};
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, fs};
+use std::fmt::Display;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Key {
@@ -11,29 +15,54 @@ pub struct Key {
     pub hashed_note: String,
     pub pubkey_only: Option<bool>,
     pub uuid: Option<String>,
+    /// Set once the key has been revoked with `envx key revoke`
+    pub revoked: Option<bool>,
 }
 
 impl Key {
+    /// The secret key from `ENVX_PRIVATE_KEY` if it is this key
+    fn env_secret_key(&self) -> Result<Option<SignedSecretKey>> {
+        Ok(env::private_key()?
+            .filter(|(_, fp)| fp.eq_ignore_ascii_case(&self.fingerprint))
+            .map(|(seckey, _)| seckey))
+    }
+
     pub fn public_key(&self) -> Result<String> {
-        let key_location = get_vault_location()?
-            .join(self.fingerprint.clone())
-            .join("public.key");
+        if let Some(seckey) = self.env_secret_key()? {
+            return public_key_from_secret(&seckey)
+                .to_armored_string(None)
+                .context("Failed to convert public key to armored string");
+        }
 
-        let key = fs::read_to_string(key_location)
-            .context("Failed to read public key")?;
+        vault::read(&self.fingerprint, vault::PUBLIC_KEY)
+            .context("Failed to read public key")
+    }
 
-        Ok(key)
+    /// The expiration date of the key, `None` if it never expires
+    pub fn expires_at(&self) -> Result<Option<DateTime<Utc>>> {
+        let (key, _) = SignedPublicKey::from_string(&self.public_key()?)
+            .context("Failed to parse public key")?;
+        Ok(key.expires_at())
     }
 
-    pub fn secret_key(&self) -> Result<String> {
-        let key_location = get_vault_location()?
-            .join(self.fingerprint.clone())
-            .join("private.key");
+    pub fn is_revoked(&self) -> bool {
+        self.revoked.unwrap_or(false)
+    }
+
+    pub fn revocation_certificate(&self) -> Result<String> {
+        vault::read(&self.fingerprint, vault::REVOCATION_CERTIFICATE)
+            .context("Failed to read revocation certificate")
+    }
 
-        let key = fs::read_to_string(key_location)
-            .context("Failed to read secret key")?;
+    pub fn secret_key(&self) -> Result<String> {
+        if let Some(seckey) = self.env_secret_key()? {
+            return seckey
+                .to_armored_string(None)
+                .context("Failed to convert secret key to armored string");
+        }
 
-        Ok(key)
+        vault::read(&self.fingerprint, vault::PRIVATE_KEY)
+            .context("Failed to read secret key")
     }
 }
 
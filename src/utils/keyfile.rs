@@ -0,0 +1,372 @@
+// Encrypted file keyring, for machines without Secret Service or a kernel
+// keyring, such as servers and containers
+//
+// Passphrases are kept in keyring.enc next to the key vault, each sealed
+// with ChaCha20-Poly1305. The key is derived with Argon2id from a master
+// passphrase and the machine id, so a copied file is useless elsewhere.
+//
+// The master passphrase is asked for once per boot: the derived key is
+// cached in the runtime dir together with the boot id and ignored after
+// a reboot. ENVX_KEYRING_PASSPHRASE skips the prompt.
+
+use super::atomic::{create_private_dir, write_atomic_private, FileLock};
+use super::dirs;
+use super::env::{self, ENVX_KEYRING_PASSPHRASE};
+use super::prompt::prompt_password;
+use crate::interact_or;
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use keyring::credential::{
+    Credential, CredentialApi, CredentialBuilderApi, CredentialPersistence,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+const FILE: &str = "keyring.enc";
+const KEY_CACHE: &str = "keyring.key";
+const VERSION: u32 = 1;
+/// Sealed in every file to tell a wrong master passphrase apart
+const CHECK: &[u8] = b"envx keyring";
+
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    /// Argon2 salt, hex
+    salt: String,
+    check: Sealed,
+    entries: BTreeMap<String, Sealed>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+pub fn path() -> Result<PathBuf> {
+    Ok(dirs::data_dir()?.join(FILE))
+}
+
+/// Builds credentials stored in the encrypted file
+#[derive(Debug)]
+pub struct FileCredentialBuilder;
+
+impl CredentialBuilderApi for FileCredentialBuilder {
+    fn build(
+        &self,
+        target: Option<&str>,
+        service: &str,
+        user: &str,
+    ) -> keyring::Result<Box<Credential>> {
+        let name = match target {
+            Some(target) => target.to_string(),
+            None => format!("{}@{}", user, service),
+        };
+        Ok(Box::new(FileCredential { name }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn persistence(&self) -> CredentialPersistence {
+        CredentialPersistence::UntilDelete
+    }
+}
+
+#[derive(Debug)]
+pub struct FileCredential {
+    name: String,
+}
+
+impl CredentialApi for FileCredential {
+    fn set_password(&self, password: &str) -> keyring::Result<()> {
+        with_lock(|| {
+            let (mut file, key) = match read()? {
+                Some(file) => {
+                    let key = unlock(&file)?;
+                    (file, key)
+                }
+                None => create()?,
+            };
+            let sealed = seal(&key, &self.name, password.as_bytes())?;
+            file.entries.insert(self.name.clone(), sealed);
+            write(&file)
+        })
+        .map_err(failure)
+    }
+
+    fn get_password(&self) -> keyring::Result<String> {
+        let Some(file) = read().map_err(failure)? else {
+            return Err(keyring::Error::NoEntry);
+        };
+        let Some(sealed) = file.entries.get(&self.name) else {
+            return Err(keyring::Error::NoEntry);
+        };
+        let key = unlock(&file).map_err(failure)?;
+        let password = open(&key, &self.name, sealed)
+            .context("Failed to decrypt the keyring entry")
+            .map_err(failure)?;
+        String::from_utf8(password)
+            .map_err(|e| keyring::Error::BadEncoding(e.into_bytes()))
+    }
+
+    /// Deleting doesn't need the master passphrase
+    fn delete_password(&self) -> keyring::Result<()> {
+        with_lock(|| {
+            let Some(mut file) = read()? else {
+                return Ok(false);
+            };
+            let removed = file.entries.remove(&self.name).is_some();
+            if removed {
+                write(&file)?;
+            }
+            Ok(removed)
+        })
+        .map_err(failure)?
+        .then_some(())
+        .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn failure(e: anyhow::Error) -> keyring::Error {
+    keyring::Error::PlatformFailure(e.into())
+}
+
+/// Run `f` while holding the lock of the keyring file
+fn with_lock<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    create_private_dir(&dirs::data_dir()?)?;
+    let _lock = FileLock::acquire(&path()?)
+        .context("Failed to lock the keyring file")?;
+    f()
+}
+
+fn read() -> Result<Option<KeyFile>> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let file: KeyFile = serde_json::from_slice(&contents)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    if file.version != VERSION {
+        bail!("{} was written by a newer version of envx", path.display());
+    }
+    Ok(Some(file))
+}
+
+fn write(file: &KeyFile) -> Result<()> {
+    let path = path()?;
+    write_atomic_private(&path, &serde_json::to_vec_pretty(file)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Start a new keyring file, asking for a new master passphrase
+fn create() -> Result<(KeyFile, [u8; 32])> {
+    let passphrase = match env::var(ENVX_KEYRING_PASSPHRASE) {
+        Some(passphrase) => passphrase,
+        None => {
+            interact_or!(
+                "No keyring passphrase given, set {}",
+                ENVX_KEYRING_PASSPHRASE
+            );
+            eprintln!(
+                "No system keyring is available, passphrases of unlocked keys will be kept in {}",
+                path()?.display()
+            );
+            let passphrase =
+                prompt_password("Choose a passphrase for the keyring: ")?;
+            if passphrase != prompt_password("Repeat the passphrase: ")? {
+                bail!("Passphrases do not match");
+            }
+            passphrase
+        }
+    };
+
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let key = derive_key(&passphrase, &salt)?;
+
+    let file = KeyFile {
+        version: VERSION,
+        salt: hex::encode(salt),
+        check: seal(&key, "", CHECK)?,
+        entries: BTreeMap::new(),
+    };
+    cache_key(&key);
+    Ok((file, key))
+}
+
+/// The key of a keyring file, from the cache or the master passphrase
+fn unlock(file: &KeyFile) -> Result<[u8; 32]> {
+    if let Some(key) = cached_key(file) {
+        return Ok(key);
+    }
+
+    let salt = hex::decode(&file.salt).context("Invalid keyring salt")?;
+    let passphrase = match env::var(ENVX_KEYRING_PASSPHRASE) {
+        Some(passphrase) => passphrase,
+        None => {
+            interact_or!(
+                "The keyring file is locked, set {}",
+                ENVX_KEYRING_PASSPHRASE
+            );
+            prompt_password("Keyring passphrase: ")?
+        }
+    };
+    let key = derive_key(&passphrase, &salt)?;
+    if !is_key_of(file, &key) {
+        bail!("Wrong keyring passphrase");
+    }
+    cache_key(&key);
+    Ok(key)
+}
+
+fn is_key_of(file: &KeyFile, key: &[u8; 32]) -> bool {
+    matches!(open(key, "", &file.check), Ok(check) if check == CHECK)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let machine_id = machine_id();
+    let argon2 = Argon2::new_with_secret(
+        &machine_id,
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        argon2::Params::default(),
+    )
+    .map_err(|e| anyhow!("Failed to set up Argon2: {}", e))?;
+
+    let mut key = [0u8; 32];
+    argon2
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive the keyring key: {}", e))?;
+    Ok(key)
+}
+
+/// Entries are bound to their name, so they can't be swapped around
+fn seal(key: &[u8; 32], name: &str, plaintext: &[u8]) -> Result<Sealed> {
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: name.as_bytes(),
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt the keyring entry"))?;
+    Ok(Sealed {
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    })
+}
+
+fn open(key: &[u8; 32], name: &str, sealed: &Sealed) -> Result<Vec<u8>> {
+    let nonce = hex::decode(&sealed.nonce)?;
+    if nonce.len() != 12 {
+        bail!("Invalid nonce");
+    }
+    let ciphertext = hex::decode(&sealed.ciphertext)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: name.as_bytes(),
+            },
+        )
+        .map_err(|_| anyhow!("Failed to decrypt"))
+}
+
+/// Ties the derived key to this machine, empty where there is no id
+fn machine_id() -> Vec<u8> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().as_bytes().to_vec())
+        .unwrap_or_default()
+}
+
+/// Changes on every boot, `None` where the kernel doesn't say
+fn boot_id() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .ok()
+        .map(|id| id.trim().to_string())
+}
+
+fn key_cache_path() -> Result<PathBuf> {
+    Ok(dirs::private_runtime_dir()?.join(KEY_CACHE))
+}
+
+/// The cached key, if it's from this boot and opens `file`
+fn cached_key(file: &KeyFile) -> Option<[u8; 32]> {
+    let path = key_cache_path().ok()?;
+    let contents = fs::read_to_string(&path).ok()?;
+    let (boot, key) = contents.trim().split_once(':')?;
+    if boot_id().unwrap_or_default() != boot {
+        let _ = fs::remove_file(&path);
+        return None;
+    }
+    let key: [u8; 32] = hex::decode(key).ok()?.try_into().ok()?;
+    is_key_of(file, &key).then_some(key)
+}
+
+/// Remember the key until the next boot, failing silently as the
+/// passphrase is simply asked for again
+fn cache_key(key: &[u8; 32]) {
+    let Ok(path) = key_cache_path() else {
+        return;
+    };
+    let contents =
+        format!("{}:{}", boot_id().unwrap_or_default(), hex::encode(key));
+    let _ = write_atomic_private(&path, contents.as_bytes());
+}
+
+/// Check that the keyring file can be used, without writing or prompting
+///
+/// The data dir must be writable and the file, if there is one, must parse
+/// and open with the cached key or ENVX_KEYRING_PASSPHRASE when either is
+/// available.
+pub fn check() -> Result<()> {
+    let dir = dirs::data_dir()?;
+    if !dirs::is_writable(&dir) {
+        bail!("{} is not writable", dir.display());
+    }
+
+    let Some(file) = read()? else {
+        return Ok(());
+    };
+    if cached_key(&file).is_some() {
+        return Ok(());
+    }
+    if let Some(passphrase) = env::var(ENVX_KEYRING_PASSPHRASE) {
+        let salt = hex::decode(&file.salt).context("Invalid keyring salt")?;
+        if !is_key_of(&file, &derive_key(&passphrase, &salt)?) {
+            bail!("Wrong keyring passphrase in {}", ENVX_KEYRING_PASSPHRASE);
+        }
+    }
+    Ok(())
+}
+
+/// Forget the cached key, so the master passphrase is asked for again
+pub fn forget_key() -> Result<()> {
+    let path = key_cache_path()?;
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
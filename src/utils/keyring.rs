@@ -1,39 +1,189 @@
 use super::{
+    atomic::write_atomic_private,
     config::{get_config, Config},
+    dirs,
+    env::{self, ENVX_PASSPHRASE},
+    keyfile::{self, FileCredentialBuilder},
     prompt::prompt_password,
+    settings::KeyringBackend,
 };
+use crate::interact_or;
 use crate::{
     constants::MINIMUM_PASSWORD_LENGTH, utils::prompt::prompt_confirm,
 };
 use anyhow::bail;
+use crypto_hash::{hex_digest, Algorithm};
 use keyring::{Entry as Keyring, Result as KeyringResult};
+use serde::{Deserialize, Serialize};
 use std::{
     fs,
-    io::Write,
     path::PathBuf,
+    sync::OnceLock,
     time::{Duration, SystemTime},
 };
 
 const SERVICE: &str = "envx";
 
-fn get_session_path(fingerprint: &str) -> PathBuf {
-    std::env::temp_dir().join(format!("envx-{}", fingerprint))
+/// Entry looked up to see whether a backend responds
+const PROBE: &str = "envx-keyring-probe";
+
+static BACKEND: OnceLock<KeyringBackend> = OnceLock::new();
+
+/// The backend passphrases are stored in, picked once per process
+///
+/// With the `keyring_backend` setting on `auto` this is the system keyring
+/// if it responds, then the kernel keyring on Linux and the encrypted file
+/// as a last resort, so headless machines work without any setup.
+pub fn backend() -> KeyringBackend {
+    *BACKEND.get_or_init(|| {
+        let configured = get_config()
+            .and_then(|c| c.get_settings())
+            .map(|s| s.keyring_backend)
+            .unwrap_or_default();
+        let backend = match configured {
+            KeyringBackend::Auto => detect_backend(),
+            KeyringBackend::Keyutils if !cfg!(target_os = "linux") => {
+                KeyringBackend::File
+            }
+            backend => backend,
+        };
+
+        match backend {
+            #[cfg(target_os = "linux")]
+            KeyringBackend::Keyutils => {
+                keyring::set_default_credential_builder(
+                    keyring::keyutils::default_credential_builder(),
+                )
+            }
+            KeyringBackend::File => keyring::set_default_credential_builder(
+                Box::new(FileCredentialBuilder),
+            ),
+            _ => {}
+        }
+        backend
+    })
+}
+
+fn detect_backend() -> KeyringBackend {
+    let responds = |entry: KeyringResult<Keyring>| {
+        matches!(
+            entry.and_then(|e| e.get_password()),
+            Ok(_) | Err(keyring::Error::NoEntry)
+        )
+    };
+
+    if responds(Keyring::new(SERVICE, PROBE)) {
+        return KeyringBackend::System;
+    }
+    #[cfg(target_os = "linux")]
+    if responds(
+        keyring::keyutils::KeyutilsCredential::new_with_target(
+            None, SERVICE, PROBE,
+        )
+        .map(|c| Keyring::new_with_credential(Box::new(c))),
+    ) {
+        return KeyringBackend::Keyutils;
+    }
+    KeyringBackend::File
+}
+
+/// The keyring entry of a key in the selected backend
+fn entry(fingerprint: &str) -> KeyringResult<Keyring> {
+    backend();
+    Keyring::new(SERVICE, fingerprint)
+}
+
+/// Marks a key as unlocked until `expires`
+///
+/// The marker is bound to the key and the user with a checksum keyed with
+/// the salt of the config, which only the user can read, so a marker
+/// written by anyone else is ignored.
+#[derive(Serialize, Deserialize)]
+struct SessionMarker {
+    fingerprint: String,
+    uid: u32,
+    expires: SystemTime,
+    checksum: String,
+}
+
+impl SessionMarker {
+    fn new(fingerprint: &str, expires: SystemTime) -> anyhow::Result<Self> {
+        let mut marker = Self {
+            fingerprint: fingerprint.to_string(),
+            uid: dirs::user_id(),
+            expires,
+            checksum: String::new(),
+        };
+        marker.checksum = marker.compute_checksum()?;
+        Ok(marker)
+    }
+
+    fn compute_checksum(&self) -> anyhow::Result<String> {
+        let salt = get_config()?.salt;
+        let expires = self
+            .expires
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        let input =
+            format!("{}:{}:{}:{}", salt, self.fingerprint, self.uid, expires);
+        Ok(hex_digest(Algorithm::SHA256, input.as_bytes()))
+    }
+
+    /// Read the marker of a key, `None` if there is none or it is forged
+    fn read(fingerprint: &str) -> Option<Self> {
+        let path = get_session_path(fingerprint).ok()?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if fs::metadata(&path).ok()?.uid() != dirs::user_id() {
+                return None;
+            }
+        }
+
+        let marker: Self = bincode::deserialize(&fs::read(path).ok()?).ok()?;
+        let valid = marker.fingerprint == fingerprint
+            && marker.uid == dirs::user_id()
+            && marker.compute_checksum().ok()? == marker.checksum;
+        valid.then_some(marker)
+    }
 }
 
+fn get_session_path(fingerprint: &str) -> anyhow::Result<PathBuf> {
+    Ok(dirs::private_runtime_dir()?.join(format!("envx-{}", fingerprint)))
+}
+
+/// Store the passphrase of a key for the `session_ttl` setting
 pub fn set_password(fingerprint: &str, password: &str) -> KeyringResult<()> {
-    let keyring = Keyring::new(SERVICE, fingerprint)?;
+    let ttl = get_config()
+        .and_then(|c| c.get_settings())
+        .and_then(|s| s.session_ttl())
+        .unwrap_or(Duration::from_secs(60 * 60 * 24 * 30));
+    set_password_for(fingerprint, password, ttl)
+}
 
-    let expiration = SystemTime::now() + Duration::from_secs(60 * 60 * 24 * 30);
-    let exp_bytes = bincode::serialize(&expiration).unwrap();
-    fs::File::create(get_session_path(fingerprint))
-        .unwrap()
-        .write_all(&exp_bytes)
-        .unwrap();
+/// Store the passphrase of a key for `ttl`
+pub fn set_password_for(
+    fingerprint: &str,
+    password: &str,
+    ttl: Duration,
+) -> KeyringResult<()> {
+    let keyring = entry(fingerprint)?;
+    keyring.set_password(password)?;
 
-    keyring.set_password(password)
+    SessionMarker::new(fingerprint, SystemTime::now() + ttl)
+        .and_then(|marker| Ok(bincode::serialize(&marker)?))
+        .and_then(|bytes| {
+            write_atomic_private(&get_session_path(fingerprint)?, &bytes)
+        })
+        .map_err(|e| keyring::Error::PlatformFailure(e.into()))
 }
 
 pub fn get_password(fingerprint: &str) -> anyhow::Result<String> {
+    if let Some(password) = env::var(ENVX_PASSPHRASE) {
+        return Ok(password);
+    }
+
     let config = get_config()?;
 
     if fingerprint == config.primary_key {
@@ -42,29 +192,60 @@ pub fn get_password(fingerprint: &str) -> anyhow::Result<String> {
         }
     }
 
-    let expiry = fs::read(get_session_path(fingerprint));
-    let expiry = match expiry {
-        Ok(e) => e,
-        Err(_) => {
-            clear_password(fingerprint)?;
-            bail!("No session found");
-        }
+    let Some(marker) = SessionMarker::read(fingerprint) else {
+        let _ = clear_password(fingerprint);
+        bail!("No session found");
     };
 
-    let expiry: SystemTime = bincode::deserialize(&expiry)?;
-
-    if expiry < SystemTime::now() {
-        clear_password(fingerprint)?;
+    if marker.expires < SystemTime::now() {
+        let _ = clear_password(fingerprint);
         bail!("Session expired");
     }
 
-    let keyring = Keyring::new(SERVICE, fingerprint)?;
+    let keyring = entry(fingerprint)?;
     let password = keyring.get_password()?;
     Ok(password)
 }
 
+/// When the session of a key expires, `None` if there is no session
+pub fn session_expiry(fingerprint: &str) -> Option<SystemTime> {
+    SessionMarker::read(fingerprint).map(|marker| marker.expires)
+}
+
+/// Remove the session file and the stored password of a key
+pub fn clear_session(fingerprint: &str) -> anyhow::Result<()> {
+    let path = get_session_path(fingerprint)?;
+    let had_session = path.exists();
+    if had_session {
+        fs::remove_file(path)?;
+    }
+    match clear_password(fingerprint) {
+        Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+        // without a session nothing can have been stored
+        Err(_) if !had_session => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Check that the keyring backend can store and read a password
+///
+/// The encrypted file is only read, see [`keyfile::check`].
+pub fn check_keyring() -> KeyringResult<KeyringBackend> {
+    let backend = backend();
+    if backend == KeyringBackend::File {
+        return keyfile::check()
+            .map(|_| backend)
+            .map_err(|e| keyring::Error::PlatformFailure(e.into()));
+    }
+    let keyring = entry("envx-keyring-check")?;
+    keyring.set_password("check")?;
+    let password = keyring.get_password();
+    keyring.delete_password()?;
+    password.map(|_| backend)
+}
+
 pub fn clear_password(fingerprint: &str) -> KeyringResult<()> {
-    let keyring = Keyring::new(SERVICE, fingerprint)?;
+    let keyring = entry(fingerprint)?;
     keyring.delete_password()
 }
 
@@ -79,9 +260,15 @@ pub fn try_get_password(
         Err(e) => {
             let settings = config.get_settings()?;
 
+            interact_or!(
+                "Key {} is locked ({}), set {} or run `envx unlock` first",
+                fingerprint,
+                e,
+                ENVX_PASSPHRASE
+            );
             eprintln!("Failed to get password: {}", e);
             let key = config.get_key(fingerprint)?;
-            println!("Enter password for key {}", key);
+            eprintln!("Enter password for key {}", key);
             let password = prompt_password("Password: ")?;
             if settings.warn_on_short_passwords
                 && password.len() < MINIMUM_PASSWORD_LENGTH
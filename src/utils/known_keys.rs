@@ -0,0 +1,115 @@
+// known keys path = ~/.config/envx/known_keys.json
+//
+// The pins themselves and how keys are checked against them live in
+// `envx::known_keys`, this reads and writes the file for the CLI and turns
+// untrusted keys into instructions for `envx project trust`.
+
+use super::atomic::{write_atomic, FileLock};
+use super::config::{get_config_path, Config};
+use crate::types::User;
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use envx::known_keys::KeyStatus;
+pub use envx::known_keys::{format_fingerprint, KnownKeys, MemberKey};
+use pgp::SignedPublicKey;
+use std::path::PathBuf;
+
+/// Get the known keys path ~/.config/envx/known_keys.json
+pub fn get_known_keys_path() -> Result<PathBuf> {
+    let config_path = get_config_path()?;
+    let parent = config_path
+        .parent()
+        .context("Failed to get parent directory")?;
+    Ok(parent.join("known_keys.json"))
+}
+
+pub fn load() -> Result<KnownKeys> {
+    Ok(KnownKeys::read(&get_known_keys_path()?)?)
+}
+
+/// Load, modify and write the known keys while holding their lock, so
+/// concurrent `project trust` or `project add-user` runs don't lose pins
+pub fn update<F>(f: F) -> Result<KnownKeys>
+where
+    F: FnOnce(&mut KnownKeys) -> Result<()>,
+{
+    let path = get_known_keys_path()?;
+    let _lock = FileLock::acquire(&path)?;
+    let mut known_keys = load()?;
+    f(&mut known_keys)?;
+
+    let contents = serde_json::to_string_pretty(&known_keys)
+        .context("Failed to serialize known keys")?;
+    write_atomic(&path, contents.as_bytes())
+        .context("Failed to write known keys file")?;
+    Ok(known_keys)
+}
+
+/// Fingerprints of the keys in the config, trusted without being pinned
+pub fn own_keys(config: &Config) -> Vec<String> {
+    config.keys.iter().map(|k| k.fingerprint.clone()).collect()
+}
+
+/// Compare the keys returned by the server against the pinned ones
+pub fn check(
+    project_id: &str,
+    users: &[User],
+    config: &Config,
+) -> Result<Vec<MemberKey>> {
+    Ok(load()?.check(project_id, users, &own_keys(config))?)
+}
+
+/// Get the public keys of all project members, refusing to continue if any
+/// of them is new or has changed since it was last trusted
+pub fn trusted_public_keys(
+    project_id: &str,
+    users: &[User],
+    config: &Config,
+) -> Result<Vec<SignedPublicKey>> {
+    let members = check(project_id, users, config)?;
+    envx::known_keys::trusted_public_keys(project_id, members)
+        .map_err(untrusted)
+}
+
+/// A member with its fingerprint and the status of its key, coloured
+pub fn describe(member: &MemberKey) -> String {
+    let status = match &member.status {
+        KeyStatus::Pinned | KeyStatus::Own => {
+            member.status.to_string().green().to_string()
+        }
+        KeyStatus::New => member.status.to_string().yellow().to_string(),
+        KeyStatus::Changed { pinned } => {
+            format!("{} (was {})", "CHANGED".red().bold(), pinned)
+        }
+    };
+    format!(
+        "{} - {}\n    {} [{}]",
+        member.user.username,
+        member.user.id,
+        format_fingerprint(&member.fingerprint),
+        status
+    )
+}
+
+/// Explain how to trust the keys of an `envx::Error::Untrusted`
+pub fn untrusted(error: envx::Error) -> anyhow::Error {
+    let envx::Error::Untrusted {
+        project_id,
+        members,
+    } = error
+    else {
+        return error.into();
+    };
+
+    let list = members
+        .iter()
+        .map(|m| format!("  {}", describe(m)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    anyhow!(
+        "{}\n{}\n\nVerify these fingerprints with their owners, then run `envx project trust -p {}`",
+        "Refusing to encrypt to untrusted project member keys:".red(),
+        list,
+        project_id
+    )
+}
@@ -1,4 +1,3 @@
-use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
@@ -13,14 +12,6 @@ impl KVPair {
     pub fn new(key: String, value: String) -> Self {
         Self { key, value }
     }
-
-    pub fn from_json(json: &str) -> Result<Self> {
-        serde_json::from_str::<KVPair>(json).context("Failed to parse KVPair")
-    }
-
-    pub fn to_json(&self) -> Result<String> {
-        serde_json::to_string(&self).context("Failed to serialize KVPair")
-    }
 }
 
 impl fmt::Display for KVPair {
@@ -0,0 +1,164 @@
+// Migrations for the config file
+//
+// Each migration upgrades the raw JSON of a config from one version to the
+// next, `MIGRATIONS[n]` turns version `n` into version `n + 1`. Configs
+// written before versioning was added have no `version` field and are
+// treated as version 0.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+const MIGRATIONS: &[Migration] = &[v0_to_v1, v1_to_v2];
+
+/// The version written by this build of envx
+pub const CONFIG_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// The version of a raw config, 0 if it has none
+pub fn version_of(config: &Value) -> u32 {
+    config
+        .get("version")
+        .and_then(Value::as_u64)
+        .map_or(0, |v| v as u32)
+}
+
+/// Upgrade a raw config to `CONFIG_VERSION`
+///
+/// Returns whether anything was changed
+pub fn migrate(config: &mut Value) -> Result<bool> {
+    let version = version_of(config);
+    if version > CONFIG_VERSION {
+        bail!(
+            "Your config was written by a newer version of envx (config version {}, this build supports {}), please upgrade envx",
+            version,
+            CONFIG_VERSION
+        );
+    }
+
+    let object = config
+        .as_object_mut()
+        .context("Config file is not a JSON object")?;
+
+    for (from, migration) in
+        MIGRATIONS.iter().enumerate().skip(version as usize)
+    {
+        migration(object).with_context(|| {
+            format!("Failed to migrate config from version {}", from)
+        })?;
+        object.insert("version".into(), json!(from + 1));
+    }
+
+    Ok(version < CONFIG_VERSION)
+}
+
+/// Fill in fields that older configs might be missing
+fn v0_to_v1(config: &mut Map<String, Value>) -> Result<()> {
+    config.entry("projects").or_insert_with(|| json!([]));
+    config.entry("settings").or_insert(Value::Null);
+    config.entry("primary_key_password").or_insert(Value::Null);
+
+    if let Some(keys) = config.get_mut("keys").and_then(Value::as_array_mut) {
+        for key in keys.iter_mut().filter_map(Value::as_object_mut) {
+            key.entry("note").or_insert_with(|| json!(""));
+            key.entry("hashed_note").or_insert_with(|| json!(""));
+        }
+    }
+
+    Ok(())
+}
+
+/// Add server contexts and the connection settings of the default context
+fn v1_to_v2(config: &mut Map<String, Value>) -> Result<()> {
+    config.entry("connection").or_insert_with(|| json!({}));
+    config.entry("contexts").or_insert_with(|| json!({}));
+    config.entry("current_context").or_insert(Value::Null);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config as written before versioning was added
+    fn unversioned() -> Value {
+        json!({
+            "salt": "00",
+            "primary_key": "",
+            "keys": [{ "fingerprint": "AAAA", "primary_user_id": "a" }],
+            "online": true,
+            "sdk_url": null
+        })
+    }
+
+    #[test]
+    fn migrates_unversioned_configs_to_the_latest_version() {
+        let mut config = unversioned();
+        assert!(migrate(&mut config).unwrap());
+
+        assert_eq!(version_of(&config), CONFIG_VERSION);
+        assert_eq!(config["projects"], json!([]));
+        assert_eq!(config["keys"][0]["note"], json!(""));
+        assert_eq!(config["keys"][0]["hashed_note"], json!(""));
+        assert_eq!(config["contexts"], json!({}));
+        assert_eq!(config["connection"], json!({}));
+        assert_eq!(config["current_context"], Value::Null);
+    }
+
+    #[test]
+    fn migrated_configs_parse() {
+        let mut config = unversioned();
+        migrate(&mut config).unwrap();
+        let config =
+            serde_json::from_value::<crate::utils::config::Config>(config)
+                .unwrap();
+        assert_eq!(config.keys[0].fingerprint, "AAAA");
+        assert!(config.contexts.is_empty());
+    }
+
+    #[test]
+    fn migrates_from_each_version() {
+        for version in 0..CONFIG_VERSION {
+            let mut config = unversioned();
+            config["version"] = json!(version);
+            assert!(migrate(&mut config).unwrap());
+            assert_eq!(version_of(&config), CONFIG_VERSION);
+        }
+    }
+
+    #[test]
+    fn keeps_existing_fields() {
+        let mut config = unversioned();
+        config["projects"] = json!([{ "project_id": "p", "path": "/" }]);
+        config["keys"][0]["note"] = json!("laptop");
+        config["connection"] = json!({ "proxy": "socks5://localhost" });
+        migrate(&mut config).unwrap();
+
+        assert_eq!(config["projects"][0]["project_id"], json!("p"));
+        assert_eq!(config["keys"][0]["note"], json!("laptop"));
+        assert_eq!(config["connection"]["proxy"], json!("socks5://localhost"));
+    }
+
+    #[test]
+    fn leaves_current_configs_alone() {
+        let mut config = unversioned();
+        migrate(&mut config).unwrap();
+        let migrated = config.clone();
+
+        assert!(!migrate(&mut config).unwrap());
+        assert_eq!(config, migrated);
+    }
+
+    #[test]
+    fn refuses_newer_configs() {
+        let mut config = unversioned();
+        config["version"] = json!(CONFIG_VERSION + 1);
+        let error = migrate(&mut config).unwrap_err().to_string();
+        assert!(error.contains("newer version of envx"));
+    }
+
+    #[test]
+    fn refuses_non_objects() {
+        assert!(migrate(&mut json!([])).is_err());
+    }
+}
@@ -1,13 +1,31 @@
+pub mod agent;
+pub mod atomic;
 pub mod auth;
+pub mod backup;
 pub mod btreemap;
 pub mod choice;
+pub mod clipboard;
+pub mod completion;
 pub mod config;
+pub mod dirs;
+pub use envx::duration;
+pub mod env;
+pub mod http;
 pub mod key;
+pub mod keyfile;
 pub mod keyring;
+pub mod known_keys;
 pub mod kvpair;
+pub mod migrations;
+pub mod output;
 pub mod partial_variable;
+pub mod plugin;
+pub mod project_file;
 pub mod prompt;
 pub mod rpgp;
+pub mod schema;
 pub mod settings;
+pub mod signing;
 pub mod table;
+pub mod vault;
 pub mod vecu8;
@@ -0,0 +1,174 @@
+// How commands print their results
+//
+// Data goes through `print`, which serializes it as is for `--output json`
+// and `--output yaml` and leaves `table` and `plain` to the command. Those
+// schemas are meant for scripts, so fields are only ever added to them.
+// Plain output is one record per line, and what pipes get unless a table
+// is asked for.
+//
+// Status messages go through `info!`, which `--silent` turns off and which
+// writes to stderr in the machine readable formats so stdout stays
+// parseable.
+
+use super::config::get_config;
+use super::settings::OutputFormat;
+use anyhow::Result;
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Set by `--output` or a command's own `--json`
+static FORMAT: Mutex<Option<OutputFormat>> = Mutex::new(None);
+
+static SILENT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_format(format: OutputFormat) {
+    if let Ok(mut current) = FORMAT.lock() {
+        *current = Some(format);
+    }
+}
+
+/// Switch to JSON for commands that had `--json` before `--output` existed
+pub fn json_if(json: bool) {
+    if json {
+        set_format(OutputFormat::Json);
+    }
+}
+
+/// The format given with `--output`, or the `output_format` setting. Tables
+/// are only drawn on a terminal unless asked for with `--output table`, pipes
+/// get plain text.
+pub fn format() -> OutputFormat {
+    if let Some(format) = FORMAT.lock().ok().and_then(|f| *f) {
+        return format;
+    }
+    let format = get_config()
+        .and_then(|c| c.get_settings())
+        .map(|s| s.output_format)
+        .unwrap_or_default();
+    if format == OutputFormat::Table && !std::io::stdout().is_terminal() {
+        return OutputFormat::Plain;
+    }
+    format
+}
+
+/// Whether the output is for programs rather than people
+pub fn is_machine_readable() -> bool {
+    matches!(format(), OutputFormat::Json | OutputFormat::Yaml)
+}
+
+pub fn set_silent() {
+    SILENT.store(true, Ordering::Relaxed);
+}
+
+pub fn is_silent() -> bool {
+    SILENT.load(Ordering::Relaxed)
+}
+
+/// Print data in the selected format, `human` renders it as a table or as
+/// plain text
+pub fn print<T: Serialize + ?Sized>(
+    value: &T,
+    human: impl FnOnce(OutputFormat) -> Result<()>,
+) -> Result<()> {
+    match format() {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(value)?)
+        }
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        format => human(format)?,
+    }
+    Ok(())
+}
+
+/// Whether secret values are hidden on screen, as set with the `redact`
+/// setting and undone with `--reveal`
+pub fn redact(reveal: bool) -> bool {
+    !reveal
+        && get_config()
+            .and_then(|c| c.get_settings())
+            .map_or(true, |s| s.redact)
+}
+
+/// Values shorter than this are masked completely
+const MASK_TAIL_MIN_LENGTH: usize = 12;
+
+/// Hide a secret value, showing only its length and the last 4 characters
+/// so values can still be told apart
+///
+/// Values shorter than 12 characters don't show their last 4 characters,
+/// those would give away too much of a short secret such as a PIN.
+pub fn mask(value: &str) -> String {
+    let length = value.chars().count();
+    let tail = if length >= MASK_TAIL_MIN_LENGTH {
+        value.chars().skip(length - 4).collect()
+    } else {
+        String::new()
+    };
+    format!("••••••••{} ({} characters)", tail, length)
+}
+
+/// Print a status message, see `info!`
+pub fn info(message: &str) {
+    if is_silent() {
+        return;
+    }
+    if is_machine_readable() {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorOutput {
+    error: ErrorDetails,
+}
+
+#[derive(Serialize)]
+struct ErrorDetails {
+    message: String,
+    /// The errors that led to this one, outermost first
+    causes: Vec<String>,
+}
+
+/// Print an error to stderr, as an object in the machine readable formats
+pub fn print_error(error: &anyhow::Error) {
+    if !is_machine_readable() {
+        eprintln!("{:?}", error);
+        return;
+    }
+
+    let output = ErrorOutput {
+        error: ErrorDetails {
+            message: error.to_string(),
+            causes: error.chain().skip(1).map(|e| e.to_string()).collect(),
+        },
+    };
+    let serialized = match format() {
+        OutputFormat::Yaml => serde_yaml::to_string(&output).ok(),
+        _ => serde_json::to_string_pretty(&output).ok(),
+    };
+    match serialized {
+        Some(serialized) => eprintln!("{}", serialized.trim_end()),
+        None => eprintln!("{:?}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_short_values_completely() {
+        assert_eq!(mask("1234"), "•••••••• (4 characters)");
+        assert_eq!(mask("hunter2hunt"), "•••••••• (11 characters)");
+    }
+
+    #[test]
+    fn shows_the_tail_of_long_values() {
+        assert_eq!(mask("sk_live_abcdef"), "••••••••cdef (14 characters)");
+        assert_eq!(mask("ääääääääääöü"), "••••••••ääöü (12 characters)");
+    }
+}
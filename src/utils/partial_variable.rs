@@ -1,54 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 
-use super::kvpair::KVPair;
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct PartialVariable {
-    pub id: String,
-    pub value: String,
-    pub project_id: String,
-    pub created_at: String,
-}
-
-impl PartialVariable {
-    pub fn to_parsed(&self) -> ParsedPartialVariable {
-        ParsedPartialVariable {
-            id: self.id.clone(),
-            value: KVPair::from_json(&self.value).unwrap(),
-            project_id: self.project_id.clone(),
-            created_at: self.created_at.clone(),
-        }
-    }
-
-    pub fn zip_to_parsed(&self, kvpair: KVPair) -> ParsedPartialVariable {
-        ParsedPartialVariable {
-            id: self.id.clone(),
-            value: kvpair,
-            project_id: self.project_id.clone(),
-            created_at: self.created_at.clone(),
-        }
-    }
-}
-
-pub trait ToParsed {
-    fn to_parsed(&self) -> Vec<ParsedPartialVariable>;
-    fn zip_to_parsed(&self, kvpair: Vec<KVPair>) -> Vec<ParsedPartialVariable>;
-}
-
-impl ToParsed for Vec<PartialVariable> {
-    fn to_parsed(&self) -> Vec<ParsedPartialVariable> {
-        self.iter().map(|p| p.to_parsed()).collect()
-    }
-
-    fn zip_to_parsed(
-        &self,
-        kvpairs: Vec<KVPair>,
-    ) -> Vec<ParsedPartialVariable> {
-        self.iter()
-            .zip(kvpairs)
-            .map(|(p, k)| p.zip_to_parsed(k))
-            .collect()
-    }
-}
+use super::{kvpair::KVPair, signing::Signer};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ParsedPartialVariable {
@@ -56,31 +9,19 @@ pub struct ParsedPartialVariable {
     pub value: KVPair,
     pub project_id: String,
     pub created_at: String,
+    #[serde(default)]
+    pub signer: Signer,
 }
 
-pub trait DeDupe {
-    fn dedupe(&self) -> Self;
-}
-
-use std::{collections::HashMap, fmt::Display};
-
-impl DeDupe for Vec<ParsedPartialVariable> {
-    fn dedupe(&self) -> Self {
-        // Sort the vector based on the `created_at` timestamp in descending order
-        let mut sorted_vec = self.clone();
-        sorted_vec.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-
-        // HashMap to track encountered keys
-        let mut seen: HashMap<String, ParsedPartialVariable> = HashMap::new();
-
-        // Iterate and populate the HashMap
-        for variable in sorted_vec {
-            let key = variable.value.key.clone();
-            seen.entry(key).or_insert(variable);
+impl From<envx::Variable> for ParsedPartialVariable {
+    fn from(variable: envx::Variable) -> Self {
+        ParsedPartialVariable {
+            id: variable.id,
+            value: KVPair::new(variable.name, variable.value),
+            project_id: variable.project_id,
+            created_at: variable.created_at,
+            signer: variable.signer,
         }
-
-        // Collect the deduplicated variables into a new Vec
-        seen.into_values().collect()
     }
 }
 
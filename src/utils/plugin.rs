@@ -0,0 +1,171 @@
+// External commands: `envx foo` runs `envx-foo` from PATH, like git
+//
+// Built-in commands always win over plugins of the same name. Plugins run
+// with the environment of envx plus the following, which `envx` commands
+// run by the plugin pick up as well:
+//
+// ENVX_PROJECT     project in use, when one is given, linked or set in
+//                  .envx.toml
+// ENVX_KEY         fingerprint of the key in use
+// ENVX_API_URL     server URL of the context in use
+// ENVX_CONTEXT     name of the context in use
+// ENVX_HOME        config directory, when given with --config-dir
+// ENVX_ENV         environment from .envx.toml, when given with --env
+// ENVX_AGENT_SOCK  socket of `envx agent`, when it is running
+// ENVX_TOKEN       short lived API token, only when the agent holds the
+//                  key, sent as `Authorization: Bearer $ENVX_TOKEN`
+//
+// Whatever can't be worked out without prompting is left unset.
+
+use super::auth::agent_token;
+use super::choice::Choice;
+use super::config::get_config;
+use super::env::{
+    ENVX_AGENT_SOCK, ENVX_API_URL, ENVX_CONTEXT, ENVX_ENV, ENVX_HOME, ENVX_KEY,
+    ENVX_PROJECT, ENVX_TOKEN,
+};
+use super::project_file::selected_environment;
+use super::{agent, dirs};
+use crate::sdk::get_api_url;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const PREFIX: &str = "envx-";
+
+#[derive(Debug, Serialize)]
+pub struct Plugin {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Every plugin on PATH, the first one of each name as that's the one run
+pub fn list() -> Vec<Plugin> {
+    let mut plugins = BTreeMap::new();
+    let Some(path) = std::env::var_os("PATH") else {
+        return vec![];
+    };
+    for dir in std::env::split_paths(&path) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = plugin_name(&path) else {
+                continue;
+            };
+            if is_executable(&path) {
+                plugins.entry(name).or_insert(path);
+            }
+        }
+    }
+    plugins
+        .into_iter()
+        .map(|(name, path)| Plugin { name, path })
+        .collect()
+}
+
+pub fn find(name: &str) -> Option<Plugin> {
+    list().into_iter().find(|p| p.name == name)
+}
+
+/// The name of the plugin at `path`, without prefix and extension
+fn plugin_name(path: &Path) -> Option<String> {
+    let name = if cfg!(windows) {
+        path.file_stem()?
+    } else {
+        path.file_name()?
+    };
+    let name = name.to_str()?.strip_prefix(PREFIX)?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("exe"))
+}
+
+/// Run the plugin named by the first argument with the rest, exiting with
+/// its status
+pub async fn run(args: Vec<String>) -> Result<()> {
+    let (name, args) = args.split_first().context("No command given")?;
+    let Some(plugin) = find(name) else {
+        bail!(
+            "'{}' is not an envx command and no {}{} was found on PATH\nSee `envx --help` and `envx plugins ls`",
+            name,
+            PREFIX,
+            name
+        );
+    };
+
+    let mut command = Command::new(&plugin.path);
+    command.args(args).envs(environment());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let error = command.exec();
+        Err(error)
+            .with_context(|| format!("Failed to run {}", plugin.path.display()))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let status = command.status().with_context(|| {
+            format!("Failed to run {}", plugin.path.display())
+        })?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
+
+/// The variables described at the top of this file
+fn environment() -> BTreeMap<&'static str, String> {
+    let mut vars = BTreeMap::new();
+
+    if let Some(home) = dirs::home_override() {
+        vars.insert(ENVX_HOME, home.display().to_string());
+    }
+    if let Some(environment) = selected_environment() {
+        vars.insert(ENVX_ENV, environment);
+    }
+    if let Ok(Some(project_id)) = Choice::configured_project() {
+        vars.insert(ENVX_PROJECT, project_id);
+    }
+    if let Ok(url) = get_api_url() {
+        vars.insert(ENVX_API_URL, url.to_string());
+    }
+
+    let Ok(config) = get_config() else {
+        return vars;
+    };
+    vars.insert(ENVX_CONTEXT, config.context_name().to_string());
+    let Ok(key) = config.get_key_or_default(None) else {
+        return vars;
+    };
+    vars.insert(ENVX_KEY, key.fingerprint.clone());
+
+    if !agent::is_running() {
+        return vars;
+    }
+    if let Ok(socket) = agent::socket_path() {
+        vars.insert(ENVX_AGENT_SOCK, socket.display().to_string());
+    }
+    if let Some(Ok(token)) = key
+        .uuid
+        .as_deref()
+        .and_then(|uuid| agent_token(&key.fingerprint, uuid))
+    {
+        vars.insert(ENVX_TOKEN, token.to_string());
+    }
+    vars
+}
@@ -0,0 +1,392 @@
+// The `.envx.toml` project file
+//
+// Committed at the root of a repository so every checkout is linked to the
+// same project, e.g.
+//
+//     project = "<project id>"
+//     default_environment = "dev"
+//     required = ["DATABASE_URL", "PORT"]
+//
+//     [environments.prod]
+//     project = "<project id>"
+//
+//     [variables.PORT]
+//     type = "port"
+//
+//     [commands.run]
+//     include = ["DATABASE_*", "PORT"]
+//     exclude = ["*_ADMIN_*"]
+//
+// See `utils::schema` for the variable schema.
+
+use super::config::get_config;
+use super::env::{self, ENVX_ENV};
+use super::schema::{Problem, Validation, VariableSchema};
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+pub const PROJECT_FILE_NAME: &str = ".envx.toml";
+
+/// Environment selected with `--env`, takes precedence over `ENVX_ENV`
+static ENVIRONMENT_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// Use this environment for the rest of the process
+pub fn set_environment_override(name: String) {
+    let _ = ENVIRONMENT_OVERRIDE.set(name);
+}
+
+/// The environment selected with `--env` or `ENVX_ENV`
+pub fn selected_environment() -> Option<String> {
+    ENVIRONMENT_OVERRIDE
+        .get()
+        .cloned()
+        .or_else(|| env::var(ENVX_ENV))
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectFile {
+    /// Project used when no environment is selected
+    pub project: Option<String>,
+    /// Environment used when none is selected
+    pub default_environment: Option<String>,
+    /// Variables that must be set in the project, shorthand for
+    /// `[variables.NAME]` without any options
+    #[serde(default)]
+    pub required: Vec<String>,
+    /// Schema of the variables of the project
+    #[serde(default)]
+    pub variables: BTreeMap<String, VariableSchema>,
+    /// What `run` and `shell` do when the variables don't match the schema
+    #[serde(default)]
+    pub validation: Validation,
+    #[serde(default)]
+    pub environments: BTreeMap<String, Environment>,
+    /// Filters applied to the variables by command name
+    #[serde(default)]
+    pub commands: BTreeMap<String, Filter>,
+    /// Where the file was found
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Environment {
+    pub project: String,
+}
+
+/// Include and exclude patterns for variable names, `*` matches anything
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Filter {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl ProjectFile {
+    /// Find the project file in the current directory or one of its parents
+    pub fn find() -> Result<Option<Self>> {
+        let mut path = std::env::current_dir()?;
+        loop {
+            let candidate = path.join(PROJECT_FILE_NAME);
+            if candidate.is_file() {
+                return Self::read(candidate).map(Some);
+            }
+            if !path.pop() {
+                return Ok(None);
+            }
+        }
+    }
+
+    fn read(path: PathBuf) -> Result<Self> {
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut file = toml::from_str::<Self>(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        file.path = path;
+        Ok(file)
+    }
+
+    /// The name of the environment in use, if any
+    ///
+    /// `--env` and `ENVX_ENV` come first, then the `default_environment`
+    /// setting if the file defines that environment, and then the default
+    /// of the file.
+    pub fn environment_name(&self) -> Option<String> {
+        let setting = get_config()
+            .and_then(|c| c.get_settings())
+            .ok()
+            .and_then(|s| s.default_environment);
+        self.pick_environment(selected_environment(), setting)
+    }
+
+    fn pick_environment(
+        &self,
+        selected: Option<String>,
+        setting: Option<String>,
+    ) -> Option<String> {
+        // the setting applies to every repository, not all of them have
+        // that environment
+        let setting =
+            setting.filter(|name| self.environments.contains_key(name));
+        selected
+            .or(setting)
+            .or_else(|| self.default_environment.clone())
+    }
+
+    /// The project id for the selected environment
+    pub fn project_id(&self) -> Result<Option<String>> {
+        let Some(name) = self.environment_name() else {
+            return Ok(self.project.clone());
+        };
+
+        match self.environments.get(&name) {
+            Some(environment) => Ok(Some(environment.project.clone())),
+            None => bail!(
+                "Environment '{}' is not defined in {}",
+                name,
+                self.path.display()
+            ),
+        }
+    }
+
+    /// Keep only the variables the filter of `command` allows
+    pub fn filter<T>(
+        &self,
+        command: &str,
+        variables: Vec<T>,
+        name: impl Fn(&T) -> &str,
+    ) -> Result<Vec<T>> {
+        let Some(filter) = self.commands.get(command) else {
+            return Ok(variables);
+        };
+
+        let include = compile(&filter.include)?;
+        let exclude = compile(&filter.exclude)?;
+
+        Ok(variables
+            .into_iter()
+            .filter(|v| {
+                let name = name(v);
+                (include.is_empty() || include.iter().any(|p| p.is_match(name)))
+                    && !exclude.iter().any(|p| p.is_match(name))
+            })
+            .collect())
+    }
+
+    /// The schema of every declared variable
+    pub fn schema(&self) -> BTreeMap<String, VariableSchema> {
+        let mut schema = self.variables.clone();
+        for key in &self.required {
+            schema
+                .entry(key.clone())
+                .or_insert_with(VariableSchema::required);
+        }
+        schema
+    }
+
+    /// Whether the schema marks a variable as secret
+    pub fn is_secret(&self, name: &str) -> bool {
+        self.variables.get(name).is_some_and(|s| s.secret)
+    }
+
+    /// Check the variables against the schema
+    pub fn validate(
+        &self,
+        variables: &BTreeMap<&str, &str>,
+    ) -> Result<Vec<Problem>> {
+        let mut problems = vec![];
+        for (key, schema) in self.schema() {
+            let value = variables.get(key.as_str()).copied();
+            if let Some(message) = schema.validate(value)? {
+                problems.push(Problem { key, message });
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Validate the variables before starting `command`, failing or warning
+    /// depending on `validation`
+    pub fn enforce(
+        &self,
+        command: &str,
+        variables: &BTreeMap<&str, &str>,
+    ) -> Result<()> {
+        if self.validation == Validation::Off {
+            return Ok(());
+        }
+
+        let problems = self.validate(variables)?;
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        let list = problems
+            .iter()
+            .map(|p| format!("  {}", p))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        if self.validation == Validation::Error {
+            bail!(
+                "The variables don't match the schema in {}:\n{}\nSee `envx check`, or set `validation = \"warn\"` to {} anyway",
+                self.path.display(),
+                list,
+                command
+            );
+        }
+
+        eprintln!(
+            "{}",
+            format!(
+                "WARNING: The variables don't match the schema in {}:",
+                self.path.display()
+            )
+            .yellow()
+        );
+        eprintln!("{}", list);
+
+        Ok(())
+    }
+}
+
+/// Turn `*` wildcard patterns into anchored regexes
+fn compile(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|p| {
+            let pattern =
+                format!("^{}$", regex::escape(p).replace(r"\*", ".*"));
+            Regex::new(&pattern)
+                .with_context(|| format!("Invalid pattern '{}'", p))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml: &str) -> ProjectFile {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn filter(file: &ProjectFile, command: &str) -> Vec<&'static str> {
+        let names = vec!["DATABASE_URL", "DATABASE_ADMIN_URL", "PORT", "DEBUG"];
+        file.filter(command, names, |n| n).unwrap()
+    }
+
+    #[test]
+    fn filters_by_command() {
+        let file = parse(
+            r#"
+            [commands.run]
+            include = ["DATABASE_*", "PORT"]
+            exclude = ["*_ADMIN_*"]
+
+            [commands.shell]
+            exclude = ["DEBUG"]
+            "#,
+        );
+        assert_eq!(filter(&file, "run"), ["DATABASE_URL", "PORT"]);
+        assert_eq!(
+            filter(&file, "shell"),
+            ["DATABASE_URL", "DATABASE_ADMIN_URL", "PORT"]
+        );
+        assert_eq!(filter(&file, "variables").len(), 4);
+    }
+
+    #[test]
+    fn matches_whole_names_literally() {
+        let file = parse(
+            r#"
+            [commands.run]
+            include = ["PORT", "DATABASE.URL"]
+            "#,
+        );
+        assert_eq!(filter(&file, "run"), ["PORT"]);
+    }
+
+    #[test]
+    fn merges_required_into_the_schema() {
+        let file = parse(
+            r#"
+            required = ["PORT", "DATABASE_URL"]
+
+            [variables.PORT]
+            type = "port"
+            required = false
+            secret = true
+            "#,
+        );
+        let schema = file.schema();
+        assert_eq!(schema.len(), 2);
+        assert!(schema["DATABASE_URL"].required);
+        assert!(!schema["PORT"].required);
+        assert!(file.is_secret("PORT"));
+        assert!(!file.is_secret("DATABASE_URL"));
+    }
+
+    #[test]
+    fn validates_against_the_schema() {
+        let file = parse(
+            r#"
+            required = ["DATABASE_URL"]
+
+            [variables.PORT]
+            type = "port"
+            "#,
+        );
+        let variables = BTreeMap::from([("PORT", "http")]);
+        let problems = file.validate(&variables).unwrap();
+        let keys = problems.iter().map(|p| p.key.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, ["DATABASE_URL", "PORT"]);
+    }
+
+    #[test]
+    fn ignores_a_default_environment_the_file_lacks() {
+        let file = parse(
+            r#"
+            project = "shared"
+            default_environment = "dev"
+
+            [environments.dev]
+            project = "dev-project"
+
+            [environments.prod]
+            project = "prod-project"
+            "#,
+        );
+        let pick = |selected: Option<&str>, setting: Option<&str>| {
+            file.pick_environment(
+                selected.map(String::from),
+                setting.map(String::from),
+            )
+        };
+
+        assert_eq!(pick(None, Some("prod")).as_deref(), Some("prod"));
+        assert_eq!(pick(None, Some("staging")).as_deref(), Some("dev"));
+        assert_eq!(pick(Some("staging"), None).as_deref(), Some("staging"));
+
+        let file = parse(r#"project = "shared""#);
+        assert_eq!(file.pick_environment(None, Some("prod".into())), None);
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(toml::from_str::<ProjectFile>("projet = \"x\"").is_err());
+        assert!(toml::from_str::<ProjectFile>(
+            "[commands.run]\ninclude = [\"A\"]\nexclud = [\"B\"]"
+        )
+        .is_err());
+    }
+}
@@ -1,6 +1,48 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use inquire::ui::{Attributes, RenderConfig, StyleSheet, Styled};
 use std::fmt::Display;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--non-interactive`
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Turn every prompt into an error for the rest of the process
+pub fn set_non_interactive() {
+    NON_INTERACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// Whether prompts can be shown, false with `--non-interactive` or when
+/// stdin isn't a terminal
+pub fn is_interactive() -> bool {
+    !NON_INTERACTIVE.load(Ordering::Relaxed) && std::io::stdin().is_terminal()
+}
+
+/// Fail instead of prompting when running non-interactively
+///
+/// Callers that know which flag or environment variable answers the
+/// prompt should check with `interact_or!` first for a better message.
+fn ensure_interactive(message: &str) -> Result<()> {
+    if !is_interactive() {
+        bail!(
+            "Can't prompt for '{}' in non-interactive mode",
+            message.trim_end_matches([':', ' '])
+        );
+    }
+    Ok(())
+}
+
+/// Read `count` lines from stdin, for secrets that must not be passed as
+/// arguments where other users and the shell history can see them
+pub fn read_stdin_lines(count: usize) -> Result<Vec<String>> {
+    let mut lines = std::io::stdin().lines();
+    (0..count)
+        .map(|_| match lines.next() {
+            Some(line) => line.context("Failed to read stdin"),
+            None => bail!("Expected {} lines on stdin", count),
+        })
+        .collect()
+}
 
 pub fn get_render_config() -> RenderConfig {
     RenderConfig::default_colored()
@@ -25,6 +67,7 @@ pub fn get_render_config() -> RenderConfig {
 
 #[allow(dead_code)]
 pub fn prompt_options<T: Display>(message: &str, options: Vec<T>) -> Result<T> {
+    ensure_interactive(message)?;
     let select = inquire::Select::new(message, options);
     select
         .with_render_config(get_render_config())
@@ -34,6 +77,7 @@ pub fn prompt_options<T: Display>(message: &str, options: Vec<T>) -> Result<T> {
 
 #[allow(dead_code)]
 pub fn prompt_confirm(message: &str) -> Result<bool> {
+    ensure_interactive(message)?;
     let confirm = inquire::Confirm::new(message);
     confirm
         .with_render_config(get_render_config())
@@ -46,6 +90,7 @@ pub fn prompt_confirm_with_default(
     message: &str,
     default: bool,
 ) -> Result<bool> {
+    ensure_interactive(message)?;
     let confirm = inquire::Confirm::new(message);
     confirm
         .with_default(default)
@@ -59,6 +104,7 @@ pub fn prompt_multi_options<T: Display>(
     message: &str,
     options: Vec<T>,
 ) -> Result<Vec<T>> {
+    ensure_interactive(message)?;
     let multi_select = inquire::MultiSelect::new(message, options);
     multi_select
         .with_render_config(get_render_config())
@@ -68,6 +114,7 @@ pub fn prompt_multi_options<T: Display>(
 
 #[allow(dead_code)]
 pub fn prompt_text(message: &str) -> Result<String> {
+    ensure_interactive(message)?;
     let text = inquire::Text::new(message);
     text.with_render_config(get_render_config())
         .prompt()
@@ -76,6 +123,7 @@ pub fn prompt_text(message: &str) -> Result<String> {
 
 #[allow(dead_code)]
 pub fn prompt_password(message: &str) -> Result<String> {
+    ensure_interactive(message)?;
     let password = inquire::Password::new(message);
     password
         .with_render_config(get_render_config())
@@ -85,6 +133,7 @@ pub fn prompt_password(message: &str) -> Result<String> {
 
 #[allow(dead_code)]
 pub fn prompt_email(message: &str) -> Result<String> {
+    ensure_interactive(message)?;
     let validator = |input: &str| {
         let regex = regex::Regex::new(
             r"^[a-zA-Z0-9_.+-]+@[a-zA-Z0-9-]+\.[a-zA-Z0-9-.]+$",
@@ -109,6 +158,7 @@ pub fn prompt_email(message: &str) -> Result<String> {
 
 #[allow(dead_code)]
 pub fn prompt_select<T: Display>(message: &str, options: Vec<T>) -> Result<T> {
+    ensure_interactive(message)?;
     inquire::Select::new(message, options)
         .with_render_config(get_render_config())
         .prompt()
@@ -1,19 +1,32 @@
+use super::agent;
 use super::config::{get_config, Config};
+use super::dirs;
+use super::env;
 use super::keyring::try_get_password;
+use super::vault;
+use crate::constants::KEY_EXPIRY_WARNING_DAYS;
+use crate::info;
 use anyhow::anyhow;
 use anyhow::{Context, Ok, Result};
+use chrono::{DateTime, SubsecRound, TimeZone, Utc};
 use colored::Colorize;
 use crypto_hash::{hex_digest, Algorithm};
 use hex::ToHex;
 use pgp::composed::message::Message;
+use pgp::packet::{SignatureConfig, SignatureType, Subpacket, SubpacketData};
+use pgp::ser::Serialize;
+use pgp::types::{
+    KeyTrait, KeyVersion, SecretParams, StringToKey, Tag, Version,
+};
 use pgp::{
     composed, composed::signed_key::*, crypto, types::SecretKeyTrait,
-    Deserializable,
+    Deserializable, StandaloneSignature,
 };
 use rand::prelude::*;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use smallvec::*;
-use std::{fs, io::Cursor, path::Path};
+use std::time::Duration;
+use std::{io::Cursor, path::Path};
 
 #[derive(Debug)]
 pub struct KeyPair {
@@ -23,19 +36,14 @@ pub struct KeyPair {
 
 pub(crate) fn get_vault_location(
 ) -> anyhow::Result<std::path::PathBuf, anyhow::Error> {
-    let path = home::home_dir()
-        .context("Failed to get home directory")?
-        .join(".config")
-        .join("envcli")
-        .join("keys");
-
-    Ok(path)
+    Ok(dirs::data_dir()?.join("keys"))
 }
 
 pub fn generate_key_pair(
     name: String,
     email: String,
     password: String,
+    expires: Option<Duration>,
 ) -> Result<KeyPair, anyhow::Error> {
     let mut key_params = composed::key::SecretKeyParamsBuilder::default();
 
@@ -65,14 +73,21 @@ pub fn generate_key_pair(
 
     let passwd_fn = || password.clone();
 
-    let signed_secret_key = secret_key
+    let mut signed_secret_key = secret_key
         .sign(passwd_fn)
         .expect("Secret Key must be able to sign its own metadata");
 
-    let public_key = signed_secret_key.public_key();
-    let signed_public_key = public_key
-        .sign(&signed_secret_key, passwd_fn)
-        .expect("Public key must be able to sign its own metadata");
+    if let Some(expires) = expires {
+        set_key_expiration(&mut signed_secret_key, expires, passwd_fn)?;
+    }
+
+    // Built from the secret key's details so the self-signatures (and the
+    // expiration time in them) are identical
+    let signed_public_key = SignedPublicKey::new(
+        signed_secret_key.primary_key.public_key(),
+        signed_secret_key.details.clone(),
+        vec![],
+    );
 
     let key_pair = KeyPair {
         secret_key: signed_secret_key,
@@ -82,6 +97,200 @@ pub fn generate_key_pair(
     Ok(key_pair)
 }
 
+/// Re-sign the user id self-signatures with a key expiration time
+///
+/// rpgp doesn't support setting the expiration of v4 keys when generating
+/// them, so the subpacket is added to the existing self-signatures
+fn set_key_expiration<F>(
+    key: &mut SignedSecretKey,
+    expires: Duration,
+    passwd_fn: F,
+) -> Result<()>
+where
+    F: Fn() -> String,
+{
+    let offset = Utc
+        .timestamp_opt(expires.as_secs() as i64, 0)
+        .single()
+        .context("Invalid expiration time")?;
+
+    let signing_key = key.clone();
+    for user in key.details.users.iter_mut() {
+        let mut signatures = vec![];
+        for signature in user.signatures.iter() {
+            let mut config = signature.config.clone();
+            config.hashed_subpackets.retain(|p| {
+                !matches!(p.data, SubpacketData::SignatureCreationTime(_))
+            });
+            config.hashed_subpackets.push(Subpacket::regular(
+                SubpacketData::SignatureCreationTime(
+                    Utc::now().trunc_subsecs(0),
+                ),
+            ));
+            config.hashed_subpackets.push(Subpacket::regular(
+                SubpacketData::KeyExpirationTime(offset),
+            ));
+            signatures.push(config.sign_certificate(
+                &signing_key,
+                &passwd_fn,
+                Tag::UserId,
+                &user.id,
+            )?);
+        }
+        user.signatures = signatures;
+    }
+
+    Ok(())
+}
+
+/// Create a revocation certificate for a key, to be published if the key
+/// is lost or compromised
+pub fn generate_revocation_certificate(
+    seckey: &SignedSecretKey,
+    password: String,
+) -> Result<String> {
+    let config = SignatureConfig::new_v4(
+        Default::default(),
+        SignatureType::KeyRevocation,
+        seckey.algorithm(),
+        crypto::hash::HashAlgorithm::SHA2_256,
+        vec![
+            Subpacket::regular(SubpacketData::SignatureCreationTime(
+                Utc::now().trunc_subsecs(0),
+            )),
+            Subpacket::regular(SubpacketData::IssuerFingerprint(
+                KeyVersion::V4,
+                SmallVec::from_slice(&seckey.fingerprint()),
+            )),
+        ],
+        vec![Subpacket::regular(SubpacketData::Issuer(seckey.key_id()))],
+    );
+
+    let signature =
+        config.sign_key(seckey, || password, &seckey.primary_key)?;
+
+    Ok(StandaloneSignature::new(signature).to_armored_string(None)?)
+}
+
+/// Attach a revocation certificate to a public key
+///
+/// Returns the revoked public key, armored
+pub fn revoke_public_key(
+    public_key: &str,
+    certificate: &str,
+) -> Result<String> {
+    let (mut public_key, _) = SignedPublicKey::from_string(public_key)
+        .context("Failed to parse public key")?;
+    let (certificate, _) = StandaloneSignature::from_string(certificate)
+        .context("Failed to parse revocation certificate")?;
+
+    if certificate.signature.typ() != SignatureType::KeyRevocation {
+        return Err(anyhow!("Not a revocation certificate"));
+    }
+
+    public_key
+        .details
+        .revocation_signatures
+        .push(certificate.signature);
+
+    Ok(public_key.to_armored_string(None)?)
+}
+
+/// Rebuild the public key from a secret key
+pub fn public_key_from_secret(seckey: &SignedSecretKey) -> SignedPublicKey {
+    SignedPublicKey::new(
+        seckey.primary_key.public_key(),
+        seckey.details.clone(),
+        seckey.public_subkeys.clone(),
+    )
+}
+
+/// Re-encrypt a secret key with a new passphrase
+///
+/// Fails without touching anything if the old passphrase is wrong
+pub fn change_passphrase(
+    seckey: &SignedSecretKey,
+    old_password: String,
+    new_password: &str,
+) -> Result<SignedSecretKey> {
+    if !seckey.secret_subkeys.is_empty() {
+        return Err(anyhow!(
+            "Changing the passphrase of keys with secret subkeys is not supported"
+        ));
+    }
+
+    let primary_key = &seckey.primary_key;
+    let plain = match primary_key.secret_params() {
+        SecretParams::Plain(plain) => plain.clone(),
+        SecretParams::Encrypted(encrypted) => encrypted
+            .unlock(
+                || old_password,
+                primary_key.algorithm(),
+                primary_key.public_params(),
+            )
+            .context("Failed to unlock key, is the passphrase correct?")?,
+    };
+
+    let mut rng = StdRng::from_entropy();
+    let s2k = StringToKey::new_default(&mut rng);
+    let encrypted = plain.encrypt(
+        &mut rng,
+        new_password,
+        crypto::sym::SymmetricKeyAlgorithm::AES256,
+        s2k,
+        KeyVersion::V4,
+        254,
+    )?;
+
+    // rpgp doesn't expose a constructor for secret key packets, so the
+    // packet is rebuilt from its serialized form
+    let mut body = vec![];
+    primary_key.public_key().to_writer(&mut body)?;
+    SecretParams::Encrypted(encrypted).to_writer(&mut body)?;
+    let primary_key = pgp::packet::SecretKey::from_slice(Version::New, &body)?;
+
+    let new_key = SignedSecretKey::new(
+        primary_key,
+        seckey.details.clone(),
+        seckey.public_subkeys.clone(),
+        vec![],
+    );
+
+    // Make sure the new passphrase actually unlocks the key
+    new_key
+        .unlock(|| new_password.to_string(), |_| std::result::Result::Ok(()))
+        .context("Failed to unlock key with the new passphrase")?;
+
+    Ok(new_key)
+}
+
+/// Print a warning if the key has expired or is about to expire
+pub fn warn_on_expiry(fingerprint: &str, expires_at: Option<DateTime<Utc>>) {
+    let Some(expires_at) = expires_at else {
+        return;
+    };
+
+    let remaining = expires_at - Utc::now();
+    if remaining <= chrono::Duration::zero() {
+        eprintln!(
+            "{}",
+            format!("Warning: key {} expired on {}", fingerprint, expires_at)
+                .red()
+        );
+    } else if remaining < chrono::Duration::days(KEY_EXPIRY_WARNING_DAYS) {
+        eprintln!(
+            "{}",
+            format!(
+                "Warning: key {} expires in {} days ({})",
+                fingerprint,
+                remaining.num_days(),
+                expires_at
+            )
+            .yellow()
+        );
+    }
+}
+
 pub fn encrypt(msg: &str, pubkey_str: &str) -> Result<String, anyhow::Error> {
     let (pubkey, _) = SignedPublicKey::from_string(pubkey_str)?;
     // Requires a file name as the first arg, in this case I pass "none", as it's not used
@@ -97,24 +306,34 @@ pub fn encrypt(msg: &str, pubkey_str: &str) -> Result<String, anyhow::Error> {
     Ok(new_msg.to_armored_string(None)?)
 }
 
-pub fn encrypt_multi(
+/// Sign a message with the author's key
+pub fn sign_message(
     msg: &str,
-    pubkeys: &[SignedPublicKey],
-) -> Result<String, anyhow::Error> {
-    let mut rng = StdRng::from_entropy();
-
-    let borrowed_keys =
-        pubkeys.iter().collect::<SmallVec<[&SignedPublicKey; 1]>>();
+    seckey: &SignedSecretKey,
+    password: String,
+) -> Result<Message, anyhow::Error> {
+    composed::message::Message::new_literal("none", msg)
+        .sign(seckey, || password, crypto::hash::HashAlgorithm::SHA3_512)
+        .context("Failed to sign message")
+}
 
-    let msg = composed::message::Message::new_literal("none", msg);
+/// Sign every message with the key of `fingerprint`, through `envx agent`
+/// if it holds the key
+pub fn sign_many(
+    msgs: &[String],
+    fingerprint: &str,
+    config: &Config,
+) -> Result<Vec<Message>, anyhow::Error> {
+    if let Some(signed) = agent::sign_many(fingerprint, msgs) {
+        return signed;
+    }
 
-    let new_msg = msg.encrypt_to_keys(
-        &mut rng,
-        crypto::sym::SymmetricKeyAlgorithm::AES128,
-        &borrowed_keys,
-    )?;
+    let (seckey, fingerprint) = get_key(fingerprint)?;
+    let passphrase = try_get_password(&fingerprint, config)?;
 
-    Ok(new_msg.to_armored_string(None)?)
+    msgs.par_iter()
+        .map(|m| sign_message(m, &seckey, passphrase.clone()))
+        .collect()
 }
 
 pub fn decrypt(
@@ -122,22 +341,45 @@ pub fn decrypt(
     seckey: &SignedSecretKey,
     password: String,
 ) -> Result<String, anyhow::Error> {
+    message_to_string(&decrypt_message(armored, seckey, password)?)
+}
+
+/// Decrypt a message, keeping the inner message (and its signature, if any)
+pub fn decrypt_message(
+    armored: &str,
+    seckey: &SignedSecretKey,
+    password: String,
+) -> Result<Message, anyhow::Error> {
     let buf = Cursor::new(armored);
     let (msg, _) = composed::message::Message::from_armor_single(buf)
         .context("Failed to convert &str to armored message")?;
+    decrypt_parsed_message(&msg, seckey, password)
+}
+
+/// Decrypt an already parsed message
+pub fn decrypt_parsed_message(
+    msg: &Message,
+    seckey: &SignedSecretKey,
+    password: String,
+) -> Result<Message, anyhow::Error> {
     let (mut decryptor, _) = msg
         .decrypt(|| password, &[seckey])
         .context("Decrypting the message")?;
 
     if let Some(msg) = decryptor.next() {
-        let bytes = msg?.get_content()?.context("Failed to get content")?;
-        let clear_text = String::from_utf8(bytes)?;
-        return Ok(clear_text);
+        return Ok(msg?);
     }
 
     Err(anyhow::Error::msg("Failed to find message"))
 }
 
+/// Get the clear text content of a decrypted message
+pub fn message_to_string(msg: &Message) -> Result<String, anyhow::Error> {
+    let bytes = msg.get_content()?.context("Failed to get content")?;
+    let clear_text = String::from_utf8(bytes)?;
+    Ok(clear_text)
+}
+
 pub fn hash_string(input: &str) -> String {
     let hash = hex_digest(Algorithm::SHA512, input.as_bytes());
     hash.to_string()
@@ -188,14 +430,23 @@ pub fn decrypt_full(
     }
 
     let primary_key = &config.primary_key;
-    let (key, fingerprint) =
-        if available_keys.iter().any(|k| k.contains(primary_key)) {
-            get_key(primary_key)?
-        } else {
-            println!("Using key: {}", &available_keys[0]);
-            get_key(&available_keys[0])?
-        };
+    let fingerprint = if available_keys.iter().any(|k| k.contains(primary_key))
+    {
+        primary_key
+    } else {
+        info!("Using key: {}", &available_keys[0]);
+        &available_keys[0]
+    };
+
+    if let Some(decrypted) =
+        agent::decrypt(fingerprint, std::slice::from_ref(&message))
+    {
+        return message_to_string(
+            decrypted?.first().context("No message from the agent")?,
+        );
+    }
 
+    let (key, fingerprint) = get_key(fingerprint.as_str())?;
     let passphrase = try_get_password(&fingerprint, config)?;
 
     let decrypted = decrypt(message.as_str(), &key, passphrase)?;
@@ -203,12 +454,15 @@ pub fn decrypt_full(
     Ok(decrypted)
 }
 
-pub fn decrypt_full_many(
-    messages: Vec<String>,
+/// Decrypt messages with whichever of our keys they are encrypted to,
+/// through `envx agent` if it holds the key
+///
+/// Returns the inner messages so that their signatures can be verified
+pub fn decrypt_many_messages(
+    messages: &[Message],
     config: &Config,
-) -> Result<Vec<String>, anyhow::Error> {
-    let first = messages.first().ok_or_else(|| anyhow!("No messages"))?;
-    let msg = Message::from_string(first.as_str())?.0;
+) -> Result<Vec<Message>, anyhow::Error> {
+    let msg = messages.first().ok_or_else(|| anyhow!("No messages"))?;
 
     let recipients: Vec<String> = msg
         .get_recipients()
@@ -242,37 +496,57 @@ pub fn decrypt_full_many(
     }
 
     let primary_key = &config.primary_key;
-    let (key, fingerprint) =
-        if available_keys.iter().any(|k| k.contains(primary_key)) {
-            get_key(primary_key)?
-        } else {
-            println!("Using key: {}", &available_keys[0]);
-            get_key(&available_keys[0])?
-        };
+    let fingerprint = if available_keys.iter().any(|k| k.contains(primary_key))
+    {
+        primary_key
+    } else {
+        info!("Using key: {}", &available_keys[0]);
+        &available_keys[0]
+    };
 
+    // the agent takes armored messages, don't armor them for nothing
+    if agent::socket_path().is_ok_and(|path| path.exists()) {
+        let armored = messages
+            .iter()
+            .map(|m| m.to_armored_string(None))
+            .collect::<Result<Vec<String>, _>>()?;
+        if let Some(decrypted) = agent::decrypt(fingerprint, &armored) {
+            return decrypted;
+        }
+    }
+
+    let (key, fingerprint) = get_key(fingerprint.as_str())?;
     let passphrase = try_get_password(&fingerprint, config)?;
 
     let decrypted = messages
         .par_iter()
-        .map(|m| decrypt(m.as_str(), &key, passphrase.clone()))
-        .collect::<Result<Vec<String>, anyhow::Error>>()?;
+        .map(|m| decrypt_parsed_message(m, &key, passphrase.clone()))
+        .collect::<Result<Vec<Message>, anyhow::Error>>()?;
 
     Ok(decrypted)
 }
 
-/// Get the key from the keyring
+/// Get the key from the keyring, or from `ENVX_PRIVATE_KEY`
 ///
 /// Returns (Key, fingerprint)
-fn get_key<T>(fingerprint: T) -> Result<(SignedSecretKey, String)>
+pub fn get_key<T>(fingerprint: T) -> Result<(SignedSecretKey, String)>
 where
     T: AsRef<Path> + Into<String>,
 {
-    let location = get_vault_location()?.join(&fingerprint).join("private.key");
+    let fingerprint = fingerprint.into();
+    if let Some((seckey, env_fingerprint)) = env::private_key()? {
+        if env_fingerprint.eq_ignore_ascii_case(&fingerprint) {
+            warn_on_expiry(&fingerprint, seckey.expires_at());
+            return Ok((seckey, fingerprint));
+        }
+    }
 
-    let priv_key =
-        fs::read_to_string(location).context("Failed to read private key")?;
+    let priv_key = vault::read(&fingerprint, vault::PRIVATE_KEY)
+        .context("Failed to read private key")?;
     let (seckey, _) = SignedSecretKey::from_string(priv_key.as_str())
         .context("Failed to convert private key to string")?;
 
-    Ok((seckey, fingerprint.into()))
+    warn_on_expiry(&fingerprint, seckey.expires_at());
+
+    Ok((seckey, fingerprint))
 }
@@ -0,0 +1,230 @@
+// Schema for the variables of a project, declared in `.envx.toml`
+//
+//     [variables.DATABASE_URL]
+//     type = "url"
+//     secret = true
+//
+//     [variables.PORT]
+//     type = "port"
+//     required = false
+//
+//     [variables.LOG_LEVEL]
+//     pattern = "^(debug|info|warn|error)$"
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    #[default]
+    String,
+    Int,
+    Url,
+    Bool,
+    Json,
+    Port,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VariableSchema {
+    #[serde(default, rename = "type")]
+    pub kind: VariableType,
+    #[serde(default = "default_required")]
+    pub required: bool,
+    /// Regex the whole value has to match
+    pub pattern: Option<String>,
+    /// Masked in the table of `envx variables` unless `--reveal` is given,
+    /// and only printed by `envx get var` when the output isn't a terminal
+    #[serde(default)]
+    pub secret: bool,
+    pub description: Option<String>,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+/// What `run` and `shell` do when the variables don't match the schema
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Validation {
+    /// Refuse to start
+    #[default]
+    Error,
+    /// Print the problems and start anyway
+    Warn,
+    /// Don't validate
+    Off,
+}
+
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub key: String,
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+impl Display for VariableType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            VariableType::String => "string",
+            VariableType::Int => "int",
+            VariableType::Url => "url",
+            VariableType::Bool => "bool",
+            VariableType::Json => "json",
+            VariableType::Port => "port",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl VariableType {
+    fn check(&self, value: &str) -> Result<(), String> {
+        match self {
+            VariableType::String => Ok(()),
+            VariableType::Int => value
+                .parse::<i64>()
+                .map(|_| ())
+                .map_err(|_| "expected an integer".into()),
+            VariableType::Url => url::Url::parse(value)
+                .map(|_| ())
+                .map_err(|e| format!("expected a URL ({})", e)),
+            VariableType::Bool => match value.to_lowercase().as_str() {
+                "true" | "false" | "1" | "0" | "yes" | "no" => Ok(()),
+                _ => Err("expected true, false, 1, 0, yes or no".into()),
+            },
+            VariableType::Json => {
+                serde_json::from_str::<serde_json::Value>(value)
+                    .map(|_| ())
+                    .map_err(|e| format!("expected JSON ({})", e))
+            }
+            VariableType::Port => match value.parse::<u16>() {
+                Ok(port) if port > 0 => Ok(()),
+                _ => Err("expected a port between 1 and 65535".into()),
+            },
+        }
+    }
+}
+
+impl VariableSchema {
+    /// Plain required variable, used for the `required = [...]` shorthand
+    pub fn required() -> Self {
+        Self {
+            kind: VariableType::String,
+            required: true,
+            pattern: None,
+            secret: false,
+            description: None,
+        }
+    }
+
+    /// Check a value against the schema, `None` if the variable is unset
+    pub fn validate(&self, value: Option<&str>) -> Result<Option<String>> {
+        let Some(value) = value else {
+            return Ok(self.required.then(|| "required but not set".into()));
+        };
+
+        if let Err(message) = self.kind.check(value) {
+            return Ok(Some(message));
+        }
+
+        if let Some(pattern) = &self.pattern {
+            let regex = Regex::new(&format!("^(?:{})$", pattern))
+                .with_context(|| format!("Invalid pattern '{}'", pattern))?;
+            if !regex.is_match(value) {
+                return Ok(Some(format!("does not match {}", pattern)));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(toml: &str) -> VariableSchema {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn problem(kind: VariableType, value: &str) -> Option<String> {
+        VariableSchema {
+            kind,
+            ..VariableSchema::required()
+        }
+        .validate(Some(value))
+        .unwrap()
+    }
+
+    #[test]
+    fn checks_types() {
+        let valid = [
+            (VariableType::String, "anything"),
+            (VariableType::Int, "-42"),
+            (VariableType::Url, "postgres://db:5432/app"),
+            (VariableType::Bool, "Yes"),
+            (VariableType::Json, r#"{"a": [1]}"#),
+            (VariableType::Port, "65535"),
+        ];
+        for (kind, value) in valid {
+            assert_eq!(problem(kind, value), None, "{} {}", kind, value);
+        }
+
+        let invalid = [
+            (VariableType::Int, "4.2"),
+            (VariableType::Url, "localhost"),
+            (VariableType::Bool, "maybe"),
+            (VariableType::Json, "{"),
+            (VariableType::Port, "0"),
+            (VariableType::Port, "65536"),
+        ];
+        for (kind, value) in invalid {
+            assert!(problem(kind, value).is_some(), "{} {}", kind, value);
+        }
+    }
+
+    #[test]
+    fn matches_the_whole_value() {
+        let schema = schema(r#"pattern = "debug|info""#);
+        assert_eq!(schema.validate(Some("info")).unwrap(), None);
+        assert!(schema.validate(Some("information")).unwrap().is_some());
+        assert!(schema.validate(Some("xdebug")).unwrap().is_some());
+    }
+
+    #[test]
+    fn fails_on_invalid_patterns() {
+        assert!(schema(r#"pattern = "(""#).validate(Some("x")).is_err());
+    }
+
+    #[test]
+    fn only_complains_about_missing_required_variables() {
+        assert!(schema("").validate(None).unwrap().is_some());
+        assert_eq!(schema("required = false").validate(None).unwrap(), None);
+    }
+
+    #[test]
+    fn parses_the_schema() {
+        let schema = schema(
+            r#"
+            type = "url"
+            secret = true
+            description = "Primary database"
+            "#,
+        );
+        assert_eq!(schema.kind, VariableType::Url);
+        assert!(schema.required);
+        assert!(schema.secret);
+        assert!(toml::from_str::<VariableSchema>("typ = \"url\"").is_err());
+        assert!(toml::from_str::<VariableSchema>("type = \"uuid\"").is_err());
+    }
+}
@@ -1,14 +1,464 @@
+// User settings, stored under `settings` in config.json
+//
+// Every setting is listed in `REGISTRY`, which `envx config get/set/unset/list`
+// use to validate values and show defaults.
+
+use super::config::get_config;
+use super::duration::{format_duration, parse_duration};
+use super::env;
+use anyhow::{anyhow, bail, Context, Result};
+use envx::connection::MAX_RETRY_ATTEMPTS;
+use envx::RetryPolicy;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::IsTerminal;
+use std::time::Duration;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Settings {
+    #[serde(default)]
     pub warn_on_short_passwords: bool,
+    /// Projects that refuse unsigned or badly signed variables
+    #[serde(default)]
+    pub require_signatures: Vec<String>,
+    /// How long an unlocked key stays unlocked, e.g. `8h`
+    #[serde(default = "default_session_ttl")]
+    pub session_ttl: String,
+    /// Output format used when `--output` isn't given
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    #[serde(default)]
+    pub color: ColorMode,
+    /// How often a failed request is retried
+    #[serde(default = "default_retry_attempts")]
+    pub retry_attempts: u32,
+    /// Delay before the first retry, doubled after every attempt up to a
+    /// minute
+    #[serde(default = "default_retry_backoff")]
+    pub retry_backoff: String,
+    /// Environment from .envx.toml used when none is selected, if the file defines it
+    #[serde(default)]
+    pub default_environment: Option<String>,
+    /// Mask variable values in output unless `--reveal` is given
+    #[serde(default = "default_redact")]
+    pub redact: bool,
+    /// Lock the keys held by `envx agent` after this long without use
+    #[serde(default = "default_agent_idle_timeout")]
+    pub agent_idle_timeout: String,
+    /// Where passphrases of unlocked keys are stored
+    #[serde(default)]
+    pub keyring_backend: KeyringBackend,
 }
 
-impl Settings {
-    pub fn default() -> Self {
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    Plain,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Colour when writing to a terminal and `NO_COLOR` isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyringBackend {
+    /// The first of system, keyutils and file that works
+    #[default]
+    Auto,
+    /// Secret Service, the macOS Keychain or the Windows Credential Manager
+    System,
+    /// The Linux kernel keyring, cleared on reboot
+    Keyutils,
+    /// A file encrypted with a master passphrase
+    File,
+}
+
+impl std::fmt::Display for KeyringBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyringBackend::Auto => write!(f, "auto"),
+            KeyringBackend::System => write!(f, "system keyring"),
+            KeyringBackend::Keyutils => write!(f, "kernel keyring"),
+            KeyringBackend::File => write!(f, "encrypted file"),
+        }
+    }
+}
+
+/// Turn colours on or off as configured with the `color` setting
+pub fn apply_color() {
+    let settings = get_config()
+        .and_then(|c| c.get_settings())
+        .unwrap_or_default();
+    let enabled = match settings.color {
+        ColorMode::Auto => auto_color(),
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+    };
+    colored::control::set_override(enabled);
+    console::set_colors_enabled(enabled);
+    console::set_colors_enabled_stderr(enabled);
+}
+
+/// Colour on a terminal, unless `NO_COLOR` is set, or anywhere with
+/// `CLICOLOR_FORCE` (see no-color.org and bixense.com/clicolors)
+fn auto_color() -> bool {
+    if env::var("NO_COLOR").is_some() {
+        return false;
+    }
+    if env::var("CLICOLOR_FORCE").is_some_and(|v| v != "0") {
+        return true;
+    }
+    std::io::stdout().is_terminal()
+}
+
+fn default_session_ttl() -> String {
+    "30d".into()
+}
+
+fn default_retry_attempts() -> u32 {
+    RetryPolicy::default().attempts
+}
+
+fn default_retry_backoff() -> String {
+    format_duration(RetryPolicy::default().backoff)
+}
+
+fn default_redact() -> bool {
+    true
+}
+
+fn default_agent_idle_timeout() -> String {
+    "1h".into()
+}
+
+impl Default for Settings {
+    fn default() -> Self {
         Settings {
             warn_on_short_passwords: false,
+            require_signatures: vec![],
+            session_ttl: default_session_ttl(),
+            output_format: OutputFormat::default(),
+            color: ColorMode::default(),
+            retry_attempts: default_retry_attempts(),
+            retry_backoff: default_retry_backoff(),
+            default_environment: None,
+            redact: default_redact(),
+            agent_idle_timeout: default_agent_idle_timeout(),
+            keyring_backend: KeyringBackend::default(),
         }
     }
 }
+
+impl Settings {
+    pub fn session_ttl(&self) -> Result<Duration> {
+        parse_duration(&self.session_ttl).context("Invalid session_ttl")
+    }
+
+    pub fn retry_backoff(&self) -> Result<Duration> {
+        parse_duration(&self.retry_backoff).context("Invalid retry_backoff")
+    }
+
+    /// How to retry failed requests, larger values in config.json are
+    /// clamped to `MAX_RETRY_ATTEMPTS` and a minute
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::new(
+            self.retry_attempts,
+            self.retry_backoff().unwrap_or_default(),
+        )
+    }
+
+    pub fn agent_idle_timeout(&self) -> Result<Duration> {
+        parse_duration(&self.agent_idle_timeout)
+            .context("Invalid agent_idle_timeout")
+    }
+
+    /// The value of a setting as JSON
+    pub fn get(&self, name: &str) -> Result<Value> {
+        let setting = Setting::find(name)?;
+        let settings = serde_json::to_value(self)?;
+        Ok(settings.get(setting.name).cloned().unwrap_or(Value::Null))
+    }
+
+    /// Parse and validate `value`, then set it
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        let setting = Setting::find(name)?;
+        let value = setting
+            .kind
+            .parse(value)
+            .with_context(|| format!("Invalid value for {}", setting.name))?;
+        self.replace(setting.name, Some(value))
+    }
+
+    /// Reset a setting to its default
+    pub fn unset(&mut self, name: &str) -> Result<()> {
+        let setting = Setting::find(name)?;
+        self.replace(setting.name, None)
+    }
+
+    fn replace(&mut self, name: &str, value: Option<Value>) -> Result<()> {
+        let mut settings = serde_json::to_value(&*self)?;
+        let map = settings
+            .as_object_mut()
+            .context("Settings are not an object")?;
+        match value {
+            Some(value) => map.insert(name.to_string(), value),
+            None => map.remove(name),
+        };
+        *self = serde_json::from_value(settings)
+            .with_context(|| format!("Invalid value for {}", name))?;
+        Ok(())
+    }
+}
+
+/// The type of a setting, used to parse values given on the command line
+#[derive(Debug, Clone, Copy)]
+pub enum SettingKind {
+    Bool,
+    /// A non-negative integer up to `max`
+    Integer {
+        max: u32,
+    },
+    Duration,
+    /// Optional free-form text, an empty value clears it
+    Text,
+    /// One of the given values
+    Choice(&'static [&'static str]),
+    /// Managed by a dedicated command
+    List(&'static str),
+}
+
+impl SettingKind {
+    fn parse(&self, value: &str) -> Result<Value> {
+        match self {
+            SettingKind::Bool => match value.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok(Value::Bool(true)),
+                "false" | "no" | "off" | "0" => Ok(Value::Bool(false)),
+                _ => bail!("expected true or false"),
+            },
+            SettingKind::Integer { max } => {
+                let value = value
+                    .parse::<u32>()
+                    .context("expected a positive integer")?;
+                if value > *max {
+                    bail!("expected at most {}", max);
+                }
+                Ok(Value::from(value))
+            }
+            SettingKind::Duration => {
+                parse_duration(value)?;
+                Ok(Value::String(value.trim().to_lowercase()))
+            }
+            SettingKind::Text if value.is_empty() => Ok(Value::Null),
+            SettingKind::Text => Ok(Value::String(value.to_string())),
+            SettingKind::Choice(choices) => {
+                let value = value.to_lowercase();
+                if choices.contains(&value.as_str()) {
+                    Ok(Value::String(value))
+                } else {
+                    bail!("expected one of {}", choices.join(", "))
+                }
+            }
+            SettingKind::List(command) => {
+                Err(anyhow!("use `{}` to change this setting", command))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SettingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingKind::Bool => write!(f, "bool"),
+            SettingKind::Integer { .. } => write!(f, "integer"),
+            SettingKind::Duration => write!(f, "duration"),
+            SettingKind::Text => write!(f, "text"),
+            SettingKind::Choice(choices) => write!(f, "{}", choices.join("|")),
+            SettingKind::List(_) => write!(f, "list"),
+        }
+    }
+}
+
+pub struct Setting {
+    pub name: &'static str,
+    pub kind: SettingKind,
+    pub description: &'static str,
+}
+
+pub const REGISTRY: &[Setting] = &[
+    Setting {
+        name: "session_ttl",
+        kind: SettingKind::Duration,
+        description: "How long an unlocked key stays unlocked",
+    },
+    Setting {
+        name: "output_format",
+        kind: SettingKind::Choice(&["table", "json", "yaml", "plain"]),
+        description: "Output format used when --output isn't given",
+    },
+    Setting {
+        name: "color",
+        kind: SettingKind::Choice(&["auto", "always", "never"]),
+        description: "When to colour the output",
+    },
+    Setting {
+        name: "retry_attempts",
+        kind: SettingKind::Integer {
+            max: MAX_RETRY_ATTEMPTS,
+        },
+        description: "How often a failed request to the server is retried",
+    },
+    Setting {
+        name: "retry_backoff",
+        kind: SettingKind::Duration,
+        description:
+            "Delay before the first retry, doubled after every retry up to 1m",
+    },
+    Setting {
+        name: "default_environment",
+        kind: SettingKind::Text,
+        description: "Environment from .envx.toml used when none is selected, if the file defines it",
+    },
+    Setting {
+        name: "redact",
+        kind: SettingKind::Bool,
+        description: "Mask variable values unless --reveal is given",
+    },
+    Setting {
+        name: "agent_idle_timeout",
+        kind: SettingKind::Duration,
+        description: "Lock the keys held by envx agent after this long unused",
+    },
+    Setting {
+        name: "keyring_backend",
+        kind: SettingKind::Choice(&["auto", "system", "keyutils", "file"]),
+        description: "Where passphrases of unlocked keys are stored",
+    },
+    Setting {
+        name: "warn_on_short_passwords",
+        kind: SettingKind::Bool,
+        description: "Warn when a passphrase is shorter than recommended",
+    },
+    Setting {
+        name: "require_signatures",
+        kind: SettingKind::List("envx config set require-signatures"),
+        description: "Projects that refuse unsigned variables",
+    },
+];
+
+impl Setting {
+    /// Look up a setting, accepting `-` in place of `_`
+    pub fn find(name: &str) -> Result<&'static Setting> {
+        let name = name.replace('-', "_");
+        REGISTRY.iter().find(|s| s.name == name).with_context(|| {
+            format!(
+                "Unknown setting '{}', see `envx config list` for all settings",
+                name
+            )
+        })
+    }
+
+    /// The default value as JSON
+    pub fn default_value(&self) -> Value {
+        serde_json::to_value(Settings::default())
+            .ok()
+            .and_then(|s| s.get(self.name).cloned())
+            .unwrap_or(Value::Null)
+    }
+}
+
+/// Show a setting value the way it would be given on the command line
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "(unset)".into(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) if items.is_empty() => "(none)".into(),
+        Value::Array(items) => items
+            .iter()
+            .map(display_value)
+            .collect::<Vec<String>>()
+            .join(", "),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bools() {
+        for value in ["true", "Yes", "ON", "1"] {
+            assert_eq!(SettingKind::Bool.parse(value).unwrap(), true);
+        }
+        for value in ["false", "no", "Off", "0"] {
+            assert_eq!(SettingKind::Bool.parse(value).unwrap(), false);
+        }
+        assert!(SettingKind::Bool.parse("maybe").is_err());
+    }
+
+    #[test]
+    fn limits_integers() {
+        let kind = SettingKind::Integer { max: 10 };
+        assert_eq!(kind.parse("0").unwrap(), 0);
+        assert_eq!(kind.parse("10").unwrap(), 10);
+        assert!(kind.parse("11").is_err());
+        assert!(kind.parse("-1").is_err());
+        assert!(kind.parse("ten").is_err());
+    }
+
+    #[test]
+    fn normalizes_durations() {
+        assert_eq!(SettingKind::Duration.parse(" 8H ").unwrap(), "8h");
+        assert!(SettingKind::Duration.parse("8 hours").is_err());
+    }
+
+    #[test]
+    fn clears_empty_text() {
+        assert_eq!(SettingKind::Text.parse("").unwrap(), Value::Null);
+        assert_eq!(SettingKind::Text.parse("Dev").unwrap(), "Dev");
+    }
+
+    #[test]
+    fn accepts_only_listed_choices() {
+        let kind = SettingKind::Choice(&["auto", "never"]);
+        assert_eq!(kind.parse("NEVER").unwrap(), "never");
+        assert!(kind.parse("sometimes").is_err());
+    }
+
+    #[test]
+    fn refuses_list_settings() {
+        let kind = SettingKind::List("envx config set require-signatures");
+        assert!(kind.parse("project").is_err());
+    }
+
+    #[test]
+    fn rejects_too_many_retry_attempts() {
+        let mut settings = Settings::default();
+        settings.set("retry-attempts", "5").unwrap();
+        assert_eq!(settings.retry_attempts, 5);
+        assert!(settings
+            .set("retry_attempts", &(MAX_RETRY_ATTEMPTS + 1).to_string())
+            .is_err());
+        assert_eq!(settings.retry_attempts, 5);
+    }
+}
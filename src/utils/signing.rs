@@ -0,0 +1,52 @@
+// Who wrote a variable record, see `envx::signing` for how signatures are
+// checked
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+pub use envx::Signer;
+
+/// Who signed a record, with invalid signatures in red
+pub fn describe(signer: &Signer) -> String {
+    match signer {
+        Signer::Invalid => signer.to_string().red().to_string(),
+        signer => signer.to_string(),
+    }
+}
+
+/// Warn about records that aren't signed by a trusted member, or refuse
+/// them entirely if signatures are required for the project
+pub fn enforce_signatures<'a>(
+    project_id: &str,
+    records: impl Iterator<Item = (&'a str, &'a Signer)>,
+    require_signatures: bool,
+) -> Result<()> {
+    let unverified = records
+        .filter(|(_, signer)| !signer.is_verified())
+        .map(|(key, signer)| format!("  {} - {}", key, describe(signer)))
+        .collect::<Vec<String>>();
+
+    if unverified.is_empty() {
+        return Ok(());
+    }
+
+    if require_signatures {
+        bail!(
+            "{}\n{}",
+            format!(
+                "Project {} requires signed variables, refusing records:",
+                project_id
+            )
+            .red(),
+            unverified.join("\n")
+        );
+    }
+
+    eprintln!(
+        "{}\n{}",
+        "Warning: some variables are not signed by a trusted project member:"
+            .yellow(),
+        unverified.join("\n")
+    );
+
+    Ok(())
+}
@@ -5,7 +5,8 @@ use std::collections::BTreeMap;
 
 const FIRST_COLUMN_MIN_WIDTH: usize = 10;
 const MIN_BOX_WIDTH: usize = 20;
-const MAX_BOX_WIDTH: usize = 80;
+/// Used when the width of the terminal is unknown
+const DEFAULT_BOX_WIDTH: usize = 80;
 
 pub struct Table {
     name: String,
@@ -17,13 +18,14 @@ impl Table {
         Self { name, rows }
     }
     pub fn get_string(&self) -> Result<String> {
-        let title_str = self.name.to_string();
+        let max_box_width = terminal_width().max(MIN_BOX_WIDTH);
+        let title_str = fit(&self.name, max_box_width - 2);
         let title_width = console::measure_text_width(title_str.as_str());
 
         let max_right_content = self
             .rows
-            .iter()
-            .flat_map(|(_, content)| {
+            .values()
+            .flat_map(|content| {
                 content
                     .split('\n')
                     .map(console::measure_text_width)
@@ -32,6 +34,7 @@ impl Table {
             .max()
             .unwrap_or(0);
         let max_right_content = std::cmp::max(max_right_content, title_width);
+        // Long names are cut so the values keep most of the width
         let first_column_width = std::cmp::max(
             FIRST_COLUMN_MIN_WIDTH,
             self.rows
@@ -39,7 +42,8 @@ impl Table {
                 .map(|name| console::measure_text_width(name))
                 .max()
                 .unwrap_or(0),
-        );
+        )
+        .min(std::cmp::max(FIRST_COLUMN_MIN_WIDTH, max_box_width / 3));
 
         let edge = format!("{} ", box_drawing::double::VERTICAL);
         let edge_width = console::measure_text_width(edge.as_str());
@@ -53,7 +57,7 @@ impl Table {
             + first_column_width
             + middle_padding_width
             + max_right_content)
-            .clamp(MIN_BOX_WIDTH, MAX_BOX_WIDTH);
+            .clamp(MIN_BOX_WIDTH, max_box_width);
 
         let second_column_width = box_width
             - (edge_width * 2)
@@ -156,7 +160,7 @@ fn print_row(
         "{}{}{}{}{}",
         left_edge.cyan().dimmed(),
         console::pad_str(
-            title,
+            &fit(title, first_column_width),
             first_column_width,
             console::Alignment::Left,
             None
@@ -196,3 +200,18 @@ fn print_row(
 
     output
 }
+
+fn terminal_width() -> usize {
+    console::Term::stdout()
+        .size_checked()
+        .map(|(_, columns)| columns as usize)
+        .unwrap_or(DEFAULT_BOX_WIDTH)
+}
+
+/// Cut `text` to `width` columns, marking that it was cut
+fn fit(text: &str, width: usize) -> String {
+    if console::measure_text_width(text) <= width {
+        return text.to_string();
+    }
+    console::truncate_str(text, width, "…").to_string()
+}
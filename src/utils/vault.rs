@@ -0,0 +1,319 @@
+// The key vault, one directory per key holding
+//
+//   private.key     armored secret key
+//   public.key      armored public key
+//   revocation.asc  revocation certificate
+//   CHECKSUMS       HMAC-SHA256 of the files above, keyed with the salt of
+//                   the config
+//
+// Directories are created 0700 and files 0600. Secret material in files
+// other users can read is refused unless ENVX_INSECURE_KEYS is set, and
+// every file is checked against CHECKSUMS when it is read. The salt lives
+// in config.json, outside the vault, so replacing a key file and its
+// checksum takes write access to both. Files written before checksums
+// existed are recorded the first time they are read, with a warning.
+
+use super::atomic::{create_private_dir, write_atomic_private};
+use super::config;
+use super::env::{self, ENVX_INSECURE_KEYS};
+use super::rpgp::get_vault_location;
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const PRIVATE_KEY: &str = "private.key";
+pub const PUBLIC_KEY: &str = "public.key";
+pub const REVOCATION_CERTIFICATE: &str = "revocation.asc";
+pub const MANIFEST: &str = "CHECKSUMS";
+
+/// Plain SHA-256 manifest of earlier versions, removed once replaced
+const LEGACY_MANIFEST: &str = "SHA256SUMS";
+
+/// Files only the owner may be able to read
+const SECRET_FILES: [&str; 2] = [PRIVATE_KEY, REVOCATION_CERTIFICATE];
+
+/// The directory of a key in the vault
+pub fn key_dir(fingerprint: &str) -> Result<PathBuf> {
+    Ok(get_vault_location()?.join(fingerprint))
+}
+
+/// Write a file of a key and record its checksum
+pub fn write(fingerprint: &str, name: &str, contents: &[u8]) -> Result<()> {
+    create_private_dir(&get_vault_location()?)?;
+    let dir = key_dir(fingerprint)?;
+    create_private_dir(&dir)?;
+
+    write_atomic_private(&dir.join(name), contents)
+        .with_context(|| format!("Failed to write {}", name))?;
+
+    let salt = config::salt()?;
+    let mut manifest = read_manifest(&dir)?;
+    manifest.insert(
+        name.to_string(),
+        checksum(&salt, fingerprint, name, contents),
+    );
+    write_manifest(&dir, &manifest)
+}
+
+/// Read a file of a key, checking its permissions and checksum
+pub fn read(fingerprint: &str, name: &str) -> Result<String> {
+    let dir = key_dir(fingerprint)?;
+    let path = dir.join(name);
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    if SECRET_FILES.contains(&name) {
+        check_permissions(&path)?;
+    }
+
+    let salt = config::salt()?;
+    if !check_checksum(&salt, fingerprint, name, contents.as_bytes())? {
+        eprintln!(
+            "{} {} had no checksum, trusting it as it is now",
+            "Warning:".yellow(),
+            path.display()
+        );
+        if let Err(e) =
+            record_checksums(fingerprint, &[name.to_string()], &salt)
+        {
+            eprintln!("{} {:#}", "Warning:".yellow(), e);
+        }
+    }
+
+    Ok(contents)
+}
+
+/// HMAC-SHA256 of a file of a key, keyed with the salt of the config
+fn checksum(
+    salt: &str,
+    fingerprint: &str,
+    name: &str,
+    contents: &[u8],
+) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(salt.as_bytes())
+        .expect("HMAC takes keys of any length");
+    mac.update(format!("{}:{}:", fingerprint, name).as_bytes());
+    mac.update(contents);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Compare a file with the manifest
+///
+/// Returns `false` if the file has no checksum, fails if it doesn't match.
+fn check_checksum(
+    salt: &str,
+    fingerprint: &str,
+    name: &str,
+    contents: &[u8],
+) -> Result<bool> {
+    let dir = key_dir(fingerprint)?;
+    let Some(expected) = read_manifest(&dir)?.remove(name) else {
+        return Ok(false);
+    };
+
+    if expected != checksum(salt, fingerprint, name, contents) {
+        bail!(
+            "{} does not match its checksum in {}, it may have been tampered with",
+            dir.join(name).display(),
+            dir.join(MANIFEST).display()
+        );
+    }
+    Ok(true)
+}
+
+/// The mode of a file if users other than the owner can access it
+pub fn insecure_mode(path: &Path) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+        (mode & 0o077 != 0).then_some(mode)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Refuse files other users can read, unless ENVX_INSECURE_KEYS is set
+fn check_permissions(path: &Path) -> Result<()> {
+    let Some(mode) = insecure_mode(path) else {
+        return Ok(());
+    };
+    if env::var(ENVX_INSECURE_KEYS).is_some() {
+        return Ok(());
+    }
+
+    bail!(
+        "{} can be read by other users (mode {:o}), run `chmod 600 {}` or `envx doctor --fix`, or set {}=1 to use it anyway",
+        path.display(),
+        mode,
+        path.display(),
+        ENVX_INSECURE_KEYS
+    );
+}
+
+/// Make a file readable and writable by the owner only
+pub fn restrict_permissions(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .with_context(|| {
+                format!("Failed to set permissions of {}", path.display())
+            })?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// The files of a key that are secret, exist and can be read by others
+pub fn insecure_files(fingerprint: &str) -> Result<Vec<PathBuf>> {
+    let dir = key_dir(fingerprint)?;
+    Ok(SECRET_FILES
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| insecure_mode(path).is_some())
+        .collect())
+}
+
+/// Check every file of a key against the manifest
+///
+/// Returns the files that have no checksum, fails if any file doesn't match
+/// its checksum.
+pub fn verify(fingerprint: &str) -> Result<Vec<String>> {
+    let dir = key_dir(fingerprint)?;
+    let salt = config::salt()?;
+    let mut unrecorded = vec![];
+    for name in [PRIVATE_KEY, PUBLIC_KEY, REVOCATION_CERTIFICATE] {
+        let path = dir.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        let contents = fs::read(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if !check_checksum(&salt, fingerprint, name, &contents)? {
+            unrecorded.push(name.to_string());
+        }
+    }
+    Ok(unrecorded)
+}
+
+/// Record the checksums of files of a key as they are now, for files
+/// written before the vault had a manifest
+pub fn record_checksums(
+    fingerprint: &str,
+    names: &[String],
+    salt: &str,
+) -> Result<()> {
+    let dir = key_dir(fingerprint)?;
+    let mut manifest = read_manifest(&dir)?;
+    for name in names {
+        let path = dir.join(name);
+        let contents = fs::read(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        manifest
+            .insert(name.clone(), checksum(salt, fingerprint, name, &contents));
+    }
+    write_manifest(&dir, &manifest)
+}
+
+/// Record the checksums of every key file in the vault that has none
+///
+/// Used when moving keys over from the legacy location, before the config
+/// that holds `salt` is in place.
+pub fn record_missing_checksums(salt: &str) -> Result<()> {
+    let vault = get_vault_location()?;
+    if !vault.is_dir() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(&vault).context("Failed to read key vault")? {
+        let dir = entry.context("Failed to read key vault")?.path();
+        let Some(fingerprint) = dir.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if !dir.is_dir() {
+            continue;
+        }
+
+        let manifest = read_manifest(&dir)?;
+        let names = [PRIVATE_KEY, PUBLIC_KEY, REVOCATION_CERTIFICATE]
+            .iter()
+            .filter(|name| {
+                dir.join(name).is_file() && !manifest.contains_key(**name)
+            })
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        if !names.is_empty() {
+            record_checksums(fingerprint, &names, salt)?;
+        }
+    }
+    Ok(())
+}
+
+/// File name to checksum
+fn read_manifest(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let path = dir.join(MANIFEST);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (checksum, name) =
+                line.split_once("  ").with_context(|| {
+                    format!("Invalid line in {}", path.display())
+                })?;
+            Ok((name.to_string(), checksum.to_string()))
+        })
+        .collect()
+}
+
+fn write_manifest(
+    dir: &Path,
+    manifest: &BTreeMap<String, String>,
+) -> Result<()> {
+    let contents = manifest
+        .iter()
+        .map(|(name, checksum)| format!("{}  {}\n", checksum, name))
+        .collect::<String>();
+    write_atomic_private(&dir.join(MANIFEST), contents.as_bytes())
+        .context("Failed to write vault manifest")?;
+
+    let legacy = dir.join(LEGACY_MANIFEST);
+    if legacy.exists() {
+        fs::remove_file(&legacy).with_context(|| {
+            format!("Failed to remove {}", legacy.display())
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_are_keyed_with_the_salt() {
+        let digest = checksum("aa", "FP", PRIVATE_KEY, b"key");
+        assert_eq!(digest, checksum("aa", "FP", PRIVATE_KEY, b"key"));
+        assert_ne!(digest, checksum("bb", "FP", PRIVATE_KEY, b"key"));
+        assert_ne!(digest, checksum("aa", "FP", PUBLIC_KEY, b"key"));
+        assert_ne!(digest, checksum("aa", "OTHER", PRIVATE_KEY, b"key"));
+    }
+}